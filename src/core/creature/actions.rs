@@ -8,7 +8,7 @@
 /// This is also used by the main game engine to percieve what the player is doing
 /// at any given moment in time and make choices on what to do based on it
 ///
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Actions {
   // Creature moved
   Move,
@@ -24,10 +24,20 @@ pub enum Actions {
   Blink,
   // Creature Talked
   Talk,
+  // Creature attacked something in melee
+  Attack,
   // Creature died
   Die,
   // Creature picked up an item
   Pickup,
+  // Creature dropped an item
+  Drop,
+  // Creature opened a door
+  Open,
+  // Creature closed a door
+  Close,
+  // Creature threw or fired something
+  Throw,
   // Unknown action (Creature did something weird)
   Unknown
 }
\ No newline at end of file
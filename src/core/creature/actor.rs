@@ -4,13 +4,14 @@
 
 use core::world::dungeon::map::Pos;
 use core::renderer::{Renderable, RGB};
+use core::save::StaticStr;
 
 ///
 /// Actor struct. Holds necessary properties that extend from `Renderable`
 ///
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub struct Actor {
-  name: &'static str,
+  name: StaticStr,
   glyph: char,
   pub pos: Pos,
   pub prev_pos: Pos,
@@ -56,7 +57,7 @@ impl Actor {
   #[inline]
   pub fn new(name: &'static str, glyph: char, pos: Pos, fg: RGB, bg: RGB) -> Self {
     Actor {
-      name: name,
+      name: StaticStr(name),
       glyph: glyph, 
       pos: pos,
       prev_pos: pos,
@@ -97,7 +98,7 @@ impl Renderable for Actor {
 
   #[inline]
   fn get_id(&self) -> &'static str {
-    self.name.clone()
+    self.name.0
   }
 
   #[inline]
@@ -117,7 +118,7 @@ impl Renderable for Actor {
 
   #[inline]
   fn set_id(&mut self, name: &'static str) {
-    self.name = name;
+    self.name = StaticStr(name);
   }
 
 }
\ No newline at end of file
@@ -1,18 +1,18 @@
 extern crate rand;
 
-use self::rand::Rng;
+use self::rand::{Rng, StdRng};
 
-use core::world::dungeon::map::{self, Measurable, Pos, tile, Tile};
+use core::world::dungeon::map::{path, Measurable, Pos, tile};
 
-use super::{AI, RANDOM_TRIES};
-use core::creature::{Actions, Creature, Actor, Stats};
+use super::{AI, Context, Intent, RANDOM_TRIES, SavedAI};
+use core::creature::{Actions, Actor, Stats};
 
 const BLINK_RANGE : isize = 8;
 
 ///
 /// BlinkAI makes monster teleport around the map periodically
 ///
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub struct BlinkAI;
 
 impl BlinkAI {
@@ -20,8 +20,7 @@ impl BlinkAI {
   ///
   /// Return a `Pos` to a random tile nearby
   /// 
-  pub fn blink(&mut self, me: &mut Actor) -> Pos {
-    let mut rng = rand::thread_rng();
+  pub fn blink(&mut self, me: &mut Actor, rng: &mut StdRng) -> Pos {
     let mut pos = me.pos.clone();
     pos += Pos::new(rng.gen_range(-BLINK_RANGE, BLINK_RANGE), rng.gen_range(-BLINK_RANGE, BLINK_RANGE));
     return pos;
@@ -41,9 +40,9 @@ impl AI for BlinkAI {
   ///
   /// Walk around randomly, and occasionally blink
   ///
-  fn take_turn(&mut self, map: &map::Grid<Tile>, _player: &Creature, me: &mut Actor, _stats: &mut Stats) -> Actions {
+  fn take_turn(&mut self, ctx: &Context, me: &mut Actor, _stats: &mut Stats, _paths: &mut path::Paths, rng: &mut StdRng) -> Intent {
 
-    let mut rng = rand::thread_rng();
+    let map = ctx.map;
 
     me.prev_pos = me.pos.clone();
     
@@ -69,7 +68,7 @@ impl AI for BlinkAI {
         4 => pos.y -= 1,
         // Blink
         5 => {
-          pos = self.blink(me);
+          pos = self.blink(me, rng);
           state = Actions::Blink;
         },
         // If the rng breaks something is very wrong
@@ -91,7 +90,10 @@ impl AI for BlinkAI {
         pos.x = (map.width() - 1) as isize;
       }
 
-      if tile::walkable(&map[pos.x as usize][pos.y as usize]) {
+      // Blinking into something is a telefrag waiting to happen, so only walking gets to bump into things
+      let blocked = state == Actions::Blink && ctx.occupancy.is_occupied(pos);
+
+      if tile::walkable(&map[pos.x as usize][pos.y as usize]) && !blocked {
         break;
       // If we make a lot of attempts and still can't find a tile::walkable tile, just stop
       } else if count > RANDOM_TRIES {
@@ -105,7 +107,7 @@ impl AI for BlinkAI {
     // Update creature position
     me.pos = pos;
 
-    return state;
+    return state.into();
 
   }
 
@@ -116,4 +118,11 @@ impl AI for BlinkAI {
     Box::new((*self).clone())
   }
 
+  ///
+  /// Allow Box<AI> saving
+  ///
+  fn save(&self) -> SavedAI {
+    SavedAI::Blink(self.clone())
+  }
+
 }
\ No newline at end of file
@@ -33,13 +33,137 @@ pub use self::talker::TalkerAI;
 pub mod tracker;
 pub use self::tracker::TrackerAI;
 
-use core::world::dungeon::map::{self, Measurable, Tile};
+extern crate rand;
+use self::rand::StdRng;
+
+extern crate serde;
+use self::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use core::world::dungeon::map::{self, fov, path, Measurable, Pos, tile, Tile};
 
 use core::creature::{Actions, Creature, Actor, Stats};
+use core::creature::faction::FACTIONS;
+use core::item::Item;
 
 // As AI becomes more complex it might be a good idea to put 'general' functions in this file to help guide and maintain
 // certain 'motifs' of AI such as boundary checking, creature overlap checking, etc.
 
+///
+/// What a creature knows about another creature it can see
+///
+#[derive(Clone, Debug)]
+pub struct Sighting {
+  // Which creature on the floor it is, for `Target::Creature`
+  pub id: usize,
+  pub name: &'static str,
+  pub pos: Pos,
+  pub scent: tile::Scent,
+  pub faction: &'static str,
+  pub state: Actions
+}
+
+///
+/// What an `Intent` is aimed at
+///
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Target {
+  Player,
+  // A creature on the floor, by the id in its `Sighting`
+  Creature(usize)
+}
+
+///
+/// What an AI wants to do with its turn, which the world then carries out
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Intent {
+  // The AI has already done it to its own `Actor`, like moving or blinking, and the world just checks over it
+  Act(Actions),
+  // Attack something standing next to the creature
+  Attack(Target),
+  // Pick up whatever is lying where the creature stands
+  Pickup,
+  // Take a step away from something
+  Flee(Target)
+}
+
+impl From<Actions> for Intent {
+  fn from(action: Actions) -> Intent {
+    Intent::Act(action)
+  }
+}
+
+///
+/// Everything an AI gets to look at on its turn
+///
+/// All of it is read only. AIs change the world by changing their own `Actor` or by handing back an `Intent`
+///
+pub struct Context<'a> {
+  // Faction of the creature taking its turn
+  pub faction: &'static str,
+  // The floor, which also holds the sound and scents on every tile
+  pub map: &'a map::Grid<Tile>,
+  pub player: &'a Creature,
+  // Other creatures that can be seen, not including the player
+  pub creatures: Vec<Sighting>,
+  // Items lying on the floor
+  pub items: &'a [Item],
+  // Who is standing where, including the creature itself
+  pub occupancy: &'a map::Occupancy,
+  // Everything the creature can see right now
+  pub vision: fov::Vision,
+  // Ticks of time since the world was made
+  pub turn: usize,
+  // How deep the floor is
+  pub depth: usize
+}
+
+impl<'a> Context<'a> {
+
+  ///
+  /// Where a target is, `None` if it can't be seen
+  ///
+  pub fn locate(&self, target: Target) -> Option<Pos> {
+    match target {
+      Target::Player => if self.vision.can_see(self.player.actor.pos) { Some(self.player.actor.pos) } else { None },
+      Target::Creature(id) => self.creatures.iter().find(|sighting| sighting.id == id).map(|sighting| sighting.pos)
+    }
+  }
+
+  ///
+  /// Check if the creature attacks a target on sight
+  ///
+  pub fn is_hostile(&self, target: Target) -> bool {
+    match target {
+      Target::Player => FACTIONS.is_hostile(self.faction, self.player.faction.0),
+      Target::Creature(id) => self.creatures.iter()
+        .find(|sighting| sighting.id == id)
+        .map_or(false, |sighting| FACTIONS.is_hostile(self.faction, sighting.faction))
+    }
+  }
+
+  ///
+  /// Get the closest thing in sight that the creature is hostile to, along with where it is
+  ///
+  pub fn nearest_hostile(&self, from: Pos) -> Option<(Target, Pos)> {
+
+    let player = self.locate(Target::Player).map(|pos| (Target::Player, pos));
+    let creatures = self.creatures.iter().map(|sighting| (Target::Creature(sighting.id), sighting.pos));
+
+    let mut nearest : Option<(Target, Pos)> = None;
+    for (target, pos) in player.into_iter().chain(creatures).filter(|&(target, _)| self.is_hostile(target)) {
+      // ^ is overridden to be the distance formula, this isn't xor
+      if nearest.map_or(true, |(_, best)| (from ^ pos) < (from ^ best)) {
+        nearest = Some((target, pos));
+      }
+    }
+
+    return nearest;
+
+  }
+
+}
+
 ///
 /// Represents basic actions AI can take in the game
 /// 
@@ -59,14 +183,22 @@ use core::creature::{Actions, Creature, Actor, Stats};
 pub trait AI {
 
   ///
-  /// Make the AI take it's turn based on map, player, and itself
+  /// Make the AI take it's turn based on what it knows about the floor, and itself
   /// 
   /// NOTE: AIs are basically just state deciders at this point but more complex AIs have to be state machines in of themselves
-  /// in order to create complex behaviors. At some point they should take in a state, a vector of all creatures on the floor
-  /// (for monster infighting, fight-flight) and maybe even some sort of "mood" though that would be a part of the `Creature`. I am
+  /// in order to create complex behaviors. `ctx` gives them the creatures they can see (for monster infighting, fight-flight),
+  /// and maybe at some point they should get some sort of "mood" though that would be a part of the `Creature`. I am
   /// completely considering adding randomized personalities to monsters to create even more combinations of behavior.
   ///
-  fn take_turn(&mut self, map: &map::Grid<Tile>, player: &Creature, me: &mut Actor, stats: &mut Stats) -> Actions;
+  /// AIs must only roll dice with `rng`, which is the world's seeded RNG, so that monster behaviour can be reproduced
+  ///
+  /// AIs that want to go somewhere should ask `path` for the way, either with `path::next_step()` or by following
+  /// one of the floor's flow maps in `paths`, walking with the costs in `stats.movement`
+  ///
+  /// `ctx.vision` is everything the creature can see right now. AIs should only react to what's in it, or to what they
+  /// can smell or hear, and never to things they have no way of knowing about
+  ///
+  fn take_turn(&mut self, ctx: &Context, me: &mut Actor, stats: &mut Stats, paths: &mut path::Paths, rng: &mut StdRng) -> Intent;
 
   ///
   /// Determine if the AI has gone out of bounds with respect to the given map
@@ -81,6 +213,11 @@ pub trait AI {
   /// 
   fn box_clone(&self) -> Box<dyn AI>;
 
+  ///
+  /// Allow boxed trait objects to be saved
+  ///
+  fn save(&self) -> SavedAI;
+
 }
 
 ///
@@ -93,4 +230,60 @@ impl Clone for Box<dyn AI> {
   fn clone(&self) -> Box<dyn AI> {
     self.box_clone()
   }
+}
+
+///
+/// Every concrete AI, so that boxed AIs can be written to and read from save files
+///
+/// Serde has no idea what is behind a `Box<dyn AI>`, so each AI hands back a copy of itself wrapped in this
+/// enum via `save()`, much like how `box_clone()` works. When loading, the enum is unwrapped back into a box.
+///
+/// The downside is the same as `box_clone()`: new AIs must be added here and implement a near identical `save()`
+///
+#[derive(Clone, Serialize, Deserialize)]
+pub enum SavedAI {
+  Blink(BlinkAI),
+  Player(PlayerAI),
+  Simple(SimpleAI),
+  Smeller(SmellerAI),
+  Talker(TalkerAI),
+  Tracker(TrackerAI)
+}
+
+impl SavedAI {
+
+  ///
+  /// Turn a saved AI back into a boxed trait object
+  ///
+  pub fn into_box(self) -> Box<dyn AI> {
+    match self {
+      SavedAI::Blink(ai)   => Box::new(ai),
+      SavedAI::Player(ai)  => Box::new(ai),
+      SavedAI::Simple(ai)  => Box::new(ai),
+      SavedAI::Smeller(ai) => Box::new(ai),
+      SavedAI::Talker(ai)  => Box::new(ai),
+      SavedAI::Tracker(ai) => Box::new(ai)
+    }
+  }
+
+}
+
+///
+/// Allow AI trait objects to be serialized via save()
+///
+/// Serde already knows how to serialize a `Box<T>` if it can serialize `T`, so this covers `Box<dyn AI>`
+///
+impl Serialize for dyn AI {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.save().serialize(serializer)
+  }
+}
+
+///
+/// Allow boxed trait objects to be deserialized via `SavedAI`
+///
+impl<'de> Deserialize<'de> for Box<dyn AI> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Box<dyn AI>, D::Error> {
+    SavedAI::deserialize(deserializer).map(SavedAI::into_box)
+  }
 }
\ No newline at end of file
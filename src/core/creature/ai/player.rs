@@ -1,12 +1,16 @@
-use core::world::dungeon::map::{self, Tile};
+extern crate rand;
 
-use super::AI;
-use core::creature::{Actions, Creature, Actor, Stats};
+use self::rand::StdRng;
+
+use core::world::dungeon::map::path;
+
+use super::{AI, Context, Intent, SavedAI};
+use core::creature::{Actions, Actor, Stats};
 
 ///
 /// PlayerAI does nothing
 ///
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub struct PlayerAI;
 
 impl PlayerAI {
@@ -20,9 +24,9 @@ impl AI for PlayerAI {
   ///
   /// Do nothing
   ///
-  fn take_turn(&mut self, _map: &map::Grid<Tile>, _player: &Creature, _me: &mut Actor, _stats: &mut Stats) -> Actions {
+  fn take_turn(&mut self, _ctx: &Context, _me: &mut Actor, _stats: &mut Stats, _paths: &mut path::Paths, _rng: &mut StdRng) -> Intent {
     //me.prev_pos = me.pos.clone();
-    Actions::Unknown
+    Actions::Unknown.into()
   }
 
   ///
@@ -32,4 +36,11 @@ impl AI for PlayerAI {
     Box::new((*self).clone())
   }
 
+  ///
+  /// Allow Box<AI> saving
+  ///
+  fn save(&self) -> SavedAI {
+    SavedAI::Player(self.clone())
+  }
+
 }
\ No newline at end of file
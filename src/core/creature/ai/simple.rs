@@ -1,19 +1,25 @@
 extern crate rand;
 
-use self::rand::Rng;
+use self::rand::{Rng, StdRng};
 
-use core::world::dungeon::map::{self, tile, Tile};
+use core::world::dungeon::map::path;
 
-use super::{AI, RANDOM_TRIES};
-use core::creature::{Actions, Creature, Actor, Stats};
+use super::{AI, Context, Intent, SavedAI, Target};
+use core::creature::{Actions, Actor, Stats};
+
+// How close the player has to get before a simple creature runs off
+const FLEE_DISTANCE : f32 = 3.0;
+
+// Chance out of this for a simple creature to pick up something it's standing on
+const PICKUP_CHANCE : usize = 4;
 
 ///
-/// SimpleAI is literally just an AI that walks around randomly
+/// SimpleAI is literally just an AI that walks around randomly, runs away from the player and picks up shiny things
 ///
 /// NOTE: There is really no intention to keep this AI around... Maybe as a confused AI?
 /// Definitely will be replaced/refactored.
 ///
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub struct SimpleAI;
 
 impl SimpleAI {
@@ -27,58 +33,27 @@ impl AI for SimpleAI {
   ///
   /// Walk around randomly
   ///
-  fn take_turn(&mut self, map: &map::Grid<Tile>, _player: &Creature, me: &mut Actor, _stats: &mut Stats) -> Actions {
-
-    let mut rng = rand::thread_rng();
-    let mut dice : usize;
-    let mut state = Actions::Move;
-    
-    let mut x : usize;
-    let mut y : usize;
-    let mut count : usize = 0;
+  fn take_turn(&mut self, ctx: &Context, me: &mut Actor, _stats: &mut Stats, _paths: &mut path::Paths, rng: &mut StdRng) -> Intent {
 
     me.prev_pos = me.pos.clone();
 
-    // Try to move around until we find a good spot to land
-    loop {
-
-      count += 1;
-      x = me.pos.x as usize;
-      y = me.pos.y as usize;
-      dice = rng.gen_range(1, 5);
-
-      if x == 0 || y == 0 {
-        x += 1;
-        y += 1;
-      }
-
-      // Match dice for movement
-      match dice {
-        1 => x += 1,
-        2 => x -= 1,
-        3 => y += 1,
-        4 => y -= 1,
-        _ => unreachable!("SimpleAI - Unreachable dice state reached in movement")
-      }
-
-      // Since the only thing this thing can do is move, there is no need to match the dice again to determine state
-      
-      // If we find a good tile, great, otherwise keep trying until we get tired of it
-      if tile::walkable(&map[x][y]) {
-        break;
-      } else if count > RANDOM_TRIES {
-        x = me.pos.x as usize;
-        y = me.pos.y as usize;
-        state = Actions::Wait;
-        break; 
-      }
+    // ^ is overridden to be the distance formula, this isn't xor
+    if ctx.locate(Target::Player).map_or(false, |pos| (me.pos ^ pos) < FLEE_DISTANCE) {
+      return Intent::Flee(Target::Player);
+    }
 
+    if ctx.items.iter().any(|item| item.pos == me.pos) && rng.gen_range(0, PICKUP_CHANCE) == 0 {
+      return Intent::Pickup;
     }
-    
-    me.pos.x = x as isize;
-    me.pos.y = y as isize;
 
-    return state;
+    // Step onto any walkable tile around us, or wait if we're boxed in
+    match path::wander(ctx.map, me.pos, rng) {
+      Some(pos) => {
+        me.pos = pos;
+        Actions::Move.into()
+      },
+      None => Actions::Wait.into()
+    }
 
   }
 
@@ -89,4 +64,11 @@ impl AI for SimpleAI {
     Box::new((*self).clone())
   }
 
+  ///
+  /// Allow Box<AI> saving
+  ///
+  fn save(&self) -> SavedAI {
+    SavedAI::Simple(self.clone())
+  }
+
 }
\ No newline at end of file
@@ -1,18 +1,20 @@
 extern crate rand;
 
-use self::rand::Rng;
+use self::rand::StdRng;
 
-use core::world::dungeon::map::{self, Measurable, tile, Tile};
+use std::cmp;
 
-use super::{AI, RANDOM_TRIES};
-use core::creature::{Actions, Creature, Actor, Stats};
+use core::world::dungeon::map::{path, Measurable, Pos};
+
+use super::{AI, Context, Intent, SavedAI};
+use core::creature::{Actions, Actor, Stats};
 
 ///
-/// SmellerAI is an AI that follows insect smells
+/// SmellerAI is an AI that follows insect smells, and eats the insects at the end of them
 ///
 /// NOTE: This is a proof of concept AI
 ///
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub struct SmellerAI;
 
 impl SmellerAI {
@@ -26,30 +28,34 @@ impl AI for SmellerAI {
   ///
   /// Walk around randomly until it picks up a scent
   ///
-  fn take_turn(&mut self, map: &map::Grid<Tile>, _player: &Creature, me: &mut Actor, _stats: &mut Stats) -> Actions {
+  fn take_turn(&mut self, ctx: &Context, me: &mut Actor, stats: &mut Stats, _paths: &mut path::Paths, rng: &mut StdRng) -> Intent {
 
+    let map = ctx.map;
     let mut state = Actions::Wait;
     
-    let mut x = me.pos.x as usize;
-    let mut y = me.pos.y as usize;
+    let x = me.pos.x as usize;
+    let y = me.pos.y as usize;
 
     let mut tx : isize = -1;
     let mut ty : isize = -1;
     let mut scent_val : isize = 0;
 
     // Represents how far the ai can smell
-    let mut scent_range = 2;
+    let scent_range = stats.smell_radius();
 
     me.prev_pos = me.pos.clone();
+
+    // Snap up anything hostile in reach. ^ is overridden to be the distance formula, this isn't xor
+    if let Some((target, _)) = ctx.nearest_hostile(me.pos).filter(|&(_, pos)| (me.pos ^ pos) < 2.0) {
+      return Intent::Attack(target);
+    }
   
     // Avoid OOB Errors
-    if x <= 2 || x > map.width() - 2 || y <= 2 || y > map.height() - 2 {
-      // NOTE: scent_range one 1 has weird behaviors...
-      scent_range = 1;
-    }
+    let (min_x, max_x) = (x.saturating_sub(scent_range), cmp::min(x + scent_range, map.width() - 1));
+    let (min_y, max_y) = (y.saturating_sub(scent_range), cmp::min(y + scent_range, map.height() - 1));
 
-    for sx in x-scent_range..x+scent_range {
-      for sy in y-scent_range..y+scent_range {
+    for sx in min_x..max_x + 1 {
+      for sy in min_y..max_y + 1 {
         // Scents[1] refers to the insectoid smell via the c-like enum
         // not the best solution
         if map[sx][sy].scents[1].val as isize > scent_val && (sx, sy) != (x, y) { 
@@ -60,85 +66,25 @@ impl AI for SmellerAI {
       }
     }
 
-    // If a scent has been picked up behave like a tracker and move towards that tile
-    if scent_val > 0 && scent_val < 80{
-
-      // Move x
-      if x < tx as usize {
-        x += 1;
-        state = Actions::Move;
-      } else if x > tx as usize {
-        x -= 1;
-        state = Actions::Move;
-      }
+    // If a scent has been picked up, find the way to the tile it's strongest on
+    if scent_val > 0 && scent_val < 80 {
 
-      // Check
-      if !tile::walkable(&map[x][y]) {
-        x = tx as usize;
+      if let Some(pos) = path::next_step(map, me.pos, Pos::new(tx, ty), &stats.movement) {
+        me.pos = pos;
         state = Actions::Move;
       }
 
-      // Move y
-      if y < ty as usize{
-        y += 1;
-      } else if y > ty as usize {
-        y -= 1;
-      }
-
-      // Check
-      if !tile::walkable(&map[x][y]) {
-        y = ty as usize;
-        state = Actions::Move;
-      }
-
-      me.pos.x = x as isize;
-      me.pos.y = y as isize;
-      
     } else {
-      
-      // Otherwise behave like a simple ai and walk around randomly
-      loop {
 
-        let mut rng = rand::thread_rng();
-        let dice : usize;
+      // Otherwise behave like a simple ai and walk around randomly
+      if let Some(pos) = path::wander(map, me.pos, rng) {
+        me.pos = pos;
         state = Actions::Move;
-        
-        let mut count : usize = 0;
-
-        count += 1;
-        x = me.pos.x as usize;
-        y = me.pos.y as usize;
-        dice = rng.gen_range(1, 5);
-
-        // Match dice for movement
-        match dice {
-          1 => x += 1,
-          2 => x -= 1,
-          3 => y += 1,
-          4 => y -= 1,
-          _ => unreachable!("SimpleAI - Unreachable dice state reached in movement")
-        }
-
-        // Since the only thing this thing can do is move, there is no need to match the dice again to determine state
-        
-        // If we find a good tile, great, otherwise keep trying until we get tired of it
-        if tile::walkable(&map[x][y]) {
-          break;
-        } else if count > RANDOM_TRIES {
-          x = me.pos.x as usize;
-          y = me.pos.y as usize;
-          state = Actions::Wait;
-          break; 
-        }
-
       }
-      
-      me.pos.x = x as isize;
-      me.pos.y = y as isize;
 
     }
 
-    return state;
+    return state.into();
 
   }
 
@@ -149,4 +95,11 @@ impl AI for SmellerAI {
     Box::new((*self).clone())
   }
 
+  ///
+  /// Allow Box<AI> saving
+  ///
+  fn save(&self) -> SavedAI {
+    SavedAI::Smeller(self.clone())
+  }
+
 }
\ No newline at end of file
@@ -1,18 +1,18 @@
 extern crate rand;
 
-use self::rand::Rng;
+use self::rand::{Rng, StdRng};
 
 use core::log;
-use core::world::dungeon::map::{self, Tile};
+use core::world::dungeon::map::path;
 use core::renderer::Renderable;
 
-use super::{AI, TALK_DISTANCE};
-use core::creature::{Actions, Creature, Actor, Stats};
+use super::{AI, Context, Intent, TALK_DISTANCE, SavedAI};
+use core::creature::{Actions, Actor, Stats};
 
 ///
 /// AI that talks to the player
 ///
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub struct TalkerAI;
 
 impl TalkerAI {
@@ -27,18 +27,17 @@ impl AI for TalkerAI {
   ///
   /// Talk to player if near
   ///
-  fn take_turn(&mut self, _map: &map::Grid<Tile>, player: &Creature, me: &mut Actor, _stats: &mut Stats) -> Actions {
+  fn take_turn(&mut self, ctx: &Context, me: &mut Actor, _stats: &mut Stats, _paths: &mut path::Paths, rng: &mut StdRng) -> Intent {
 
     let mut state = Actions::Wait;
 
     // ^ is overridden to be the distance formula, this isn't xor
-    let distance = me.pos ^ player.actor.pos;
+    let distance = me.pos ^ ctx.player.actor.pos;
 
     me.prev_pos = me.pos.clone();
 
     if distance < TALK_DISTANCE {
 
-      let mut rng = rand::thread_rng();
       let dice : i32 = rng.gen_range(1, 15);
 
       // Match dice for voiceline
@@ -59,7 +58,7 @@ impl AI for TalkerAI {
       
     }
 
-    return state;
+    return state.into();
 
   }
 
@@ -70,4 +69,11 @@ impl AI for TalkerAI {
     Box::new((*self).clone())
   }
 
+  ///
+  /// Allow Box<AI> saving
+  ///
+  fn save(&self) -> SavedAI {
+    SavedAI::Talker(self.clone())
+  }
+
 }
\ No newline at end of file
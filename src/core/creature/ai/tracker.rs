@@ -1,73 +1,111 @@
-use core::world::dungeon::map::{self, tile, Tile};
+extern crate rand;
 
-use super::AI;
-use core::creature::{Actions, Creature, Actor, Stats};
+use self::rand::StdRng;
+
+use core::world::dungeon::map::{path, Measurable, Pos};
+
+use super::{AI, Context, Intent, SavedAI, Target};
+use core::creature::{Actions, Actor, Stats};
+use core::creature::faction::FACTIONS;
 
 ///
-/// AI that tracks player
+/// AI that hunts down whatever its faction is hostile to
 ///
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
-pub struct TrackerAI;
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct TrackerAI {
+  // Where its prey was last seen, so that ducking out of sight doesn't shake the tracker off right away
+  last_seen: Option<Pos>
+}
 
 impl TrackerAI {
   #[inline]
   pub fn new() -> Self {
-    TrackerAI {}
+    TrackerAI { last_seen: None }
+  }
+
+  ///
+  /// Get a step towards the tile within smelling distance where the scents the faction tracks are strongest, if it's
+  /// stronger than here
+  ///
+  fn sniff(ctx: &Context, pos: Pos, stats: &Stats) -> Option<Pos> {
+
+    let map = ctx.map;
+    let tracks = FACTIONS.tracks(ctx.faction);
+
+    if tracks.is_empty() {
+      return None;
+    }
+
+    let strength = |p: Pos| -> usize {
+      tracks.iter().map(|scent| map[p].scents[scent.clone() as usize].val as usize).max().unwrap_or(0)
+    };
+
+    let radius = stats.smell_radius() as isize;
+
+    let mut best = (pos, strength(pos));
+    for dx in -radius..radius + 1 {
+      for dy in -radius..radius + 1 {
+        let next = Pos::new(pos.x + dx, pos.y + dy);
+        if next.x < 0 || next.y < 0 || next.x as usize >= map.width() || next.y as usize >= map.height() {
+          continue;
+        }
+        if stats.movement.cost(&map[next]).is_some() && strength(next) > best.1 {
+          best = (next, strength(next));
+        }
+      }
+    }
+
+    if best.0 != pos { path::next_step(map, pos, best.0, &stats.movement) } else { None }
+
   }
 }
 
 impl AI for TrackerAI {
   
   ///
-  /// Track player and follow if near
+  /// Chase the nearest hostile thing in sight, attacking it once caught up
   ///
-  fn take_turn(&mut self, map: &map::Grid<Tile>, player: &Creature, me: &mut Actor, _stats: &mut Stats) -> Actions {
-
-    let mut state = Actions::Wait;
+  /// The way to the player comes from the floor's flow map towards them, so trackers go around walls instead of
+  /// getting stuck on them. Other prey is found with `path::next_step()`. Once nothing is in sight, the tracker heads
+  /// to where its prey was last seen, and after that follows whatever scents its faction tracks
+  ///
+  fn take_turn(&mut self, ctx: &Context, me: &mut Actor, stats: &mut Stats, paths: &mut path::Paths, _rng: &mut StdRng) -> Intent {
 
-    // ^ is overridden to be the distance formula, this isn't xor
-    let distance = me.pos ^ player.actor.pos;
-    let mut x = me.pos.x;
-    let mut y = me.pos.y;
+    let map = ctx.map;
 
     me.prev_pos = me.pos.clone();
 
-    if distance < 20.0 && distance > 2.00 {
-
-      // Move x
-      if x < player.actor.pos.x {
-        x += 1;
-        state = Actions::Move;
-      } else if x > player.actor.pos.x {
-        x -= 1;
-        state = Actions::Move;
+    let step = if let Some((target, prey_pos)) = ctx.nearest_hostile(me.pos) {
+      self.last_seen = Some(prey_pos);
+      // Caught up. ^ is overridden to be the distance formula, this isn't xor
+      if (me.pos ^ prey_pos) < 2.0 {
+        return Intent::Attack(target);
       }
-
-      // Check
-      if !tile::walkable(&map[x as usize][y as usize]) {
-        x = me.pos.x;
+      match target {
+        Target::Player => paths.flow(map, path::Goal::Player(prey_pos), &stats.movement).next_step(map, me.pos, &stats.movement),
+        Target::Creature(_) => path::next_step(map, me.pos, prey_pos, &stats.movement)
       }
-
-      // Move y
-      if y < player.actor.pos.y {
-        y += 1;
-        state = Actions::Move;
-      } else if y > player.actor.pos.y {
-        y -= 1;
-        state = Actions::Move;
+    } else {
+      match self.last_seen {
+        Some(pos) if pos != me.pos => path::next_step(map, me.pos, pos, &stats.movement),
+        _ => {
+          self.last_seen = None;
+          TrackerAI::sniff(ctx, me.pos, stats)
+        }
       }
-
-      // Check
-      if !tile::walkable(&map[x as usize][y as usize]) {
-        y = me.pos.y
+    };
+
+    match step {
+      Some(pos) => {
+        me.pos = pos;
+        Actions::Move.into()
+      },
+      // Lost the trail
+      None => {
+        self.last_seen = None;
+        Actions::Wait.into()
       }
-
     }
-    
-    me.pos.x = x as isize;
-    me.pos.y = y as isize;
-
-    return state;
 
   }
 
@@ -78,4 +116,11 @@ impl AI for TrackerAI {
     Box::new((*self).clone())
   }
 
+  ///
+  /// Allow Box<AI> saving
+  ///
+  fn save(&self) -> SavedAI {
+    SavedAI::Tracker(self.clone())
+  }
+
 }
\ No newline at end of file
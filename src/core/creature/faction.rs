@@ -0,0 +1,188 @@
+//!
+//! Factions, and who is hostile to who, loaded from `config/factions.yml`
+//!
+//! Every creature belongs to a faction. The file maps faction names to what they think of everyone else:
+//!
+//! ```yaml
+//! felines:
+//!   hostile: [insects, player]
+//!   tracks: [insectoid]
+//! ```
+//!
+//! `hostile` lists the factions that members attack on sight. Hostility only goes one way, so cats hunting ants doesn't
+//! make ants fight back unless the ants say so too.
+//! `tracks` lists the scents that hunters follow when nothing hostile is in sight.
+//!
+//! The `player` faction has to be defined, since it's what the player belongs to.
+//!
+
+// Serde
+extern crate serde_yaml;
+
+// Read files
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, prelude::*};
+
+use core::creature::registry::{scent_from_str, SCENTS};
+use core::world::dungeon::map::tile;
+
+///
+/// Configuration
+///
+
+// Where factions are defined
+pub const FACTION_PATH : &str = "config/factions.yml";
+
+// Faction the player belongs to
+pub const PLAYER_FACTION : &str = "player";
+
+// Every faction. Loaded the first time it's used, which is when monsters get loaded
+lazy_static! {
+  pub static ref FACTIONS : Factions = Factions::load(FACTION_PATH).unwrap_or_else(|err| panic!("{}", err));
+}
+
+///
+/// Things that can go wrong while loading factions
+///
+#[derive(Debug)]
+pub enum FactionError {
+  // Couldn't read the file
+  Io(io::Error),
+  // File isn't valid YAML, or has unknown keys
+  Format(serde_yaml::Error),
+  // A faction is hostile to a faction that doesn't exist
+  Unknown(String, String),
+  // Scent string doesn't match any `tile::Scent`
+  Scent(String, String),
+  // There's no faction for the player
+  NoPlayer
+}
+
+impl fmt::Display for FactionError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      FactionError::Io(ref err) =>
+        write!(fmt, "{}: could not read faction file: {}", FACTION_PATH, err),
+      FactionError::Format(ref err) =>
+        write!(fmt, "{}: bad faction definition: {}", FACTION_PATH, err),
+      FactionError::Unknown(ref faction, ref other) =>
+        write!(fmt, "{}: faction `{}` is hostile to unknown faction `{}`", FACTION_PATH, faction, other),
+      FactionError::Scent(ref faction, ref scent) =>
+        write!(fmt, "{}: faction `{}` tracks unknown scent `{}`, expected one of {}", FACTION_PATH, faction, scent, SCENTS.join(", ")),
+      FactionError::NoPlayer =>
+        write!(fmt, "{}: there is no `{}` faction", FACTION_PATH, PLAYER_FACTION)
+    }
+  }
+}
+
+///
+/// Faction as written in the file
+///
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FactionDef {
+  #[serde(default)]
+  hostile: Vec<String>,
+  #[serde(default)]
+  tracks: Vec<String>
+}
+
+///
+/// Every loaded faction
+///
+pub struct Factions {
+  // Sorted by name
+  names: Vec<String>,
+  // `hostile[a][b]` is whether faction `a` attacks faction `b`
+  hostile: Vec<Vec<bool>>,
+  // Scents each faction's hunters follow
+  tracks: Vec<Vec<tile::Scent>>
+}
+
+impl Factions {
+
+  ///
+  /// Get where a faction is in the matrix
+  ///
+  fn index(&self, name: &str) -> Option<usize> {
+    self.names.iter().position(|faction| faction == name)
+  }
+
+  ///
+  /// Check if a faction exists
+  ///
+  pub fn exists(&self, name: &str) -> bool {
+    self.index(name).is_some()
+  }
+
+  ///
+  /// Check if members of `from` attack members of `to`
+  ///
+  /// Unknown factions are at peace with everyone
+  ///
+  pub fn is_hostile(&self, from: &str, to: &str) -> bool {
+    match (self.index(from), self.index(to)) {
+      (Some(a), Some(b)) => self.hostile[a][b],
+      _ => false
+    }
+  }
+
+  ///
+  /// Get the scents a faction's hunters follow
+  ///
+  pub fn tracks(&self, name: &str) -> &[tile::Scent] {
+    match self.index(name) {
+      Some(idx) => &self.tracks[idx],
+      None => &[]
+    }
+  }
+
+  ///
+  /// Load the faction file into `Factions`
+  ///
+  pub fn load(path: &str) -> Result<Factions, FactionError> {
+
+    // Load file to String
+    let mut contents = String::new();
+    File::open(path)
+      .and_then(|mut file| file.read_to_string(&mut contents))
+      .map_err(FactionError::Io)?;
+
+    let defs : BTreeMap<String, FactionDef> = serde_yaml::from_str(&contents).map_err(FactionError::Format)?;
+
+    let names : Vec<String> = defs.keys().cloned().collect();
+
+    if !names.iter().any(|name| name == PLAYER_FACTION) {
+      return Err(FactionError::NoPlayer);
+    }
+
+    let mut hostile = vec![vec![false; names.len()]; names.len()];
+    let mut tracks = vec![];
+
+    for (a, (name, def)) in defs.into_iter().enumerate() {
+
+      for other in def.hostile {
+        match names.iter().position(|faction| *faction == other) {
+          Some(b) => hostile[a][b] = true,
+          None => return Err(FactionError::Unknown(name, other))
+        }
+      }
+
+      let mut scents = vec![];
+      for scent in def.tracks {
+        match scent_from_str(&scent) {
+          Some(scent) => scents.push(scent),
+          None => return Err(FactionError::Scent(name, scent))
+        }
+      }
+      tracks.push(scents);
+
+    }
+
+    return Ok(Factions { names, hostile, tracks });
+
+  }
+
+}
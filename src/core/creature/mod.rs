@@ -16,22 +16,53 @@ pub use self::actor::Actor;
 pub mod stats;
 pub use self::stats::Stats;
 
+pub mod registry;
+pub use self::registry::MONSTERS;
+
+pub mod faction;
+pub use self::faction::FACTIONS;
+
+pub mod status;
+pub use self::status::{Effect, Statuses};
+
+pub mod origin;
+pub use self::origin::Character;
+
 mod object_tests;
 
-use core::item::Item;
+extern crate rand;
+use self::rand::StdRng;
+
+use core::item::{Item, ItemProperty};
 use core::renderer::RGB;
-use core::time::Time;
-use core::world::dungeon::map::{self, Pos, Tile};
+use core::save::StaticStr;
+use core::time::{self, Energy, Time};
+use core::world::dungeon::map::{path, Pos};
+
+///
+/// Configuration
+///
+
+// Most stacks of items a creature can hold, one for every letter on the inventory screen
+pub const INVENTORY_SLOTS : usize = 26;
 
 ///
 /// Creature holds a `Actor` and an `AI`, basically a package that we can create monsters from
 ///
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Creature {
   pub actor: Actor,
   pub stats: Stats,
   pub state: Actions,
 
+  // Energy left to act with
+  #[serde(default)]
+  pub energy: Energy,
+
+  // Who the creature sides with, see `faction`. Creatures with no faction are at peace with everyone
+  #[serde(default)]
+  pub faction: StaticStr,
+
   // Q: Wait, an AI trait object is clonable?
   // A: A *Boxed* AI trait object is clonable, as pointers to objects are clonable
   pub ai: Box<dyn ai::AI>,
@@ -40,7 +71,11 @@ pub struct Creature {
   // Hold money
   pub wallet: f32,
   // Hold other items
-  pub inventory: Vec<Item>
+  pub inventory: Vec<Item>,
+
+  // Effects that last for a while, like bleeding or being confused
+  #[serde(default)]
+  pub statuses: Statuses
 }
 
 impl Creature {
@@ -71,9 +106,58 @@ impl Creature {
       actor: Actor::new(name, glyph, pos, fg, bg),
       stats,
       state: Actions::Unknown,
+      energy: Energy::default(),
+      faction: StaticStr::default(),
       ai: Box::new(ai),
       wallet: 0.0,
-      inventory: vec![]
+      inventory: vec![],
+      statuses: Statuses::default()
+    }
+  }
+
+  ///
+  /// Total weight of everything in the inventory
+  ///
+  pub fn carried_weight(&self) -> usize {
+    self.inventory.iter().map(|item| item.total_weight()).sum()
+  }
+
+  ///
+  /// Check if an item can be carried without going over the weight limit
+  ///
+  pub fn can_carry(&self, item: &Item) -> bool {
+    self.carried_weight() + item.total_weight() <= self.stats.carry_capacity()
+  }
+
+  ///
+  /// Check if there's a free slot in the inventory for an item, or a stack it can go on
+  ///
+  /// Money goes in the wallet, so it never needs a slot
+  ///
+  pub fn has_room(&self, item: &Item) -> bool {
+    if let ItemProperty::Money(_) = item.property {
+      return true;
+    }
+    self.inventory.len() < INVENTORY_SLOTS || self.inventory.iter().any(|held| held.stacks_with(item))
+  }
+
+  ///
+  /// How many tiles away the creature can see right now, which isn't far at all when it's blind
+  ///
+  pub fn sight_radius(&self) -> usize {
+    if self.statuses.has(Effect::Blind) {
+      return status::BLIND_RADIUS;
+    }
+    return self.stats.sight_radius();
+  }
+
+  ///
+  /// Put an item in the inventory, adding it to a stack of like items if there is one
+  ///
+  pub fn stash(&mut self, item: Item) {
+    match self.inventory.iter_mut().find(|held| held.stacks_with(&item)) {
+      Some(held) => held.quantity += item.quantity,
+      None => self.inventory.push(item)
     }
   }
 
@@ -91,8 +175,23 @@ impl Time for Creature {
   ///
   /// Essentially allows us to not need to include `AI` when we need to `take_turn()`
   ///
-  fn take_turn(&mut self, map: &map::Grid<Tile>, player: &Creature) {
-    self.state = self.ai.take_turn(map, player, &mut self.actor, &mut self.stats);
+  /// The AI only says what it wants to do, and it's up to the world to make it happen
+  ///
+  fn take_turn(&mut self, ctx: &ai::Context, paths: &mut path::Paths, rng: &mut StdRng) -> ai::Intent {
+    self.ai.take_turn(ctx, &mut self.actor, &mut self.stats, paths, rng)
+  }
+
+  ///
+  /// More agile creatures are faster, and being slowed or hasted halves or doubles that
+  ///
+  fn speed(&self) -> isize {
+    let speed = time::speed_from_agility(self.stats.agility);
+    if self.statuses.has(Effect::Slowed) {
+      return speed / 2;
+    } else if self.statuses.has(Effect::Hasted) {
+      return speed * 2;
+    }
+    return speed;
   }
 
 }
\ No newline at end of file
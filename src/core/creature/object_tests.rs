@@ -1,2 +1,88 @@
 #[cfg(test)]
-mod tests {}
+mod tests {
+
+  use core::creature::status::{Effect, Statuses, MAX_TURNS};
+
+  ///
+  /// Turns left on an effect, `None` if it isn't on
+  ///
+  fn turns(statuses: &Statuses, effect: Effect) -> Option<usize> {
+    statuses.iter().find(|status| status.effect == effect).map(|status| status.turns)
+  }
+
+  //
+  // Status effects
+  //
+
+  #[test]
+  fn test_status_add() {
+
+    // Bleeding piles up
+    let mut statuses = Statuses::default();
+    statuses.apply(Effect::Bleeding, 5);
+    statuses.apply(Effect::Bleeding, 3);
+    assert_eq!(turns(&statuses, Effect::Bleeding), Some(8));
+
+  }
+
+  #[test]
+  fn test_status_refresh() {
+
+    // Everything else lasts as long as the longest of it
+    let mut statuses = Statuses::default();
+    statuses.apply(Effect::Confused, 5);
+    statuses.apply(Effect::Confused, 3);
+    assert_eq!(turns(&statuses, Effect::Confused), Some(5));
+    statuses.apply(Effect::Confused, 9);
+    assert_eq!(turns(&statuses, Effect::Confused), Some(9));
+
+  }
+
+  #[test]
+  fn test_status_cap() {
+
+    let mut statuses = Statuses::default();
+    statuses.apply(Effect::Blind, MAX_TURNS + 10);
+    assert_eq!(turns(&statuses, Effect::Blind), Some(MAX_TURNS));
+
+    statuses.apply(Effect::Bleeding, MAX_TURNS - 1);
+    statuses.apply(Effect::Bleeding, MAX_TURNS - 1);
+    assert_eq!(turns(&statuses, Effect::Bleeding), Some(MAX_TURNS));
+
+  }
+
+  #[test]
+  fn test_status_opposites() {
+
+    // Slowed and hasted cancel out rather than both being on
+    let mut statuses = Statuses::default();
+    statuses.apply(Effect::Slowed, 5);
+    statuses.apply(Effect::Hasted, 10);
+    assert!(!statuses.has(Effect::Slowed));
+    assert!(!statuses.has(Effect::Hasted));
+
+    statuses.apply(Effect::Hasted, 10);
+    statuses.apply(Effect::Slowed, 5);
+    assert!(!statuses.has(Effect::Slowed));
+    assert!(!statuses.has(Effect::Hasted));
+
+  }
+
+  #[test]
+  fn test_status_tick() {
+
+    let mut statuses = Statuses::default();
+    statuses.apply(Effect::Bleeding, 1);
+    statuses.apply(Effect::Masked, 2);
+
+    assert_eq!(statuses.tick(), vec![Effect::Bleeding]);
+    assert!(!statuses.has(Effect::Bleeding));
+    assert_eq!(turns(&statuses, Effect::Masked), Some(1));
+
+    assert_eq!(statuses.tick(), vec![Effect::Masked]);
+    assert_eq!(statuses.iter().count(), 0);
+    assert!(statuses.tick().is_empty());
+
+  }
+
+}
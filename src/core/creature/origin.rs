@@ -0,0 +1,266 @@
+//!
+//! Races and backgrounds the player picks from when making a character, loaded from `config/races.yml` and
+//! `config/backgrounds.yml`
+//!
+//! A race gives the player their starting stats along with the scent they leave behind:
+//!
+//! ```yaml
+//! estmere:
+//!   name: Estmere
+//!   description: Sneaky and quick, they see every movement long before they are seen.
+//!   scent: feline
+//!   perception: 28
+//!   olfaction: 20
+//!   fortitude: 0
+//!   agility: 2
+//!   reason: -2
+//!   insight: 0
+//! ```
+//!
+//! A background then shifts whole groups of those stats, with `sense` going to perception and olfaction, `body` to
+//! fortitude and agility, and `mind` to reason and insight:
+//!
+//! ```yaml
+//! seer:
+//!   name: Seer
+//!   description: The tapestry of your dreams grants sight like no other.
+//!   sense: 0
+//!   body: -1
+//!   mind: 1
+//! ```
+//!
+
+// Serde
+extern crate serde;
+extern crate serde_yaml;
+use self::serde::de::DeserializeOwned;
+
+// Read files
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, prelude::*};
+
+use core::creature::Stats;
+use core::creature::registry::{scent_from_str, SCENTS};
+use core::world::dungeon::map::tile;
+
+///
+/// Configuration
+///
+
+// Where races and backgrounds are defined
+pub const RACE_PATH : &str = "config/races.yml";
+pub const BACKGROUND_PATH : &str = "config/backgrounds.yml";
+
+// Every race and background, sorted by id. Loaded the first time a character is made
+lazy_static! {
+  pub static ref RACES : Vec<Race> = load_races(RACE_PATH).unwrap_or_else(|err| panic!("{}", err));
+  pub static ref BACKGROUNDS : Vec<Background> = load_backgrounds(BACKGROUND_PATH).unwrap_or_else(|err| panic!("{}", err));
+}
+
+///
+/// Things that can go wrong while loading races or backgrounds
+///
+#[derive(Debug)]
+pub enum OriginError {
+  // Couldn't read a file
+  Io(&'static str, io::Error),
+  // File isn't valid YAML, or has unknown keys
+  Format(&'static str, serde_yaml::Error),
+  // Scent string doesn't match any `tile::Scent`
+  Scent(&'static str, String, String),
+  // There's nothing to pick from
+  Empty(&'static str)
+}
+
+impl fmt::Display for OriginError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      OriginError::Io(path, ref err) =>
+        write!(fmt, "{}: could not read file: {}", path, err),
+      OriginError::Format(path, ref err) =>
+        write!(fmt, "{}: bad definition: {}", path, err),
+      OriginError::Scent(path, ref id, ref scent) =>
+        write!(fmt, "{}: race `{}` has unknown scent `{}`, expected one of {}", path, id, scent, SCENTS.join(", ")),
+      OriginError::Empty(path) =>
+        write!(fmt, "{}: there is nothing to pick from", path)
+    }
+  }
+}
+
+///
+/// Race as written in the file
+///
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RaceDef {
+  name: String,
+  description: String,
+  scent: String,
+  perception: isize,
+  olfaction: isize,
+  fortitude: isize,
+  agility: isize,
+  reason: isize,
+  insight: isize
+}
+
+///
+/// What the player can be born as
+///
+#[derive(Clone, Debug)]
+pub struct Race {
+  pub id: String,
+  pub name: String,
+  pub description: String,
+  pub scent: tile::Scent,
+  pub perception: isize,
+  pub olfaction: isize,
+  pub fortitude: isize,
+  pub agility: isize,
+  pub reason: isize,
+  pub insight: isize
+}
+
+///
+/// What the player did before coming to the dungeon
+///
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Background {
+  // Filled in from the key the background is under
+  #[serde(skip)]
+  pub id: String,
+  pub name: String,
+  pub description: String,
+  // Added to every stat in each group
+  pub sense: isize,
+  pub body: isize,
+  pub mind: isize
+}
+
+///
+/// The race and background a player was made with, by id
+///
+/// This is all that's needed to make the same player again, so it's what saves and replays hold on to
+///
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Character {
+  pub race: String,
+  pub background: String
+}
+
+impl Default for Character {
+  fn default() -> Self {
+    Character { race: RACES[0].id.clone(), background: BACKGROUNDS[0].id.clone() }
+  }
+}
+
+impl Character {
+
+  ///
+  /// Get the character's race
+  ///
+  /// Panics if the race doesn't exist, since characters are only ever made from what's loaded
+  ///
+  pub fn race(&self) -> &'static Race {
+    RACES.iter().find(|race| race.id == self.race)
+      .unwrap_or_else(|| panic!("{}: race `{}` isn't defined", RACE_PATH, self.race))
+  }
+
+  ///
+  /// Get the character's background, with the same rules as `race()`
+  ///
+  pub fn background(&self) -> &'static Background {
+    BACKGROUNDS.iter().find(|background| background.id == self.background)
+      .unwrap_or_else(|| panic!("{}: background `{}` isn't defined", BACKGROUND_PATH, self.background))
+  }
+
+  ///
+  /// What the character is, as in "Estmere Seer"
+  ///
+  pub fn title(&self) -> String {
+    format!("{} {}", self.race().name, self.background().name)
+  }
+
+  ///
+  /// Make the stat block of the character
+  ///
+  /// Everything that isn't decided by race or background, like health, is left for whoever makes the player
+  ///
+  pub fn stats(&self, weight: usize) -> Stats {
+    let (race, background) = (self.race(), self.background());
+    Stats {
+      perception: race.perception + background.sense,
+      olfaction: race.olfaction + background.sense,
+      fortitude: race.fortitude + background.body,
+      agility: race.agility + background.body,
+      reason: race.reason + background.mind,
+      insight: race.insight + background.mind,
+      ..Stats::debug_new(weight, race.scent.clone())
+    }
+  }
+
+}
+
+///
+/// Read a file of ids to definitions
+///
+fn read<T: DeserializeOwned>(path: &'static str) -> Result<BTreeMap<String, T>, OriginError> {
+
+  // Load file to String
+  let mut contents = String::new();
+  File::open(path)
+    .and_then(|mut file| file.read_to_string(&mut contents))
+    .map_err(|err| OriginError::Io(path, err))?;
+
+  let defs : BTreeMap<String, T> = serde_yaml::from_str(&contents).map_err(|err| OriginError::Format(path, err))?;
+
+  if defs.is_empty() {
+    return Err(OriginError::Empty(path));
+  }
+
+  return Ok(defs);
+
+}
+
+///
+/// Load the race file
+///
+pub fn load_races(path: &'static str) -> Result<Vec<Race>, OriginError> {
+
+  let mut races = vec![];
+
+  for (id, def) in read::<RaceDef>(path)? {
+
+    let scent = match scent_from_str(&def.scent) {
+      Some(scent) => scent,
+      None => return Err(OriginError::Scent(path, id, def.scent))
+    };
+
+    races.push(Race {
+      id,
+      name: def.name,
+      description: def.description,
+      scent,
+      perception: def.perception,
+      olfaction: def.olfaction,
+      fortitude: def.fortitude,
+      agility: def.agility,
+      reason: def.reason,
+      insight: def.insight
+    });
+
+  }
+
+  return Ok(races);
+
+}
+
+///
+/// Load the background file
+///
+pub fn load_backgrounds(path: &'static str) -> Result<Vec<Background>, OriginError> {
+  Ok(read::<Background>(path)?.into_iter().map(|(id, background)| Background { id, ..background }).collect())
+}
@@ -0,0 +1,414 @@
+//!
+//! Registry of every monster the game knows about, loaded from the JSON files in `monsters/`
+//!
+//! Each file holds an object of monster ids to monster definitions:
+//!
+//! ```json
+//! {
+//!   "ant": {
+//!     "name": "ant",
+//!     "glyph": "a",
+//!     "fg": [255, 0, 0],
+//!     "bg": [0, 0, 0],
+//!     "scent": "insectoid",
+//!     "faction": "insects",
+//!     "ai": "simple",
+//!     "weight": 15,
+//!     "agility": 0,
+//!     "perception": 16,
+//!     "olfaction": 4,
+//!     "dread": 0,
+//!     "costs": { "water": 6 },
+//!     "spawn": [ { "depths": [0, 5], "frequency": 10 } ]
+//!   }
+//! }
+//! ```
+//!
+//! `faction` has to be one of the factions in `config/factions.yml` (see `faction`).
+//! `weight` is how heavy the monster is (which is how loud it is when it moves) and defaults to `DEFAULT_WEIGHT`.
+//! `agility` decides how fast the monster is and defaults to 0, normal speed.
+//! `perception` decides how far the monster can see, 2 points for every tile, and defaults to `DEFAULT_PERCEPTION`.
+//! `olfaction` decides how far away the monster can pick up scents the same way, and defaults to `DEFAULT_OLFACTION`.
+//! `dread` is how much sanity the player loses for having the monster in sight, and defaults to 0, not unsettling at all.
+//! `costs` overrides how much walking over water, tall grass and vines costs when the monster finds its way around
+//! (see `path::Costs`), with anything left out keeping its default. Monsters only open doors if `costs` has a `door`.
+//! `spawn` says which floors a monster shows up on (both ends of `depths` included, the first floor being 0) and how
+//! often compared to everything else that can spawn there. Monsters without any spawn rules never spawn on their own.
+//!
+//! Every file in the directory is read, so new monsters can be added by dropping in a new file without recompiling.
+//!
+
+// Serde
+extern crate serde_json;
+
+extern crate rand;
+use self::rand::{Rng, StdRng};
+
+// Read files
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
+
+use core::creature::{ai, Actions, Actor, Creature, Stats, Statuses};
+use core::creature::faction::FACTIONS;
+use core::renderer::RGB;
+use core::save::StaticStr;
+use core::time::Energy;
+use core::world::dungeon::map::{path, Pos, tile};
+
+///
+/// Configuration
+///
+
+// Where monster definitions live
+pub const MONSTER_DIR : &str = "monsters";
+
+// Weight of monsters that don't specify one
+const DEFAULT_WEIGHT : usize = 15;
+
+// Perception of monsters that don't specify one
+const DEFAULT_PERCEPTION : isize = 16;
+
+// Olfaction of monsters that don't specify one
+const DEFAULT_OLFACTION : isize = 4;
+
+// Every monster the game knows about. Loaded the first time it's used, which is when the first floor gets populated
+lazy_static! {
+  pub static ref MONSTERS : Registry = Registry::load(MONSTER_DIR).unwrap_or_else(|err| panic!("{}", err));
+}
+
+///
+/// Things that can go wrong while loading monsters
+///
+#[derive(Debug)]
+pub enum RegistryError {
+  // Couldn't read the directory or a file in it
+  Io(PathBuf, io::Error),
+  // File isn't valid JSON, or has missing or unknown keys
+  Format(PathBuf, serde_json::Error),
+  // Scent string doesn't match any `tile::Scent`
+  Scent(PathBuf, String, String),
+  // AI string doesn't match any AI
+  AI(PathBuf, String, String),
+  // Faction isn't in the faction file
+  Faction(PathBuf, String, String),
+  // Depth range is backwards
+  Depths(PathBuf, String, (usize, usize)),
+  // Two monsters share the same id
+  Duplicate(PathBuf, String)
+}
+
+impl fmt::Display for RegistryError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      RegistryError::Io(ref path, ref err) =>
+        write!(fmt, "{}: could not read monster file: {}", path.display(), err),
+      RegistryError::Format(ref path, ref err) =>
+        write!(fmt, "{}: bad monster definition: {}", path.display(), err),
+      RegistryError::Scent(ref path, ref id, ref scent) =>
+        write!(fmt, "{}: monster `{}` has unknown scent `{}`, expected one of {}", path.display(), id, scent, SCENTS.join(", ")),
+      RegistryError::AI(ref path, ref id, ref ai) =>
+        write!(fmt, "{}: monster `{}` has unknown ai `{}`, expected one of {}", path.display(), id, ai, AIS.join(", ")),
+      RegistryError::Faction(ref path, ref id, ref faction) =>
+        write!(fmt, "{}: monster `{}` is in unknown faction `{}`", path.display(), id, faction),
+      RegistryError::Depths(ref path, ref id, (min, max)) =>
+        write!(fmt, "{}: monster `{}` spawns between depths {} and {}, but the first depth must not be deeper than the second", path.display(), id, min, max),
+      RegistryError::Duplicate(ref path, ref id) =>
+        write!(fmt, "{}: monster `{}` is already defined", path.display(), id)
+    }
+  }
+}
+
+// Valid scent strings, for error messages
+pub const SCENTS : [&str; 9] = ["player", "insectoid", "canine", "feline", "reptilian", "decay", "avian", "smoke", "incense"];
+
+// Valid ai strings, for error messages
+const AIS : [&str; 5] = ["blink", "simple", "smeller", "talk", "tracker"];
+
+///
+/// Turn a scent string from a monster file into a `tile::Scent`
+///
+pub fn scent_from_str(scent: &str) -> Option<tile::Scent> {
+  match scent {
+    "player"    => Some(tile::Scent::Player),
+    "insectoid" => Some(tile::Scent::Insectoid),
+    "canine"    => Some(tile::Scent::Canine),
+    "feline"    => Some(tile::Scent::Feline),
+    "reptilian" => Some(tile::Scent::Reptilian),
+    "decay"     => Some(tile::Scent::Decay),
+    "avian"     => Some(tile::Scent::Avian),
+    "smoke"     => Some(tile::Scent::Smoke),
+    "incense"   => Some(tile::Scent::Incense),
+    _ => None
+  }
+}
+
+///
+/// Turn an ai string from a monster file into a fresh AI
+///
+/// The AI is handed back as a `SavedAI` since those can be cloned out of the registry for every new monster
+///
+fn ai_from_str(ai: &str) -> Option<ai::SavedAI> {
+  match ai {
+    "blink"   => Some(ai::SavedAI::Blink(ai::BlinkAI::new())),
+    "simple"  => Some(ai::SavedAI::Simple(ai::SimpleAI::new())),
+    "smeller" => Some(ai::SavedAI::Smeller(ai::SmellerAI::new())),
+    "talk"    => Some(ai::SavedAI::Talker(ai::TalkerAI::new())),
+    "tracker" => Some(ai::SavedAI::Tracker(ai::TrackerAI::new())),
+    _ => None
+  }
+}
+
+///
+/// When and how often a monster spawns
+///
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnRule {
+  // Shallowest and deepest floors the rule applies to
+  pub depths: (usize, usize),
+  // How likely the monster is to be picked compared to other monsters
+  pub frequency: usize
+}
+
+///
+/// Monster as written in a file
+///
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MonsterDef {
+  name: String,
+  glyph: char,
+  fg: RGB,
+  bg: RGB,
+  scent: String,
+  faction: String,
+  ai: String,
+  #[serde(default = "default_weight")]
+  weight: usize,
+  #[serde(default)]
+  agility: isize,
+  #[serde(default = "default_perception")]
+  perception: isize,
+  #[serde(default = "default_olfaction")]
+  olfaction: isize,
+  #[serde(default)]
+  dread: isize,
+  #[serde(default)]
+  costs: path::Costs,
+  #[serde(default)]
+  spawn: Vec<SpawnRule>
+}
+
+fn default_weight() -> usize {
+  DEFAULT_WEIGHT
+}
+
+fn default_perception() -> isize {
+  DEFAULT_PERCEPTION
+}
+
+fn default_olfaction() -> isize {
+  DEFAULT_OLFACTION
+}
+
+///
+/// A monster that has been checked over and is ready to be spawned
+///
+#[derive(Clone)]
+pub struct Monster {
+  pub id: String,
+  pub name: &'static str,
+  pub glyph: char,
+  pub fg: RGB,
+  pub bg: RGB,
+  pub scent: tile::Scent,
+  pub faction: &'static str,
+  pub weight: usize,
+  pub agility: isize,
+  pub perception: isize,
+  pub olfaction: isize,
+  pub dread: isize,
+  pub costs: path::Costs,
+  pub spawn: Vec<SpawnRule>,
+  ai: ai::SavedAI
+}
+
+impl Monster {
+
+  ///
+  /// How often the monster spawns at a depth, 0 being never
+  ///
+  pub fn frequency(&self, depth: usize) -> usize {
+    self.spawn.iter()
+      .filter(|rule| rule.depths.0 <= depth && depth <= rule.depths.1)
+      .map(|rule| rule.frequency)
+      .sum()
+  }
+
+  ///
+  /// Create a `Creature` of this monster at a position
+  ///
+  pub fn create(&self, pos: Pos) -> Creature {
+    Creature {
+      actor: Actor::new(self.name, self.glyph, pos, self.fg, self.bg),
+      stats: Stats {
+        agility: self.agility,
+        perception: self.perception,
+        olfaction: self.olfaction,
+        dread: self.dread,
+        movement: self.costs,
+        ..Stats::debug_new(self.weight, self.scent.clone())
+      },
+      state: Actions::Unknown,
+      energy: Energy::default(),
+      faction: StaticStr(self.faction),
+      ai: self.ai.clone().into_box(),
+      wallet: 0.0,
+      inventory: vec![],
+      statuses: Statuses::default()
+    }
+  }
+
+}
+
+///
+/// Every loaded monster
+///
+pub struct Registry {
+  // Sorted by id so that spawning is the same on every machine for a given seed
+  monsters: Vec<Monster>
+}
+
+impl Registry {
+
+  ///
+  /// Get a monster by id
+  ///
+  pub fn get(&self, id: &str) -> Option<&Monster> {
+    self.monsters.iter().find(|monster| monster.id == id)
+  }
+
+  ///
+  /// Find a monster's name or faction, so a name read back from a save can point at the registry's copy
+  ///
+  pub fn name(&self, name: &str) -> Option<&'static str> {
+    self.monsters.iter()
+      .flat_map(|monster| vec![monster.name, monster.faction])
+      .find(|&known| known == name)
+  }
+
+  ///
+  /// Pick a random monster that can spawn at `depth`, weighted by the monsters' spawn frequencies
+  ///
+  /// Returns `None` if nothing spawns that deep
+  ///
+  pub fn choose(&self, depth: usize, rng: &mut StdRng) -> Option<&Monster> {
+
+    let total : usize = self.monsters.iter().map(|monster| monster.frequency(depth)).sum();
+
+    if total == 0 {
+      return None;
+    }
+
+    let mut roll = rng.gen_range(0, total);
+    for monster in &self.monsters {
+      let frequency = monster.frequency(depth);
+      if roll < frequency {
+        return Some(monster);
+      }
+      roll -= frequency;
+    }
+
+    unreachable!();
+
+  }
+
+  ///
+  /// Load every `.json` file in a directory into a `Registry`
+  ///
+  pub fn load(dir: &str) -> Result<Registry, RegistryError> {
+
+    let dir = Path::new(dir);
+
+    // Get every json file, sorted so that load order doesn't depend on the file system
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir).map_err(|err| RegistryError::Io(dir.to_path_buf(), err))? {
+      let path = entry.map_err(|err| RegistryError::Io(dir.to_path_buf(), err))?.path();
+      if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+        paths.push(path);
+      }
+    }
+    paths.sort();
+
+    let mut monsters : Vec<Monster> = vec![];
+
+    for path in paths {
+
+      // Load file to String
+      let mut contents = String::new();
+      File::open(&path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|err| RegistryError::Io(path.clone(), err))?;
+
+      let defs : BTreeMap<String, MonsterDef> = match serde_json::from_str(&contents) {
+        Ok(defs) => defs,
+        Err(err) => return Err(RegistryError::Format(path, err))
+      };
+
+      for (id, def) in defs {
+
+        if monsters.iter().any(|monster| monster.id == id) {
+          return Err(RegistryError::Duplicate(path, id));
+        }
+
+        let scent = match scent_from_str(&def.scent) {
+          Some(scent) => scent,
+          None => return Err(RegistryError::Scent(path, id, def.scent))
+        };
+
+        if !FACTIONS.exists(&def.faction) {
+          return Err(RegistryError::Faction(path, id, def.faction));
+        }
+
+        let ai = match ai_from_str(&def.ai) {
+          Some(ai) => ai,
+          None => return Err(RegistryError::AI(path, id, def.ai))
+        };
+
+        if let Some(rule) = def.spawn.iter().find(|rule| rule.depths.0 > rule.depths.1) {
+          return Err(RegistryError::Depths(path, id, rule.depths));
+        }
+
+        monsters.push(Monster {
+          id,
+          // Actors need `&'static str` names, and monsters are loaded only once
+          name: Box::leak(def.name.into_boxed_str()),
+          glyph: def.glyph,
+          fg: def.fg,
+          bg: def.bg,
+          scent,
+          faction: Box::leak(def.faction.into_boxed_str()),
+          weight: def.weight,
+          agility: def.agility,
+          perception: def.perception,
+          olfaction: def.olfaction,
+          dread: def.dread,
+          costs: def.costs,
+          spawn: def.spawn,
+          ai
+        });
+
+      }
+
+    }
+
+    monsters.sort_by(|a, b| a.id.cmp(&b.id));
+
+    return Ok(Registry { monsters });
+
+  }
+
+}
@@ -6,10 +6,33 @@
 //! any serious attempts at what a `Creature` needs to have in order to create a fun
 //! combat system, and as of right now this is pretty much just a lot of fluff for a scent holder
 //!
+//! Anything that follows from the base attributes, like how far a creature can see or smell, is worked out here from
+//! the rules in `ideas/stats.md` rather than being kept around, so it can never fall out of step with them
+//!
+
+use std::cmp;
+
+use core::world::dungeon::map::{path, tile};
+
+// How much a creature with no fortitude can carry
+const BASE_CARRY : isize = 30;
+
+// How much more can be carried per point of fortitude
+const CARRY_PER_FORTITUDE : isize = 5;
 
-use core::world::dungeon::map::tile;
+// Points of perception needed for every tile of sight radius
+const PERCEPTION_PER_RADIUS : isize = 2;
 
-#[derive(Clone, Debug)]
+// Points of olfaction needed for every tile of smell radius
+const OLFACTION_PER_RADIUS : isize = 2;
+
+// Most sanity a creature with no insight can have
+const BASE_SANITY : isize = 50;
+
+// How much more sanity there is per point of insight
+const SANITY_PER_INSIGHT : isize = 5;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Stats {
   // Sense
   pub perception: isize,
@@ -29,7 +52,13 @@ pub struct Stats {
   // Weight
   pub weight: usize,
   // Scent
-  pub scent_type: tile::Scent
+  pub scent_type: tile::Scent,
+  // How much sanity is lost every so often by having the creature in sight
+  #[serde(default)]
+  pub dread: isize,
+  // How much walking over different tiles costs when finding a path
+  #[serde(default)]
+  pub movement: path::Costs
 }
 
 impl Stats {
@@ -37,15 +66,70 @@ impl Stats {
   ///
   /// Debug stat block
   /// 
+  /// Everything is zeroed except for health, so that creatures can survive a hit or two
+  /// 
   pub fn debug_new(weight: usize, scent_type: tile::Scent) -> Stats {
     Stats {
       perception: 0, olfaction: 0, 
       fortitude: 0, agility: 0, 
       reason: 0, insight: 0, 
-      health_points: 0, sanity_points: 0, 
+      health_points: 10, sanity_points: 0, 
       armor_value: 0, evasion_value: 0,
-      weight: weight, scent_type: scent_type
+      weight: weight, scent_type: scent_type,
+      dread: 0,
+      movement: path::Costs::default()
+    }
+  }
+
+  ///
+  /// Heaviest load that can be carried
+  ///
+  pub fn carry_capacity(&self) -> usize {
+    let capacity = BASE_CARRY + self.fortitude * CARRY_PER_FORTITUDE;
+    if capacity < 0 {
+      return 0;
+    }
+    return capacity as usize;
+  }
+
+  ///
+  /// Most sanity that can be held onto
+  ///
+  pub fn max_sanity(&self) -> isize {
+    let sanity = BASE_SANITY + self.insight * SANITY_PER_INSIGHT;
+    if sanity < 0 {
+      return 0;
     }
+    return sanity;
+  }
+
+  ///
+  /// How many tiles away things can be seen
+  ///
+  pub fn sight_radius(&self) -> usize {
+    if self.perception < 0 {
+      return 0;
+    }
+    return (self.perception / PERCEPTION_PER_RADIUS) as usize;
+  }
+
+  ///
+  /// How many tiles away scents can be picked up
+  ///
+  pub fn smell_radius(&self) -> usize {
+    if self.olfaction < 0 {
+      return 0;
+    }
+    return (self.olfaction / OLFACTION_PER_RADIUS) as usize;
+  }
+
+  ///
+  /// How good an aim the creature has with projectiles
+  ///
+  /// Aiming is done with the eyes, though a creature with a better nose than eyes can aim by smell instead
+  ///
+  pub fn accuracy(&self) -> isize {
+    cmp::max(self.sight_radius(), self.smell_radius()) as isize
   }
 
   ///
@@ -77,7 +161,9 @@ impl Stats {
       armor_value,
       evasion_value,
       weight,
-      scent_type
+      scent_type,
+      dread: 0,
+      movement: path::Costs::default()
     }
   }
 }
\ No newline at end of file
@@ -0,0 +1,178 @@
+//!
+//! Status effects, things that happen to a creature for a while
+//!
+//! Effects are put on a creature for some number of turns, and the world counts them down once a turn. What each one
+//! does is up to whatever it affects:
+//!
+//! * Bleeding - Lose a health point every turn
+//! * Confused - Half of all moves go off in a random direction
+//! * Blind - Only what's right next to the creature can be seen
+//! * Slowed - Half speed
+//! * Hasted - Double speed
+//! * Masked - Thick incense or smoke covers up the creature's own scent
+//!
+//! Putting an effect on a creature that already has it follows the effect's `Stacking` rule, and slowed and hasted
+//! cancel each other out.
+//!
+
+use std::cmp;
+use std::slice::Iter;
+
+use core::renderer::RGB;
+
+///
+/// Configuration
+///
+
+// Longest anything can last
+pub const MAX_TURNS : usize = 50;
+
+// How far a blind creature can see
+pub const BLIND_RADIUS : usize = 1;
+
+///
+/// Something that can happen to a creature for a while
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+  Bleeding,
+  Confused,
+  Blind,
+  Slowed,
+  Hasted,
+  Masked
+}
+
+///
+/// What happens when an effect is put on a creature that already has it
+///
+enum Stacking {
+  // Turns are added on top of what's left
+  Add,
+  // Whichever lasts longer wins
+  Refresh
+}
+
+impl Effect {
+
+  ///
+  /// Name of the effect, as in "You are no longer bleeding"
+  ///
+  pub fn name(&self) -> &'static str {
+    match *self {
+      Effect::Bleeding => "bleeding",
+      Effect::Confused => "confused",
+      Effect::Blind    => "blind",
+      Effect::Slowed   => "slowed",
+      Effect::Hasted   => "hasted",
+      Effect::Masked   => "masked"
+    }
+  }
+
+  ///
+  /// Color the effect is shown in
+  ///
+  pub fn color(&self) -> RGB {
+    match *self {
+      Effect::Bleeding => RGB(200, 0, 0),
+      Effect::Confused => RGB(200, 100, 255),
+      Effect::Blind    => RGB(150, 150, 150),
+      Effect::Slowed   => RGB(100, 150, 255),
+      Effect::Hasted   => RGB(255, 255, 100),
+      Effect::Masked   => RGB(180, 140, 100)
+    }
+  }
+
+  ///
+  /// Wounds pile up, everything else just lasts as long as the worst of it
+  ///
+  fn stacking(&self) -> Stacking {
+    match *self {
+      Effect::Bleeding => Stacking::Add,
+      _ => Stacking::Refresh
+    }
+  }
+
+  ///
+  /// Effect that this one cancels out
+  ///
+  fn opposite(&self) -> Option<Effect> {
+    match *self {
+      Effect::Slowed => Some(Effect::Hasted),
+      Effect::Hasted => Some(Effect::Slowed),
+      _ => None
+    }
+  }
+
+}
+
+///
+/// An effect along with how many turns it has left
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Status {
+  pub effect: Effect,
+  pub turns: usize
+}
+
+///
+/// Every effect on a creature
+///
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Statuses(Vec<Status>);
+
+impl Statuses {
+
+  ///
+  /// Put an effect on for some number of turns
+  ///
+  /// An effect with an opposite that's already on just cancels it out instead
+  ///
+  pub fn apply(&mut self, effect: Effect, turns: usize) {
+
+    if let Some(opposite) = effect.opposite() {
+      if self.has(opposite) {
+        self.0.retain(|status| status.effect != opposite);
+        return;
+      }
+    }
+
+    match self.0.iter_mut().find(|status| status.effect == effect) {
+      Some(status) => status.turns = match effect.stacking() {
+        Stacking::Add => cmp::min(status.turns + turns, MAX_TURNS),
+        Stacking::Refresh => cmp::max(status.turns, cmp::min(turns, MAX_TURNS))
+      },
+      None => self.0.push(Status { effect, turns: cmp::min(turns, MAX_TURNS) })
+    }
+
+  }
+
+  ///
+  /// Check if an effect is on
+  ///
+  #[inline]
+  pub fn has(&self, effect: Effect) -> bool {
+    self.0.iter().any(|status| status.effect == effect)
+  }
+
+  ///
+  /// Let a turn pass, returning the effects that wore off
+  ///
+  pub fn tick(&mut self) -> Vec<Effect> {
+    for status in &mut self.0 {
+      status.turns = status.turns.saturating_sub(1);
+    }
+    let worn_off = self.0.iter().filter(|status| status.turns == 0).map(|status| status.effect).collect();
+    self.0.retain(|status| status.turns > 0);
+    return worn_off;
+  }
+
+  ///
+  /// Get every effect that's on
+  ///
+  pub fn iter(&self) -> Iter<Status> {
+    self.0.iter()
+  }
+
+}
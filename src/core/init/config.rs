@@ -73,7 +73,14 @@ pub struct Config {
   pub wizard: bool,
 
   // Debug logs
-  pub debug: bool
+  pub debug: bool,
+
+  // World seed. Left empty (`~`) to pick a random seed every time the game starts
+  #[serde(default)]
+  pub seed: Option<usize>,
+
+  // Depth the player has to reach to win
+  pub victory_depth: usize
 
 }
 
@@ -1,3 +1,107 @@
 #[cfg(test)]
-mod tests {}
+mod tests {
 
+  use core::init::{default_keymap, DEFAULT_KEYMAP};
+  use core::init::keymap::{self, Command, Keymap, KeymapError};
+  use core::tcod::input::{Key, KeyCode};
+
+  fn parse(contents: &str) -> Result<Keymap, KeymapError> {
+    keymap::parse("test.yml", contents, false)
+  }
+
+  ///
+  /// A printable key, with or without shift
+  ///
+  fn key(printable: char, shift: bool) -> Key {
+    Key { code: KeyCode::Char, printable, shift, ..Default::default() }
+  }
+
+  #[test]
+  fn test_keymap_modifiers() {
+
+    let keymap = parse("bindings:\n  \".\": wait\n  \"shift+.\": descend\n  \"ctrl+s\": sniff\n").unwrap();
+
+    assert_eq!(keymap.command(&key('.', false)), Some(Command::Wait));
+    assert_eq!(keymap.command(&key('.', true)), Some(Command::Descend));
+
+    // Modifiers have to be held exactly
+    let ctrl_s = Key { left_ctrl: true, ..key('s', false) };
+    assert_eq!(keymap.command(&ctrl_s), Some(Command::Sniff));
+    assert_eq!(keymap.command(&key('s', false)), None);
+    assert_eq!(keymap.command(&Key { shift: true, ..ctrl_s }), None);
+
+  }
+
+  #[test]
+  fn test_keymap_named_keys() {
+
+    let keymap = parse("bindings:\n  \"kp5\": wait\n  \"Escape\": quit\n").unwrap();
+
+    assert_eq!(keymap.command(&Key { code: KeyCode::NumPad5, ..Default::default() }), Some(Command::Wait));
+    assert_eq!(keymap.command(&Key { code: KeyCode::Escape, ..Default::default() }), Some(Command::Quit));
+
+  }
+
+  #[test]
+  fn test_keymap_presets() {
+
+    // The file's own bindings win over presets
+    let keymap = parse("presets: [vi]\nbindings:\n  \"h\": pickup\n").unwrap();
+
+    assert_eq!(keymap.command(&key('h', false)), Some(Command::Pickup));
+    assert_eq!(keymap.command(&key('j', false)), Some(Command::MoveSouth));
+
+  }
+
+  #[test]
+  fn test_keymap_wizard() {
+
+    let contents = "bindings:\n  \"w\": regenerate\n";
+
+    assert_eq!(parse(contents).unwrap().command(&key('w', false)), None);
+    let wizard = keymap::parse("test.yml", contents, true).unwrap();
+    assert_eq!(wizard.command(&key('w', false)), Some(Command::Regenerate));
+
+  }
+
+  #[test]
+  fn test_keymap_errors() {
+
+    match parse("bindings:\n  \"g\": fly\n") {
+      Err(KeymapError::Yaml(..)) => (),
+      _ => panic!("unknown command wasn't an error")
+    }
+
+    match parse("presets: [emacs]\n") {
+      Err(KeymapError::Yaml(..)) => (),
+      _ => panic!("unknown preset wasn't an error")
+    }
+
+    match parse("bindings:\n  \"banana\": wait\n") {
+      Err(KeymapError::Key(..)) => (),
+      _ => panic!("unknown key wasn't an error")
+    }
+
+    match parse("bindings:\n  \"shift+.\": descend\n  \"SHIFT+.\": ascend\n") {
+      Err(KeymapError::Key(..)) => (),
+      _ => panic!("duplicate key wasn't an error")
+    }
+
+    match keymap::load("config/no_such_keys.yml", false) {
+      Err(KeymapError::Io(..)) => (),
+      _ => panic!("missing file wasn't an error")
+    }
+
+  }
+
+  #[test]
+  fn test_default_keymap() {
+
+    // The keys the game falls back on have to work, with or without wizard mode
+    assert!(keymap::parse("default", DEFAULT_KEYMAP, false).is_ok());
+    assert!(keymap::parse("default", DEFAULT_KEYMAP, true).is_ok());
+    assert_eq!(default_keymap(false).command(&key('g', false)), Some(Command::Pickup));
+
+  }
+
+}
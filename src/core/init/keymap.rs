@@ -0,0 +1,394 @@
+//!
+//! Load key bindings from a YAML keymap file with serde
+//!
+//! A keymap file picks any number of movement presets and then binds everything else:
+//!
+//! ```yaml
+//! presets: [vi, numpad, arrows]
+//! bindings:
+//!   "g": pickup
+//!   "shift+.": descend
+//!   "escape": quit
+//! ```
+//!
+//! Keys are either a single character, like `g` or `.`, or the name of a key, like `up` or `kp5` (see `named_key()`).
+//! They can be prefixed with any of `shift+`, `ctrl+` and `alt+`, in which case those exact modifiers must be held.
+//! Bindings in the file take priority over the ones from presets.
+//!
+
+// Serde
+extern crate serde_yaml;
+
+// Use to read files
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, prelude::*};
+
+use core::tcod::input::{Key, KeyCode};
+
+///
+/// Everything a key can be bound to
+///
+/// Commands are also what replays are made of, so they serialize under the same names they're bound by
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+  MoveNorth,
+  MoveSouth,
+  MoveEast,
+  MoveWest,
+  MoveNorthEast,
+  MoveNorthWest,
+  MoveSouthEast,
+  MoveSouthWest,
+  Wait,
+  Pickup,
+  Drop,
+  Inventory,
+  Descend,
+  Ascend,
+  Open,
+  Close,
+  Throw,
+  Fire,
+  Sniff,
+  Quit,
+  // Wizard commands
+  Regenerate,
+  Empty,
+  ToggleScent,
+  ToggleSound,
+  ToggleFov,
+  ToggleNoclip,
+  TcodTest,
+  Screenshot
+}
+
+impl Command {
+
+  ///
+  /// Check if the command is only for wizards
+  ///
+  pub fn wizard(&self) -> bool {
+    match *self {
+      Command::Regenerate | Command::Empty | Command::ToggleScent | Command::ToggleSound |
+      Command::ToggleFov | Command::ToggleNoclip | Command::TcodTest | Command::Screenshot => true,
+      _ => false
+    }
+  }
+
+  ///
+  /// Get which way a movement command goes, for commands that ask for a direction
+  ///
+  pub fn direction(&self) -> Option<(isize, isize)> {
+    match *self {
+      Command::MoveWest      => Some((-1,  0)),
+      Command::MoveSouth     => Some(( 0,  1)),
+      Command::MoveNorth     => Some(( 0, -1)),
+      Command::MoveEast      => Some(( 1,  0)),
+      Command::MoveNorthWest => Some((-1, -1)),
+      Command::MoveNorthEast => Some(( 1, -1)),
+      Command::MoveSouthWest => Some((-1,  1)),
+      Command::MoveSouthEast => Some(( 1,  1)),
+      _ => None
+    }
+  }
+
+}
+
+///
+/// Sets of movement keys that come with the game
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+  // hjklyubn
+  Vi,
+  // Numpad, with 5 to wait
+  Numpad,
+  // Arrow keys, with home, end, page up and page down for diagonals
+  Arrows
+}
+
+const VI : [(&str, Command); 8] = [
+  ("h", Command::MoveWest),
+  ("j", Command::MoveSouth),
+  ("k", Command::MoveNorth),
+  ("l", Command::MoveEast),
+  ("y", Command::MoveNorthWest),
+  ("u", Command::MoveNorthEast),
+  ("b", Command::MoveSouthWest),
+  ("n", Command::MoveSouthEast)
+];
+
+const NUMPAD : [(&str, Command); 9] = [
+  ("kp4", Command::MoveWest),
+  ("kp2", Command::MoveSouth),
+  ("kp8", Command::MoveNorth),
+  ("kp6", Command::MoveEast),
+  ("kp7", Command::MoveNorthWest),
+  ("kp9", Command::MoveNorthEast),
+  ("kp1", Command::MoveSouthWest),
+  ("kp3", Command::MoveSouthEast),
+  ("kp5", Command::Wait)
+];
+
+const ARROWS : [(&str, Command); 8] = [
+  ("left", Command::MoveWest),
+  ("down", Command::MoveSouth),
+  ("up", Command::MoveNorth),
+  ("right", Command::MoveEast),
+  ("home", Command::MoveNorthWest),
+  ("pageup", Command::MoveNorthEast),
+  ("end", Command::MoveSouthWest),
+  ("pagedown", Command::MoveSouthEast)
+];
+
+impl Preset {
+
+  fn bindings(&self) -> &'static [(&'static str, Command)] {
+    match *self {
+      Preset::Vi => &VI,
+      Preset::Numpad => &NUMPAD,
+      Preset::Arrows => &ARROWS
+    }
+  }
+
+}
+
+///
+/// Get the tcod `KeyCode` of a named key
+///
+fn named_key(name: &str) -> Option<KeyCode> {
+  match name {
+    "escape"    => Some(KeyCode::Escape),
+    "enter"     => Some(KeyCode::Enter),
+    "tab"       => Some(KeyCode::Tab),
+    "backspace" => Some(KeyCode::Backspace),
+    "space"     => Some(KeyCode::Spacebar),
+    "insert"    => Some(KeyCode::Insert),
+    "delete"    => Some(KeyCode::Delete),
+    "up"        => Some(KeyCode::Up),
+    "down"      => Some(KeyCode::Down),
+    "left"      => Some(KeyCode::Left),
+    "right"     => Some(KeyCode::Right),
+    "home"      => Some(KeyCode::Home),
+    "end"       => Some(KeyCode::End),
+    "pageup"    => Some(KeyCode::PageUp),
+    "pagedown"  => Some(KeyCode::PageDown),
+    "kp0"       => Some(KeyCode::NumPad0),
+    "kp1"       => Some(KeyCode::NumPad1),
+    "kp2"       => Some(KeyCode::NumPad2),
+    "kp3"       => Some(KeyCode::NumPad3),
+    "kp4"       => Some(KeyCode::NumPad4),
+    "kp5"       => Some(KeyCode::NumPad5),
+    "kp6"       => Some(KeyCode::NumPad6),
+    "kp7"       => Some(KeyCode::NumPad7),
+    "kp8"       => Some(KeyCode::NumPad8),
+    "kp9"       => Some(KeyCode::NumPad9),
+    _ => None
+  }
+}
+
+///
+/// What is actually pressed, a printable character or a key with no character
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Press {
+  Char(char),
+  Code(KeyCode)
+}
+
+///
+/// A key along with the modifiers that have to be held with it
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Binding {
+  press: Press,
+  shift: bool,
+  ctrl: bool,
+  alt: bool
+}
+
+impl Binding {
+
+  ///
+  /// Parse a key string like `shift+.` or `kp5`
+  ///
+  fn parse(string: &str) -> Result<Binding, String> {
+
+    let mut binding = Binding { press: Press::Char(' '), shift: false, ctrl: false, alt: false };
+    let mut rest = string;
+
+    // Peel off modifiers. A lone "+" is a key, not a modifier separator
+    loop {
+      let lower = rest.to_lowercase();
+      if lower.starts_with("shift+") && rest.len() > 6 {
+        binding.shift = true;
+        rest = &rest[6..];
+      } else if lower.starts_with("ctrl+") && rest.len() > 5 {
+        binding.ctrl = true;
+        rest = &rest[5..];
+      } else if lower.starts_with("alt+") && rest.len() > 4 {
+        binding.alt = true;
+        rest = &rest[4..];
+      } else {
+        break;
+      }
+    }
+
+    let mut chars = rest.chars();
+    binding.press = match (chars.next(), chars.next()) {
+      (Some(c), None) => Press::Char(c),
+      _ => match named_key(&rest.to_lowercase()) {
+        Some(code) => Press::Code(code),
+        None => return Err(format!("Unknown key `{}` in binding `{}`", rest, string))
+      }
+    };
+
+    return Ok(binding);
+
+  }
+
+  ///
+  /// Check if a keypress triggers the binding
+  ///
+  fn matches(&self, key: &Key) -> bool {
+
+    if self.shift != key.shift || self.ctrl != (key.left_ctrl || key.right_ctrl) || self.alt != (key.left_alt || key.right_alt) {
+      return false;
+    }
+
+    match self.press {
+      Press::Char(c) => key.printable == c,
+      Press::Code(code) => key.code == code
+    }
+
+  }
+
+}
+
+///
+/// Keymap file as written
+///
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeymapFile {
+  #[serde(default)]
+  presets: Vec<Preset>,
+  #[serde(default)]
+  bindings: BTreeMap<String, Command>
+}
+
+///
+/// Key bindings ready to be looked up
+///
+pub struct Keymap {
+  bindings: Vec<(Binding, Command)>
+}
+
+impl Keymap {
+
+  ///
+  /// Find the command a keypress is bound to
+  ///
+  pub fn command(&self, key: &Key) -> Option<Command> {
+    self.bindings.iter().find(|&&(binding, _)| binding.matches(key)).map(|&(_, command)| command)
+  }
+
+  ///
+  /// Bind a key, replacing whatever it was bound to before
+  ///
+  fn bind(&mut self, binding: Binding, command: Command) {
+    self.bindings.retain(|&(bound, _)| bound != binding);
+    self.bindings.push((binding, command));
+  }
+
+}
+
+///
+/// Things that can go wrong while reading a keymap
+///
+#[derive(Debug)]
+pub enum KeymapError {
+  // Couldn't read the file
+  Io(String, io::Error),
+  // The file isn't laid out like a keymap, or names a command or preset that doesn't exist
+  Yaml(String, serde_yaml::Error),
+  // A key that can't be bound, or that's bound twice
+  Key(String, String)
+}
+
+impl fmt::Display for KeymapError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      KeymapError::Io(ref path, ref err) => write!(fmt, "Unable to open {}: {}", path, err),
+      KeymapError::Yaml(ref path, ref err) => write!(fmt, "Bad keymap {}: {}", path, err),
+      KeymapError::Key(ref path, ref msg) => write!(fmt, "Bad keymap {}: {}", path, msg)
+    }
+  }
+}
+
+///
+/// Load a keymap from a path
+///
+/// Wizard commands are left unbound unless `wizard` is on
+///
+pub fn load(path: &str, wizard: bool) -> Result<Keymap, KeymapError> {
+
+  // Load file to String
+  let mut contents = String::new();
+  File::open(path)
+    .and_then(|mut file| file.read_to_string(&mut contents))
+    .map_err(|err| KeymapError::Io(path.to_string(), err))?;
+
+  parse(path, &contents, wizard)
+
+}
+
+///
+/// Read a keymap, where `path` is only used to say where errors came from
+///
+/// Bad keys are reported with the binding they came from, and serde reports unknown commands and presets along with
+/// the ones it expected. Two bindings in the file for the same key are an error, since only one of them could be used
+///
+pub fn parse(path: &str, contents: &str, wizard: bool) -> Result<Keymap, KeymapError> {
+
+  // Deserialize content to KeymapFile
+  let ds_keymap : KeymapFile = serde_yaml::from_str(contents).map_err(|err| KeymapError::Yaml(path.to_string(), err))?;
+
+  let mut keymap = Keymap { bindings: vec![] };
+
+  // Presets first so that the file can override them
+  for preset in &ds_keymap.presets {
+    for &(key, command) in preset.bindings() {
+      let binding = Binding::parse(key).map_err(|err| KeymapError::Key(path.to_string(), err))?;
+      keymap.bind(binding, command);
+    }
+  }
+
+  // Keys the file has bound so far, as they were written
+  let mut bound : Vec<(Binding, String)> = vec![];
+
+  for (key, command) in ds_keymap.bindings {
+
+    let binding = Binding::parse(&key).map_err(|err| KeymapError::Key(path.to_string(), err))?;
+
+    if let Some(&(_, ref first)) = bound.iter().find(|&&(other, _)| other == binding) {
+      return Err(KeymapError::Key(path.to_string(), format!("`{}` and `{}` are the same key", first, key)));
+    }
+    bound.push((binding, key));
+
+    if command.wizard() && !wizard {
+      continue;
+    }
+
+    keymap.bind(binding, command);
+
+  }
+
+  return Ok(keymap);
+
+}
@@ -4,6 +4,12 @@
 //! Depends on the `config` module.
 //! 
 
+// Command line arguments
+use std::env;
+
+extern crate rand;
+use self::rand::Rng;
+
 // We need tcod::Console to keep our consoles in scope
 #[allow(unused_imports)]
 use core::tcod::{Console, console};
@@ -11,6 +17,19 @@ use core::tcod::{Console, console};
 pub mod config;
 use self::config::Config;
 
+pub mod keymap;
+use self::keymap::Keymap;
+
+use core::log;
+use core::renderer::RGB;
+use core::replay;
+
+// Where key bindings are loaded from
+const KEYMAP_PATH : &str = "config/keys.yml";
+
+// Key bindings the game shipped with, for when the ones in `KEYMAP_PATH` are broken
+const DEFAULT_KEYMAP : &str = include_str!("../../../config/keys.yml");
+
 mod init_tests;
 
 // So for a while each function here loaded the config on it's own. And I thought to myself, "hm, is it possible for this
@@ -81,6 +100,15 @@ pub fn map_dimensions() -> (isize, isize) {
   (CFG.map_width, CFG.map_height)
 }
 
+///
+/// Get screen dimensions as a tuple
+///
+/// Only needed when there's no window to ask, like when playing back a replay headless
+///
+pub fn screen_dimensions() -> (isize, isize) {
+  (CFG.screen_width, CFG.screen_height)
+}
+
 ///
 /// Get console height
 ///
@@ -102,9 +130,98 @@ pub fn wizard() -> bool {
   CFG.wizard
 }
 
+///
+/// Load the keymap
+///
+/// Wizard commands are only bound when wizard mode is on. A keymap that can't be read is reported in the log, and the
+/// keys the game shipped with are used instead
+///
+pub fn keymap() -> Keymap {
+  keymap::load(KEYMAP_PATH, CFG.wizard).unwrap_or_else(|err| {
+    log!(err.to_string(), RGB(255, 0, 0));
+    log!("Using the default keys instead", RGB(255, 0, 0));
+    default_keymap(CFG.wizard)
+  })
+}
+
+///
+/// Get the keymap the game shipped with
+///
+fn default_keymap(wizard: bool) -> Keymap {
+  keymap::parse(KEYMAP_PATH, DEFAULT_KEYMAP, wizard).unwrap_or_else(|err| panic!("{}", err))
+}
+
 ///
 /// Get debug mode
 ///
 pub fn debug() -> bool {
   CFG.debug
+}
+
+///
+/// Get the depth the player has to reach to win
+///
+pub fn victory_depth() -> usize {
+  CFG.victory_depth
+}
+
+///
+/// Get the world seed
+///
+/// A `--seed <n>` flag on the command line takes priority over the seed in the config. If neither
+/// are present, a random seed is rolled so that it can still be shown to the player and used to recreate the game
+///
+pub fn seed() -> usize {
+
+  // Look for the flag on the command line first
+  if let Some(seed) = arg_value("--seed") {
+    return seed.parse().unwrap_or_else(|_| panic!("Bad seed: {}", seed));
+  }
+
+  // Then fall back on the config, or just roll one
+  match CFG.seed {
+    Some(seed) => seed,
+    None => rand::thread_rng().gen()
+  }
+
+}
+
+///
+/// Get how to play back a replay, if one was asked for on the command line
+///
+/// `--replay <file>` plays a replay instead of starting a game. It can be followed by any of:
+/// * `--headless` - Play without a window, as fast as possible
+/// * `--fast` - Don't wait between turns
+/// * `--stop-at <turn>` - Stop once the world reaches a turn
+///
+pub fn replay() -> Option<replay::Options> {
+  arg_value("--replay").map(|path| replay::Options {
+    path,
+    headless: has_flag("--headless"),
+    fast: has_flag("--fast"),
+    stop_at: arg_value("--stop-at").map(|turn| turn.parse().unwrap_or_else(|_| panic!("Bad turn: {}", turn)))
+  })
+}
+
+///
+/// Check if a flag was given on the command line
+///
+fn has_flag(flag: &str) -> bool {
+  env::args().any(|arg| arg == flag)
+}
+
+///
+/// Get the value given after a flag on the command line
+///
+/// Panics if the flag is there without a value
+///
+fn arg_value(flag: &str) -> Option<String> {
+  let args : Vec<String> = env::args().collect();
+  match args.iter().position(|arg| arg == flag) {
+    Some(idx) => match args.get(idx + 1) {
+      Some(value) => Some(value.clone()),
+      None => panic!("{} requires a value", flag)
+    },
+    None => None
+  }
 }
\ No newline at end of file
@@ -1,10 +1,14 @@
 
-use core::creature::Creature;
+extern crate rand;
+use self::rand::{Rng, StdRng};
+
+use core::creature::{ai, Actions};
 use core::renderer::{Renderable, RGB};
+use core::save::StaticStr;
 use core::time::Time;
-use core::world::dungeon::map::{self, Pos, Tile};
+use core::world::dungeon::map::{path, Pos, tile};
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Money {
   Copper,
   Silver,
@@ -37,18 +41,58 @@ pub fn money_value(money: &Money) -> f32 {
   }
 }
 
-#[derive(Clone)]
+///
+/// Something that fires other items, like a sling firing rocks
+///
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Launcher {
+  // Name of the item it fires
+  pub ammo: StaticStr,
+  // How many tiles it can fire
+  pub range: usize,
+  // Hardest it can hit
+  pub damage: isize
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum ItemProperty {
   // Money is an interesting property because it should go right to a player's wallet
-  Money(Money)
+  Money(Money),
+  // Fires other items at range
+  Launcher(Launcher),
+  // Gives off a scent while it's lying on the floor
+  Scented(tile::Scent),
+  // Stuff that doesn't do anything special (yet), like rocks and bones
+  Misc
+}
+
+// Ids of items that can be made with `from_id()`
+pub const ITEM_IDS : [&str; 5] = ["gold", "rock", "bone", "sling", "incense"];
+
+///
+/// Make an item by id, so that data files can ask for items
+///
+/// Amounts of stackable items are rolled with `rng`
+///
+pub fn from_id(id: &str, pos: Pos, rng: &mut StdRng) -> Option<Item> {
+  match id {
+    "gold" => Some(Item::new("gold piece", '$', pos, RGB(238, 232, 170), RGB(0, 0, 0), rng.gen_range(10, 40), 0, ItemProperty::Money(Money::Gold))),
+    "rock" => Some(Item::new("rock", '*', pos, RGB(130, 130, 130), RGB(0, 0, 0), rng.gen_range(1, 4), 2, ItemProperty::Misc)),
+    "bone" => Some(Item::new("bone", '%', pos, RGB(220, 220, 200), RGB(0, 0, 0), 1, 1, ItemProperty::Misc)),
+    "incense" => Some(Item::new("joss stick", '/', pos, RGB(200, 160, 120), RGB(0, 0, 0), 1, 1, ItemProperty::Scented(tile::Scent::Incense))),
+    "sling" => Some(Item::new("sling", '}', pos, RGB(160, 110, 60), RGB(0, 0, 0), 1, 1, ItemProperty::Launcher(
+      Launcher { ammo: StaticStr("rock"), range: 12, damage: 5 }
+    ))),
+    _ => None
+  }
 }
 
 ///
 /// Item struct
 ///
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Item {
-  name: &'static str,
+  name: StaticStr,
   glyph: char,
   // Items can potentially be in something's inventory
   pub pos: Pos,
@@ -58,6 +102,10 @@ pub struct Item {
   // Items could have quantity like stacks of arrows, portions of food, liters of water etc
   pub quantity: isize,
 
+  // Weight of a single one of the item, so a stack weighs `weight * quantity`
+  #[serde(default)]
+  pub weight: usize,
+
   // Item property
   pub property: ItemProperty
 }
@@ -67,17 +115,62 @@ impl Item {
   /// Return a new `Item`
   ///
   #[inline]
-  pub fn new(name: &'static str, glyph: char, pos: Pos, fg: RGB, bg: RGB, quantity: isize, property: ItemProperty) -> Self {
+  pub fn new(name: &'static str, glyph: char, pos: Pos, fg: RGB, bg: RGB, quantity: isize, weight: usize, property: ItemProperty) -> Self {
     Item {
-      name,
+      name: StaticStr(name),
       glyph, 
       pos, 
       fg, 
       bg,
       quantity,
+      weight,
       property
     }
   }
+
+  ///
+  /// Check if two items are alike enough to be put in the same stack
+  ///
+  #[inline]
+  pub fn stacks_with(&self, other: &Item) -> bool {
+    self.name == other.name && self.glyph == other.glyph && self.weight == other.weight && self.property == other.property
+  }
+
+  ///
+  /// Weight of the whole stack
+  ///
+  #[inline]
+  pub fn total_weight(&self) -> usize {
+    self.weight * self.quantity as usize
+  }
+
+  ///
+  /// Hardest one of these can hit when thrown. Heavier things hurt more
+  ///
+  #[inline]
+  pub fn thrown_damage(&self) -> isize {
+    1 + self.weight as isize
+  }
+
+  ///
+  /// Check if this is the ammo a launcher fires
+  ///
+  #[inline]
+  pub fn is_ammo_for(&self, launcher: &Launcher) -> bool {
+    self.name == launcher.ammo
+  }
+
+  ///
+  /// Describe the stack, like "a rock" or "3 rocks"
+  ///
+  pub fn describe(&self) -> String {
+    if self.quantity > 1 {
+      format!("{} {}s", self.quantity, self.name.0)
+    } else {
+      format!("a {}", self.name.0)
+    }
+  }
+
 }
 
 ///
@@ -102,7 +195,7 @@ impl Renderable for Item {
 
   #[inline]
   fn get_id(&self) -> &'static str {
-    self.name.clone()
+    self.name.0
   }
 
   #[inline]
@@ -122,15 +215,15 @@ impl Renderable for Item {
 
   #[inline]
   fn set_id(&mut self, name: &'static str) {
-    self.name = name;
+    self.name = StaticStr(name);
   }
 
 }
 
 impl Time for Item {
 
-  fn take_turn(&mut self, _map: &map::Grid<Tile>, _player: &Creature) {
-
+  fn take_turn(&mut self, _ctx: &ai::Context, _paths: &mut path::Paths, _rng: &mut StdRng) -> ai::Intent {
+    Actions::Unknown.into()
   }
 
 }
\ No newline at end of file
@@ -14,6 +14,8 @@
 ///
 /// Import log and use the macro `log!()` and pass in the string/RGB tuple. Anything else and im pretty sure it panics
 ///
+/// The string can be a `&'static str` or a `String`, so messages made with `format!()` can go straight in
+///
 
 ///
 /// How it actually works
@@ -42,6 +44,7 @@
 ///
 /// Then it's slapped into a macro.
 ///
+use std::borrow::Cow;
 use std::sync::Mutex;
 
 use core::renderer::RGB;
@@ -51,7 +54,7 @@ use core::renderer::RGB;
 ///
 #[derive(Default)]
 pub struct Log {
-  pub data: Vec<(&'static str, RGB, u32)>,
+  pub data: Vec<(Cow<'static, str>, RGB, u32)>,
 }
 
 impl Log {
@@ -68,7 +71,7 @@ impl Log {
   /// The intention of this is that the range is the interated over, and then used as indices
   /// to read the log data
   ///
-  pub fn get_last_n_messages(&self, n: usize) -> &[(&'static str, RGB, u32)] {
+  pub fn get_last_n_messages(&self, n: usize) -> &[(Cow<'static, str>, RGB, u32)] {
     // Basically if there are n items in the log, but we want to get > n items, we
     // should make sure rust doesn't have some sort of underflow error
     if n > self.data.len() {
@@ -81,12 +84,12 @@ impl Log {
   ///
   /// Push new data onto the log stack
   ///
-  pub fn push(&mut self, message: (&'static str, RGB, u32)) {
+  pub fn push(&mut self, message: (Cow<'static, str>, RGB, u32)) {
     // If there are elements in the log
     if self.data.len() > 0 {
       // If the last message string is the same, update the counter instead of pushing.
       let last_pos = self.data.len() - 1;
-      if &self.data[last_pos].0 == &message.0 {
+      if self.data[last_pos].0 == message.0 {
         self.data[last_pos].2 += 1;
         return;
       }
@@ -114,7 +117,7 @@ macro_rules! log {
     let mut log = GlobalLog.lock().unwrap();
     // Push the message
     // Highly implies a correct expression for the push arguments are being supplied
-    log.push(($msg, $col, 1));
+    log.push(($msg.into(), $col, 1));
     // Drop the reference
     drop(log);
   }};
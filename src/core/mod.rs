@@ -2,7 +2,13 @@
 //! A game engine based around a state machine
 //! 
 
+use std::cmp;
 use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+extern crate rand;
+use self::rand::Rng;
 
 // tcod
 //
@@ -36,7 +42,7 @@ use self::log::GlobalLog;
 pub mod world;
 // Import world directly so we can make instances of it
 use self::world::World;
-use self::world::dungeon::map::Pos;
+use self::world::dungeon::map::{Pos, tile};
 
 // Creatures
 //
@@ -46,14 +52,29 @@ pub mod creature;
 // While normally this module most likely should not have access to objects, we need to see `Action`s as the player's
 // choices changes the state of the game
 use self::creature::actions::Actions;
+use self::creature::{Character, Effect};
+use self::creature::origin::{BACKGROUNDS, RACES};
 
 // Items
 pub mod item;
-use self::item::{ItemProperty, money_value};
 
 // Time
 pub mod time;
 
+// Saving and loading
+//
+// The world can be written to disk in its entirety and loaded back up from the title screen
+pub mod save;
+mod save_tests;
+
+// Replays
+//
+// Every new game is recorded as its seed and the commands the player gave, which can be played back to get the exact
+// same game again
+pub mod replay;
+mod replay_tests;
+use self::replay::{Event, Playback, Recorder};
+
 // Renderer
 //
 // The renderer is the interface by which game objects and constructs are made real through tcod interaction. The central
@@ -64,7 +85,7 @@ pub mod time;
 // Renderer is public so that docs are generated for it
 pub mod renderer;
 // We import the renderer to create instances of it and RGB so we can color some log outputs
-use self::renderer::{Renderer, Renderable, RGB};
+use self::renderer::{Backend, Headless, Renderer, RGB};
 
 // Initializer
 // 
@@ -73,6 +94,14 @@ use self::renderer::{Renderer, Renderable, RGB};
 //
 // Pretty sure you understand why this is public by now
 pub mod init;
+use self::init::keymap::{Command, Keymap};
+
+///
+/// Configuration
+///
+
+// How long to wait between turns when playing back a replay in a window
+const PLAYBACK_DELAY_MS : u64 = 50;
 
 ///
 /// Enum representing the state of the game
@@ -91,7 +120,75 @@ pub enum State {
   // A key was pressed
   Keypress,
   // Debug command was triggered
-  Debug
+  Debug,
+  // Player wants to quit, but has to confirm it first
+  Quit,
+  // Player died
+  Dead,
+  // Player made it deep enough to win
+  Won,
+  // Game is over, so it's back to the title screen
+  Title
+}
+
+///
+/// What the player picked on the title screen
+///
+enum TitleChoice {
+  NewGame,
+  Continue,
+  Exit
+}
+
+///
+/// Where the engine draws the game
+///
+/// Keypresses come from the window, so an engine without one can only ever play back replays
+///
+pub enum Screen {
+  // A tcod window
+  Window(console::Root),
+  // An off-screen buffer, for playing back replays on machines with no display
+  Headless(Headless)
+}
+
+impl Backend for Screen {
+
+  fn size(&self) -> Pos {
+    match *self {
+      Screen::Window(ref root) => Backend::size(root),
+      Screen::Headless(ref headless) => headless.size()
+    }
+  }
+
+  fn clear(&mut self) {
+    match *self {
+      Screen::Window(ref mut root) => Backend::clear(root),
+      Screen::Headless(ref mut headless) => headless.clear()
+    }
+  }
+
+  fn put(&mut self, pos: Pos, glyph: char, fg: RGB, bg: RGB) {
+    match *self {
+      Screen::Window(ref mut root) => Backend::put(root, pos, glyph, fg, bg),
+      Screen::Headless(ref mut headless) => headless.put(pos, glyph, fg, bg)
+    }
+  }
+
+  fn print(&mut self, pos: Pos, text: &str, fg: RGB) {
+    match *self {
+      Screen::Window(ref mut root) => Backend::print(root, pos, text, fg),
+      Screen::Headless(ref mut headless) => headless.print(pos, text, fg)
+    }
+  }
+
+  fn flush(&mut self) {
+    match *self {
+      Screen::Window(ref mut root) => Backend::flush(root),
+      Screen::Headless(ref mut headless) => headless.flush()
+    }
+  }
+
 }
 
 ///
@@ -107,7 +204,13 @@ pub struct Engine {
   world: World,
   state: State,
   ren: Renderer,
-  root: console::Root,
+  screen: Screen,
+  keymap: Keymap,
+
+  // Every command the player gives is written here, so that the game can be played back
+  recorder: Option<Recorder>,
+  // Replay being played back in place of the player
+  playback: Option<Playback>,
   
   // Debug options the engine tracks
   wizard: bool,
@@ -117,266 +220,424 @@ pub struct Engine {
 
 impl Engine {
 
+  ///
+  /// Move the player by `x` and `y`
+  ///
+  fn move_player(&mut self, x: isize, y: isize) {
+    self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
+    self.world.player.actor.move_cart(x, y);
+    self.world.player.state = Actions::Move;
+  }
+
+  ///
+  /// Work out which door next to the player a door command is for
+  ///
+  /// If only one door around the player is in one of `states` there's nothing to ask, otherwise the player picks a
+  /// direction with a movement key
+  ///
+  fn pick_door(&mut self, states: &[tile::Door], prompt: &'static str) -> Option<Pos> {
+
+    let pos = self.world.player.actor.pos;
+    let doors = self.world.doors_around(pos, states);
+    if doors.len() == 1 {
+      return Some(doors[0]);
+    }
+
+    // Replays already know which way the player went
+    let direction = match self.playback {
+      Some(ref mut playback) => playback.next_direction(),
+      None => {
+        log!(prompt, RGB(200, 200, 200));
+        self.ren.draw_all(&mut self.screen, &mut self.world);
+        let keypress = self.wait_for_keypress();
+        let direction = self.keymap.command(&keypress).and_then(|command| command.direction());
+        self.record(Event::Direction(direction));
+        direction
+      }
+    };
+
+    match direction {
+      Some((x, y)) => Some(Pos::new(pos.x + x, pos.y + y)),
+      None => None
+    }
+
+  }
+
+  ///
+  /// Let the player pick a tile to aim at with a cursor
+  ///
+  /// The cursor starts on the closest creature in view, or on the player if there aren't any, and is moved around with
+  /// the movement keys. Enter or the key for `confirm` picks the tile under the cursor, anything else gives up, and so
+  /// does picking the player's own tile
+  ///
+  fn pick_target(&mut self, prompt: &'static str, confirm: Command) -> Option<Pos> {
+
+    // Replays already know where the player aimed
+    if let Some(ref mut playback) = self.playback {
+      return playback.next_target();
+    }
+
+    let player = self.world.player.actor.pos;
+    let mut cursor = self.world.floor.creatures.iter()
+      .map(|creature| creature.actor.pos)
+      .filter(|pos| self.world.tcod_map.is_in_fov(pos.x as i32, pos.y as i32))
+      .min_by_key(|pos| cmp::max((pos.x - player.x).abs(), (pos.y - player.y).abs()))
+      .unwrap_or(player);
+
+    let bounds = self.world.floor.dun.get_bounds_pos();
+
+    let target = loop {
+
+      self.ren.draw_all(&mut self.screen, &mut self.world);
+      self.ren.draw_target(&mut self.screen, &self.world, cursor, prompt);
+
+      let keypress = self.wait_for_keypress();
+      let command = self.keymap.command(&keypress);
+
+      // Keep the cursor on the map
+      if let Some((x, y)) = command.and_then(|command| command.direction()) {
+        let moved = Pos::new(cursor.x + x, cursor.y + y);
+        if moved.x >= 0 && moved.y >= 0 && moved.x < bounds.x && moved.y < bounds.y {
+          cursor = moved;
+        }
+        continue;
+      }
+
+      // Nobody means to aim at themselves
+      if keypress.code == input::KeyCode::Enter || command == Some(confirm) {
+        break if cursor != player { Some(cursor) } else { None };
+      }
+
+      break None;
+
+    };
+
+    self.record(Event::Target(target));
+
+    return target;
+
+  }
+
+  ///
+  /// Wait for the player to press a key
+  ///
+  /// Only windows take input, and engines without one only ever play back replays, which never wait on the player
+  ///
+  fn wait_for_keypress(&mut self) -> input::Key {
+    match self.screen {
+      Screen::Window(ref mut root) => root.wait_for_keypress(true),
+      Screen::Headless(_) => unreachable!("Engine - Waited for a keypress without a window")
+    }
+  }
+
+  ///
+  /// Write something the player did to the replay
+  ///
+  /// A replay that can't be written to is given up on, since a game shouldn't stop just because it can't be recorded
+  ///
+  fn record(&mut self, event: Event) {
+    let failed = match self.recorder {
+      Some(ref mut recorder) => recorder.record(event).err(),
+      None => None
+    };
+    if let Some(err) = failed {
+      log!(err.to_string(), RGB(255, 0, 0));
+      self.recorder = None;
+    }
+  }
+
   ///
   /// Capture keyboard input from tcod and update player state
   /// 
+  /// Keys are looked up in the keymap, so this only ever deals with the commands they're bound to.
+  /// Every command is recorded before it's carried out, so a replay holds the command that crashed the game.
+  /// The one exception is quitting, which isn't recorded unless the player goes through with it
+  /// 
   fn process_keypress(&mut self, keypress: input::Key) {
 
-    match keypress.code {
-      
-      // If the keycode isn't escape we continue checking for important keys
-      input::KeyCode::Escape => panic!("Bye"),
-
-      // This part of the code is for capturing the keypress not as an object, but as a character for easier parsing
-      _ => { 
-        
-        // We only care if the key is printable, aka, has some symbol attached to it
-        if keypress.printable != ' ' {
-          
-          // First, make an assumption that the player is affecting their movement as 90% of the game
-          // is walking around. We *could* add it to every single one of the vim keypresses to save a trivial amount of
-          // time assigning this variable, but I dislike that.
-          let old_pos = self.world.player.actor.pos;
-
-          // In addition, update the game state
-          // Important so that if the game state becomes debug we can leave that state instantly
-          self.state = State::Keypress;
-
-          // Begin to pattern match the char corresponding to the key pressed
-
-          // If shift is held
-          if keypress.shift == true {
-
-            match keypress.printable {
-              
-              // Go downstairs (if possible)
-              '.' => { self.world.player.state = Actions::DownStair },
-              // Go upstairs (if possible)
-              ',' => { self.world.player.state = Actions::UpStair },
-
-              // Unbound key, so we just say we don't know what the player did
-              _ => { self.world.player.state = Actions::Unknown }
+    // Figure out what the player wants to do. Unbound keys (including lone modifiers) are ignored entirely
+    match self.keymap.command(&keypress) {
+      Some(command) => {
+        if command != Command::Quit {
+          self.record(Event::Command(command));
+        }
+        self.process_command(command);
+      },
+      None => self.state = State::Keypress
+    }
 
-            }
-          
-          // No shift held
-          } else {
+  }
 
-            match keypress.printable {
-
-              // Movement keys are bound to vim-like controls (hjklyubn)
-              'h' => { 
-                self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
-                self.world.player.actor.move_cart(-1, 0);
-                self.world.player.state = Actions::Move;
-              },
-              'j' => { 
-                self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
-                self.world.player.actor.move_cart(0, 1);
-                self.world.player.state = Actions::Move;
-              },
-              'k' => {
-                self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
-                self.world.player.actor.move_cart(0, -1);
-                self.world.player.state = Actions::Move;
-              },
-              'l' => {
-                self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
-                self.world.player.actor.move_cart(1, 0);
-                self.world.player.state = Actions::Move;
-              },
-              'y' => {
-                self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
-                self.world.player.actor.move_cart(-1, -1);
-                self.world.player.state = Actions::Move;
-              },
-              'u' => {
-                self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
-                self.world.player.actor.move_cart(1, -1);
-                self.world.player.state = Actions::Move;
-              },
-              'b' => {
-                self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
-                self.world.player.actor.move_cart(-1, 1);
-                self.world.player.state = Actions::Move;
-              },
-              'n' => { 
-                self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
-                self.world.player.actor.move_cart(1, 1);
-                self.world.player.state = Actions::Move;
-              },
-
-              // Pick up item. Will become super buggy at some point guaranteed
-              'g' => {
-                // Player pos
-                let player_pos = self.world.player.actor.pos;
-                self.state = State::Act(Actions::Unknown);
-                // Get items at players feet
-                // let items_at_feet = self.world.floor.items.iter().filter(|item| item.pos == self.world.player.actor.pos.clone());
-                for item in &self.world.floor.items {
-                  if item.pos == player_pos {
-                    match item.property {
-                      ItemProperty::Money(ref tender) => self.world.player.wallet += money_value(&tender) * item.quantity as f32
-                    }
-                    if item.quantity > 1 {
-                      log!(Box::leak(format!("You pick up {} {}s", item.quantity, item.get_id()).into_boxed_str()), item.get_fg());
-                    } else {
-                      log!(Box::leak(format!("You pick up a {}", item.get_id()).into_boxed_str()), item.get_fg());
-                    }
-                    self.state = State::Act(Actions::Pickup);
-                  }
-                }
-                // Prune picked up items
-                self.world.floor.items.retain( |item| item.pos != player_pos );
-                
-              }
+  ///
+  /// Carry out a command and update player state
+  ///
+  /// Wizard commands are never bound outside of wizard mode, so they don't need to check for it here.
+  /// Replays feed their commands straight in here
+  ///
+  fn process_command(&mut self, command: Command) {
+
+    // First, make an assumption that the player is affecting their movement as 90% of the game
+    // is walking around. 
+    let old_pos = self.world.player.actor.pos;
+
+    // In addition, update the game state
+    // Important so that if the game state becomes debug we can leave that state instantly
+    self.state = State::Keypress;
+
+    match command {
+
+      // Movement
+      Command::MoveWest      => self.move_player(-1,  0),
+      Command::MoveSouth     => self.move_player( 0,  1),
+      Command::MoveNorth     => self.move_player( 0, -1),
+      Command::MoveEast      => self.move_player( 1,  0),
+      Command::MoveNorthWest => self.move_player(-1, -1),
+      Command::MoveNorthEast => self.move_player( 1, -1),
+      Command::MoveSouthWest => self.move_player(-1,  1),
+      Command::MoveSouthEast => self.move_player( 1,  1),
+
+      // Wait
+      Command::Wait => { 
+        self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
+        self.world.player.state = Actions::Wait;
+      },
 
-              // Force reload word
-              'w' => {
-                if self.wizard {
-                  log!("You remold the earth like clay." , RGB(255, 0, 0));
-                  self.world = World::new(Pos::from_tup(init::map_dimensions()));
-                }
-                self.state = State::Act(Actions::Unknown);
-              },
-              // Create an empty level for testing
-              'q' => {
-                if self.wizard {
-                  log!("You empty the universe.", RGB(255, 0, 0));
-                  self.world.test_empty();
-                }
-                self.state = State::Act(Actions::Unknown);
-              },
-              // Wait
-              '.' => { 
-                self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
-                self.world.player.state = Actions::Wait;
-              },
-
-              // Debug keypresses
-
-              // Toggle scent
-              'r' => {
-                if self.wizard {
-                  match self.ren.show_scent {
-                    true => log!("Your vision returns to normal.", RGB(255, 0, 0)),
-                    false => log!("Your eyes perceive scent like light.", RGB(255, 0, 0))
-                  }
-                  self.ren.show_scent = !self.ren.show_scent;
-                  self.ren.draw_all(&mut self.root, &mut self.world);
-                }
-                self.state = State::Debug;
-              },
-
-              // Toggle sound
-              't' => {
-                if self.wizard {
-                  match self.ren.show_sound {
-                    true => log!("Your vision returns to normal.", RGB(255, 0, 0)),
-                    false => log!("Your eyes perceive sound like light.", RGB(255, 0, 0))
-                  }
-                  self.ren.show_sound = !self.ren.show_sound;
-                  self.ren.draw_all(&mut self.root, &mut self.world);
-                }
-                self.state = State::Debug;
-              },
-
-              // Toggle FoV
-              'f' => {
-                if self.wizard {
-                  match self.ren.fov {
-                    true => log!("Your third eye opens, revealing the universe.", RGB(255, 0, 0)),
-                    false => log!("Your third eye closes, concealing the universe in fog.", RGB(255, 0, 0))
-                  }
-                  self.ren.fov = !self.ren.fov;
-                  self.ren.draw_all(&mut self.root, &mut self.world);
-                }
-                self.state = State::Debug;
-              },
-
-              // Toggle noclip
-              'z' => {
-                if self.wizard {
-                  match self.noclip {
-                    true => log!("You form becomes tangible.", RGB(255, 0, 0)),
-                    false => log!("Your form becomes ethereal.", RGB(255, 0, 0))
-                  }
-                  self.noclip = !self.noclip;
-                }
-                self.state = State::Debug;
-              },
-
-              // Tcod test
-              'c' => {
-                self.ren.tcod_test(&mut self.root);
-                self.state = State::Debug;
-              },
-
-              // Make image
-              'p' => {
-                let name = self.world.debug_make_png_of_map();
-                log!("You take a screenshot.", RGB(200, 200, 200));
-                // So, String -> &'static str... involves leaking the memory of String.
-                // Kind of wild, gets the job done though, wish there was a better way to do this
-                let name_msg = format!("Image saved as {}", name);
-                log!(Box::leak(name_msg.into_boxed_str()), RGB(200, 200, 200));
-              }
+      // Pick up items
+      Command::Pickup => {
+        self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
+        if self.world.player_pickup() {
+          self.world.player.state = Actions::Pickup;
+        } else {
+          self.world.player.state = Actions::Unknown;
+        }
+      },
+
+      // Look at inventory
+      Command::Inventory => {
+        self.inventory_screen("You are carrying:");
+        self.world.player.state = Actions::Unknown;
+      },
+
+      // Drop an item
+      Command::Drop => {
+        self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
+        self.world.player.state = Actions::Unknown;
+        if let Some(idx) = self.inventory_screen("Drop what?") {
+          if self.world.player_drop(idx) {
+            self.world.player.state = Actions::Drop;
+          }
+        }
+      },
 
-              // Unbound key, so we just say we don't know what the player did
-              _ => { self.world.player.state = Actions::Unknown }
-              
+      // Open a door, or try to force a locked one
+      Command::Open => {
+        self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
+        self.world.player.state = Actions::Unknown;
+        if let Some(pos) = self.pick_door(&[tile::Door::Closed, tile::Door::Locked], "Open in which direction?") {
+          if self.world.player_open(pos) {
+            self.world.player.state = Actions::Open;
+          }
+        }
+      },
+
+      // Close a door
+      Command::Close => {
+        self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
+        self.world.player.state = Actions::Unknown;
+        if let Some(pos) = self.pick_door(&[tile::Door::Open], "Close in which direction?") {
+          if self.world.player_close(pos) {
+            self.world.player.state = Actions::Close;
+          }
+        }
+      },
+
+      // Throw something at a tile
+      Command::Throw => {
+        self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
+        self.world.player.state = Actions::Unknown;
+        if let Some(idx) = self.inventory_screen("Throw what?") {
+          if let Some(target) = self.pick_target("Throw where? (move the cursor, enter to throw)", Command::Throw) {
+            if self.world.player_throw(idx, target) {
+              self.world.player.state = Actions::Throw;
             }
+          }
+        }
+      },
 
+      // Fire a launcher at a tile
+      Command::Fire => {
+        self.world.player.actor.prev_pos = self.world.player.actor.pos.clone();
+        self.world.player.state = Actions::Unknown;
+        if self.world.player_ammo().is_none() {
+          log!("You have nothing to fire", RGB(150, 150, 150));
+        } else if let Some(target) = self.pick_target("Fire where? (move the cursor, enter to fire)", Command::Fire) {
+          if self.world.player_fire(target) {
+            self.world.player.state = Actions::Throw;
           }
+        }
+      },
 
-          // Now the game state needs to be properly re-oriented based on the (potential) player action.
-          // In addition, we should also process the action of the player while we're here
-          match self.state {
+      // Smell the air for what's around, which doesn't take any time
+      Command::Sniff => {
+        self.world.player.state = Actions::Unknown;
+        self.world.player_sniff();
+      },
 
-            // If state is Debug, don't override
-            State::Debug => (),
+      // Go downstairs (if possible)
+      Command::Descend => { self.world.player.state = Actions::DownStair },
+      // Go upstairs (if possible)
+      Command::Ascend => { self.world.player.state = Actions::UpStair },
 
-            _ => {
+      // Ask the player if they really want to quit
+      Command::Quit => {
+        self.world.player.state = Actions::Unknown;
+        self.state = State::Quit;
+      },
 
-              // Set game state to player state
-              self.state = State::Act(self.world.player.state.clone());
+      // Wizard commands
+
+      // Force reload word
+      Command::Regenerate => {
+        log!("You remold the earth like clay." , RGB(255, 0, 0));
+        // Draw the new seed from the current world so that the remolded world is still reproducible
+        let seed = self.world.rng.gen();
+        let map_dim = self.world.floor.dun.get_bounds_pos();
+        let character = self.world.character.clone();
+        self.world = World::new(map_dim, seed);
+        self.world.set_character(character);
+        self.world.player.state = Actions::Unknown;
+      },
 
-              // Now let's process the player's action
-              match self.world.player.state {
+      // Create an empty level for testing
+      Command::Empty => {
+        log!("You empty the universe.", RGB(255, 0, 0));
+        self.world.test_empty();
+        self.world.player.state = Actions::Unknown;
+      },
 
-                Actions::Move => {
+      // Toggle scent
+      Command::ToggleScent => {
+        match self.ren.show_scent {
+          true => log!("Your vision returns to normal.", RGB(255, 0, 0)),
+          false => log!("Your eyes perceive scent like light.", RGB(255, 0, 0))
+        }
+        self.ren.show_scent = !self.ren.show_scent;
+        self.ren.draw_all(&mut self.screen, &mut self.world);
+        self.state = State::Debug;
+      },
 
-                  // Make sure player doesn't do anything dumb
-                  if !self.world.is_valid_pos(self.world.player.actor.pos.x, self.world.player.actor.pos.y) && !self.noclip {
-                    self.world.player.actor.pos = old_pos;
-                    self.world.player.state = Actions::Unknown;
-                  // Otherwise if the position is valid...
-                  } else {
-                    // Empty for right now
-                  }
+      // Toggle sound
+      Command::ToggleSound => {
+        match self.ren.show_sound {
+          true => log!("Your vision returns to normal.", RGB(255, 0, 0)),
+          false => log!("Your eyes perceive sound like light.", RGB(255, 0, 0))
+        }
+        self.ren.show_sound = !self.ren.show_sound;
+        self.ren.draw_all(&mut self.screen, &mut self.world);
+        self.state = State::Debug;
+      },
 
-                },
+      // Toggle FoV
+      Command::ToggleFov => {
+        match self.ren.fov {
+          true => log!("Your third eye opens, revealing the universe.", RGB(255, 0, 0)),
+          false => log!("Your third eye closes, concealing the universe in fog.", RGB(255, 0, 0))
+        }
+        self.ren.fov = !self.ren.fov;
+        self.ren.draw_all(&mut self.screen, &mut self.world);
+        self.state = State::Debug;
+      },
+
+      // Toggle noclip
+      Command::ToggleNoclip => {
+        match self.noclip {
+          true => log!("You form becomes tangible.", RGB(255, 0, 0)),
+          false => log!("Your form becomes ethereal.", RGB(255, 0, 0))
+        }
+        self.noclip = !self.noclip;
+        self.state = State::Debug;
+      },
+
+      // Tcod test
+      Command::TcodTest => {
+        if let Screen::Window(ref mut root) = self.screen {
+          self.ren.tcod_test(root);
+        }
+        self.state = State::Debug;
+      },
 
-                Actions::Pickup => {
-                  // Drain AP
-                },
+      // Make image
+      Command::Screenshot => {
+        let name = self.world.debug_make_png_of_map();
+        log!("You take a screenshot.", RGB(200, 200, 200));
+        log!(format!("Image saved as {}", name), RGB(200, 200, 200));
+        self.state = State::Debug;
+      }
 
-                _ => ()
+    }
 
+    // Now the game state needs to be properly re-oriented based on the (potential) player action.
+    // In addition, we should also process the action of the player while we're here
+    match self.state {
+
+      // If state is Debug or Quit, don't override
+      State::Debug | State::Quit => (),
+
+      _ => {
+
+        // Set game state to player state
+        self.state = State::Act(self.world.player.state.clone());
+
+        // Now let's process the player's action
+        match self.world.player.state {
+
+          Actions::Move => {
+
+            // Confused players don't always go where they meant to
+            if !self.noclip && self.world.player.statuses.has(Effect::Confused) {
+              let (to, costs) = (self.world.player.actor.pos, self.world.player.stats.movement);
+              self.world.player.actor.pos = self.world.stumble(old_pos, to, &costs);
+            }
+
+            // Walking into a creature attacks it instead of moving
+            let target = self.world.player.actor.pos;
+            if self.world.player_attack(target) {
+              self.world.player.actor.pos = old_pos;
+              self.world.player.state = Actions::Attack;
+              self.state = State::Act(Actions::Attack);
+            } else {
+
+              // Unless it's peaceful, in which case the two trade places and the player carries on with their move
+              let swapped = self.world.player_swap(old_pos, target);
+
+              // Walking into a closed door opens it
+              if !swapped && !self.noclip && self.world.door_at(target) == Some(tile::Door::Closed) {
+                self.world.player.actor.pos = old_pos;
+                self.world.player.actor.prev_pos = old_pos;
+                self.world.player_open(target);
+                self.world.player.state = Actions::Open;
+                self.state = State::Act(Actions::Open);
+              // Make sure player doesn't do anything dumb
+              } else if !swapped && !self.noclip && !self.world.is_valid_pos(target.x, target.y) {
+                if self.world.door_at(target) == Some(tile::Door::Locked) {
+                  log!("The door is locked", RGB(150, 150, 150));
+                }
+                self.world.player.actor.pos = old_pos;
+                self.world.player.state = Actions::Unknown;
+              // The move went through, and swapping already took care of who stands where
+              } else if !swapped {
+                self.world.floor.occupancy.move_occupant(old_pos, target);
               }
 
             }
-          }
 
-        } 
-        
-        // Prints keycode to console in case if you're trying to find a key that isn't intuitive, or you're debugging
-        // else {
-        //   println!("{:?}", keypress.code);
-        // }
+          },
 
-      }
+          _ => ()
 
+        }
+
+      }
     }
     
   }
@@ -385,27 +646,35 @@ impl Engine {
   /// Return a new `Engine`
   /// 
   pub fn new() -> Self {
+    Engine::with_screen(Screen::Window(init::root()), Pos::from_tup(init::map_dimensions()), init::seed())
+  }
 
-    // Get map height
-    let map_dim = Pos::from_tup(init::map_dimensions());
+  ///
+  /// Return a new `Engine` that draws on `screen`, with a world of size `map_dim` made from `seed`
+  ///
+  fn with_screen(screen: Screen, map_dim: Pos, seed: usize) -> Self {
 
-    // Get root console
-    let root = init::root();
+    let screen_dim = screen.size();
 
     Engine {
 
-      world: World::new(map_dim),
+      world: World::new(map_dim, seed),
 
       state: State::New,
 
       ren: Renderer::new(
         map_dim, 
-        Pos::new(root.width() as isize, root.height() as isize), 
+        screen_dim, 
         init::console_height(),
         init::panel_width()
       ),
       
-      root,
+      screen,
+
+      keymap: init::keymap(),
+
+      recorder: None,
+      playback: None,
 
       // Debug 
       noclip: false,
@@ -422,15 +691,20 @@ impl Engine {
 
     match self.state {
 
-      // Player moving or waiting prompts a world update
-      State::Act(Actions::Move) | State::Act(Actions::Wait) => self.world.update(),
+      // Player doing anything that takes time prompts a world update
+      State::Act(Actions::Move) | State::Act(Actions::Wait) | State::Act(Actions::Attack) | State::Act(Actions::Pickup) |
+      State::Act(Actions::Drop) | State::Act(Actions::Open) | State::Act(Actions::Close) |
+      State::Act(Actions::Throw) => self.world.update(),
 
       // Trying to go up and downstairs prompts the respective response from world
       State::Act(Actions::DownStair) => {
         // No clip through floors
         if self.noclip {
-          log!("You sink into the floor.", RGB(255, 150, 150));
-          self.world.go_down();
+          if self.world.go_down() {
+            log!("You sink into the floor.", RGB(255, 150, 150));
+          } else {
+            log!("There is nothing below to sink into.", RGB(255, 150, 150));
+          }
         } else {
           self.world.player_go_down();
         }
@@ -453,19 +727,165 @@ impl Engine {
     
   }
 
+  ///
+  /// Show the player's inventory on top of the game until a key is pressed
+  ///
+  /// Items are listed with letters, so the index of the item whose letter was pressed is returned, if there is one
+  ///
+  fn inventory_screen(&mut self, prompt: &str) -> Option<usize> {
+
+    // Replays already know what was picked
+    if let Some(ref mut playback) = self.playback {
+      return playback.next_item();
+    }
+
+    self.ren.draw_all(&mut self.screen, &mut self.world);
+    self.ren.draw_inventory(&mut self.screen, &self.world.player, prompt);
+
+    let keypress = self.wait_for_keypress();
+
+    let item = match keypress.printable {
+      'a'..='z' => {
+        let idx = keypress.printable as usize - 'a' as usize;
+        if idx < self.world.player.inventory.len() {
+          Some(idx)
+        } else {
+          None
+        }
+      },
+      _ => None
+    };
+
+    self.record(Event::Item(item));
+
+    return item;
+
+  }
+
+  ///
+  /// Ask the player if they really want to quit, and if they want to save before they go
+  ///
+  /// A save that fails keeps the player in the game, so they can decide what to do about it
+  ///
+  fn quit_prompt(&mut self) {
+
+    self.ren.draw_all(&mut self.screen, &mut self.world);
+    self.ren.draw_text_box(&mut self.screen, "Really quit?", RGB(255, 255, 200), &[
+      "y - Save and quit".to_string(),
+      "n - Quit without saving".to_string(),
+      "Anything else - Keep playing".to_string()
+    ]);
+
+    let keypress = self.wait_for_keypress();
+
+    match keypress.printable {
+      'y' => if let Err(err) = save::save(&mut self.world) {
+        log!(err.to_string(), RGB(255, 0, 0));
+        self.state = State::Keypress;
+        return;
+      },
+      'n' => (),
+      _ => {
+        self.state = State::Keypress;
+        return;
+      }
+    }
+
+    // Replays stop where the player quit
+    self.record(Event::Command(Command::Quit));
+    self.state = State::Title;
+
+  }
+
+  ///
+  /// See if the game is over, either because the player died or because they made it deep enough to win
+  ///
+  fn check_ending(&mut self) {
+    if self.world.player.state == Actions::Die {
+      log!("You die...", RGB(255, 0, 0));
+      self.state = State::Dead;
+    } else if self.world.floor_num >= init::victory_depth() {
+      log!("You have reached the bottom of the dungeon!", RGB(255, 255, 0));
+      self.state = State::Won;
+    }
+  }
+
+  ///
+  /// Sum up how the game went and wait for the player to move on
+  ///
+  /// Games that are over can't be continued, so the save goes along with them
+  ///
+  fn end_screen(&mut self) {
+
+    // Deepest floor the player set foot on, which may not be the one they ended on
+    let depth = self.world.floor_stack.iter().map(|floor| floor.dun.depth).max().unwrap_or(self.world.floor_num);
+
+    let (title, color, ending) = match self.state {
+      State::Won => ("You win!", RGB(255, 255, 0), "Escaped the dungeon".to_string()),
+      _ => (
+        "You die...",
+        RGB(255, 0, 0),
+        format!("{} on floor {}", self.world.cause_of_death.clone().unwrap_or_else(|| "Died".to_string()), self.world.floor_num)
+      )
+    };
+
+    let lines = [
+      ending,
+      format!("Character: {}", self.world.character.title()),
+      format!("Deepest floor: {}", depth),
+      format!("Turns: {}", self.world.turn),
+      format!("AU: {}", self.world.player.wallet),
+      format!("World seed: {}", self.world.seed),
+      String::new(),
+      "Press any key to return to the title screen".to_string()
+    ];
+
+    if let Err(err) = save::delete() {
+      log!(err.to_string(), RGB(255, 0, 0));
+    }
+
+    self.ren.draw_all(&mut self.screen, &mut self.world);
+    self.ren.draw_text_box(&mut self.screen, title, color, &lines);
+    self.wait_for_keypress();
+
+    self.state = State::Title;
+
+  }
+
+  ///
+  /// Throw away the game that just ended and make a new world for the next one
+  ///
+  fn reset(&mut self) {
+
+    let map_dim = self.world.floor.dun.get_bounds_pos();
+    self.world = World::new(map_dim, init::seed());
+    self.state = State::New;
+    self.recorder = None;
+    self.noclip = false;
+
+    GlobalLog.lock().unwrap().data.clear();
+
+  }
+
   ///
   /// Title screen test
   ///
-  fn title_screen(&mut self) {
+  fn title_screen(&mut self) -> TitleChoice {
+
+    // There's no title screen without a window
+    let root = match self.screen {
+      Screen::Window(ref mut root) => root,
+      Screen::Headless(_) => return TitleChoice::NewGame
+    };
 
     // First part of this pretty much just fills the screen with black
 
-    let w = self.root.width();
-    let h = self.root.height();
+    let w = root.width();
+    let h = root.height();
 
     for x in 0..w {
       for y in 0..h {
-        self.root.put_char_ex(
+        root.put_char_ex(
           x as i32,
           y as i32,
           ' ',
@@ -479,39 +899,172 @@ impl Engine {
 
     let title = "Edgequest";
     let subtitle = "Press any key to start.";
+    let continue_msg = "Press c to continue.";
+
+    // Only offer to continue if there is something to continue from
+    let can_continue = save::exists();
 
     // Image blit test
-    image::blit_2x(&image::Image::from_file(Path::new("./media/bigman.png")).unwrap(), (0, 0), (-1, -1), &mut self.root, (80, 22));
-    image::blit_2x(&image::Image::from_file(Path::new("./media/guyman.png")).unwrap(), (0, 0), (-1, -1), &mut self.root, (5, 22));
+    image::blit_2x(&image::Image::from_file(Path::new("./media/bigman.png")).unwrap(), (0, 0), (-1, -1), &mut *root, (80, 22));
+    image::blit_2x(&image::Image::from_file(Path::new("./media/guyman.png")).unwrap(), (0, 0), (-1, -1), &mut *root, (5, 22));
 
-    self.root.set_default_foreground(RGB(255, 255, 255).to_tcod());
+    root.set_default_foreground(RGB(255, 255, 255).to_tcod());
     // i32 conversion is a pain since I'd rather store stuff as isize and the tcod lib wants i32 since it's
     // pretty much just a C++ interface which is annoying
-    self.root.print((w / 2 - (title.len() / 2) as i32) as i32, (h / 3 - 1) as i32, title);
-    self.root.print((w / 2 - (subtitle.len() / 2) as i32) as i32, (h / 3 + 1) as i32, subtitle);
+    // `Backend` has a print of its own, so tcod's is asked for by name
+    Console::print(&mut *root, (w / 2 - (title.len() / 2) as i32) as i32, (h / 3 - 1) as i32, title);
+    Console::print(&mut *root, (w / 2 - (subtitle.len() / 2) as i32) as i32, (h / 3 + 1) as i32, subtitle);
+    if can_continue {
+      Console::print(&mut *root, (w / 2 - (continue_msg.len() / 2) as i32) as i32, (h / 3 + 2) as i32, continue_msg);
+    }
 
-    self.root.flush();
+    root.flush();
 
     // Wait for keypress
-    let keypress = self.root.wait_for_keypress(true);
+    let keypress = root.wait_for_keypress(true);
 
     // Escape on title should quit the game
-    if let input::KeyCode::Escape = keypress.code { panic!("Bye") }
+    if let input::KeyCode::Escape = keypress.code { return TitleChoice::Exit }
+
+    // Continue from the save file
+    if can_continue && keypress.printable == 'c' {
+      match save::load() {
+        Ok(world) => {
+          self.world = world;
+          log!("You pick up where you left off.", RGB(255, 255, 200));
+          return TitleChoice::Continue;
+        },
+        // Just start a new game if it can't be loaded
+        Err(err) => log!(err.to_string(), RGB(255, 0, 0))
+      }
+    }
+
+    return TitleChoice::NewGame;
+
+  }
+
+  ///
+  /// Ask the player to pick one of `count` options listed in `lines`, by letter
+  ///
+  /// Keys that don't pick anything are ignored. Gives up with nothing picked if the window is closed
+  ///
+  fn pick_from(&mut self, title: &str, lines: &[String], count: usize) -> Option<usize> {
+
+    while !self.window_closed() {
+
+      self.screen.clear();
+      self.ren.draw_text_box(&mut self.screen, title, RGB(255, 255, 200), lines);
+
+      let keypress = self.wait_for_keypress();
+      if let 'a'..='z' = keypress.printable {
+        let idx = keypress.printable as usize - 'a' as usize;
+        if idx < count {
+          return Some(idx);
+        }
+      }
+
+    }
+
+    return None;
 
   }
 
+  ///
+  /// Have the player make their character, first picking a race and then a background
+  ///
+  /// Without a window there's nobody to ask, so the first of each is taken
+  ///
+  fn create_character(&mut self) -> Character {
+
+    if let Screen::Headless(_) = self.screen {
+      return Character::default();
+    }
+
+    let mut lines = vec![];
+    for (idx, race) in RACES.iter().enumerate() {
+      lines.push(format!("{} - {}: {}", (b'a' + idx as u8) as char, race.name, race.description));
+      lines.push(format!(
+        "    PER {} OLF {} FRT {} AGI {} REA {} INS {}, smells {}",
+        race.perception, race.olfaction, race.fortitude, race.agility, race.reason, race.insight,
+        race.scent.to_string().to_lowercase()
+      ));
+    }
+    let race = self.pick_from("Pick a race", &lines, RACES.len()).unwrap_or(0);
+
+    let mut lines = vec![];
+    for (idx, background) in BACKGROUNDS.iter().enumerate() {
+      lines.push(format!("{} - {}: {}", (b'a' + idx as u8) as char, background.name, background.description));
+      lines.push(format!("    Sense {:+} Body {:+} Mind {:+}", background.sense, background.body, background.mind));
+    }
+    let background = self.pick_from("Pick a background", &lines, BACKGROUNDS.len()).unwrap_or(0);
+
+    return Character { race: RACES[race].id.clone(), background: BACKGROUNDS[background].id.clone() };
+
+  }
+
+  ///
+  /// Check if the player closed the window
+  ///
+  fn window_closed(&self) -> bool {
+    match self.screen {
+      Screen::Window(ref root) => root.window_closed(),
+      Screen::Headless(_) => false
+    }
+  }
+
   ///
   /// Play the game.
+  ///
+  /// Every game that ends, whether the player quit, died or won, goes back to the title screen. Leaving from the title
+  /// screen or closing the window is the only way out
   /// 
   pub fn play(&mut self) {
-    
-    // Create the title screen
-    self.title_screen();
+
+    loop {
+
+      // Create the title screen, then start recording if this is a new game. Saved games can't be played back since
+      // they weren't made from their seed alone
+      match self.title_screen() {
+        TitleChoice::Exit => return,
+        TitleChoice::Continue => (),
+        TitleChoice::NewGame => {
+          let character = self.create_character();
+          self.world.set_character(character);
+          let map_dim = self.world.floor.dun.get_bounds_pos();
+          match Recorder::create(replay::REPLAY_PATH, self.world.seed, map_dim, &self.world.character) {
+            Ok(recorder) => self.recorder = Some(recorder),
+            Err(err) => log!(err.to_string(), RGB(255, 0, 0))
+          }
+        }
+      }
+
+      self.play_game();
+
+      if self.window_closed() {
+        return;
+      }
+
+      self.reset();
+
+    }
+
+  }
+
+  ///
+  /// Play a single game until it's over
+  ///
+  fn play_game(&mut self) {
 
     // Some starting messages, will be removed in later versions (hopefully)
     log!("Welcome to Edgequest",                       RGB(255,   0, 255));
-    log!("Move with vim keys, g to pick up items",     RGB(255, 255, 255));
-    log!("esc to quit",                                RGB(255, 255, 255));
+    log!("Move with vim keys, numpad or arrows",       RGB(255, 255, 255));
+    log!("g to pick up items",                         RGB(255, 255, 255));
+    log!("i to look at your inventory, d to drop",     RGB(255, 255, 255));
+    log!("Walk into creatures to attack them",         RGB(255, 255, 255));
+    log!("T to throw things, F to fire a sling",       RGB(255, 255, 255));
+    log!("s to sniff the air for nearby scents",       RGB(255, 255, 255));
+    log!("esc to quit, keys can be changed in config/keys.yml", RGB(255, 255, 255));
+    log!(format!("World seed: {}", self.world.seed), RGB(150, 150, 150));
 
     if self.wizard {
       log!("You are in wizard mode",                   RGB(255,   0,   0));
@@ -525,10 +1078,10 @@ impl Engine {
     self.update();
 
     // Draw all and capture keypresses
-    while !self.root.window_closed() {
+    while !self.window_closed() {
 
       // Draw what the camera sees
-      self.ren.draw_all(&mut self.root, &mut self.world);
+      self.ren.draw_all(&mut self.screen, &mut self.world);
       
       // Capture game keys (Keys that change the state of the player)
       // This is what gives it the turn based nature, i.e. waits for player input before
@@ -536,16 +1089,112 @@ impl Engine {
       //
       // We save input to keypress because directly sending the root back into the engine 
       // causes it to be mutably borrowed more than once
-      let keypress = self.root.wait_for_keypress(true);
+      let keypress = self.wait_for_keypress();
       self.process_keypress(keypress);
 
       // Update engine based on state
       self.update();
 
+      // Game over
+      self.check_ending();
+
+      match self.state {
+        State::Quit => self.quit_prompt(),
+        State::Dead | State::Won => self.end_screen(),
+        _ => ()
+      }
+
+      if let State::Title = self.state {
+        break;
+      }
+
     } 
 
   }
 
+  ///
+  /// Return a new `Engine` that plays back `playback` on `screen` instead of taking input
+  ///
+  fn with_playback(screen: Screen, playback: Playback) -> Self {
+    let mut engine = Engine::with_screen(screen, playback.map_dim, playback.seed);
+    engine.world.set_character(playback.character.clone());
+    engine.playback = Some(playback);
+    return engine;
+  }
+
+  ///
+  /// Play the next command of the replay
+  ///
+  /// Returns `false` once there's nothing left to play, because the replay ran out or the player quit, died or won
+  ///
+  fn play_next(&mut self) -> bool {
+
+    // Quitting would save over the player's own game, so replays just stop there
+    let command = match self.playback.as_mut().and_then(|playback| playback.next_command()) {
+      Some(Command::Quit) | None => return false,
+      Some(command) => command
+    };
+
+    self.process_command(command);
+    self.update();
+
+    self.check_ending();
+    match self.state {
+      State::Dead | State::Won => false,
+      _ => true
+    }
+
+  }
+
+  ///
+  /// Play back a replay instead of taking input
+  ///
+  /// Commands from the replay go through the same paths keypresses do, and the world is drawn after every one of them
+  /// so that bugs in drawing turn up too. Playback stops when the replay runs out, the player quits, dies or wins, or the
+  /// world reaches the turn to stop at. Without a window, the last frame is printed once it's over
+  ///
+  pub fn watch(options: replay::Options) {
+
+    let playback = Playback::load(&options.path).unwrap_or_else(|err| panic!("{}: {}", options.path, err));
+
+    let screen = if options.headless {
+      Screen::Headless(Headless::new(Pos::from_tup(init::screen_dimensions())))
+    } else {
+      Screen::Window(init::root())
+    };
+
+    let mut engine = Engine::with_playback(screen, playback);
+
+    log!(format!("Playing back {}, world seed: {}", options.path, engine.world.seed), RGB(150, 150, 150));
+
+    while !engine.window_closed() {
+
+      engine.ren.draw_all(&mut engine.screen, &mut engine.world);
+
+      if options.stop_at.map_or(false, |turn| engine.world.turn >= turn) {
+        break;
+      }
+
+      if !engine.play_next() {
+        break;
+      }
+
+      if !options.headless && !options.fast {
+        thread::sleep(Duration::from_millis(PLAYBACK_DELAY_MS));
+      }
+
+    }
+
+    log!(format!("Replay stopped on turn {}", engine.world.turn), RGB(255, 255, 200));
+    engine.ren.draw_all(&mut engine.screen, &mut engine.world);
+
+    match engine.screen {
+      Screen::Window(ref mut root) => { root.wait_for_keypress(true); },
+      Screen::Headless(ref headless) => print!("{}", headless.frame_to_string())
+    }
+
+  }
+
 }
 
 impl Default for Engine {
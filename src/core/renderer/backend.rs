@@ -0,0 +1,79 @@
+//!
+//! A `Backend` is anything the `Renderer` can draw onto
+//!
+//! The renderer only ever needs to put glyphs on a grid of cells, print some text, and show the result. Keeping that
+//! behind a trait means the renderer does not care if it is drawing to a tcod window or to an in-memory buffer (see
+//! `Headless`), which is what lets the game be rendered on machines with no display.
+//!
+
+use core::tcod::{Console, console};
+
+use core::renderer::RGB;
+use core::world::dungeon::map::Pos;
+
+///
+/// Something that can be drawn to as a grid of character cells
+///
+pub trait Backend {
+
+  ///
+  /// Dimensions of the backend in cells
+  ///
+  fn size(&self) -> Pos;
+
+  ///
+  /// Blank out every cell
+  ///
+  fn clear(&mut self);
+
+  ///
+  /// Put a glyph with colors on a cell
+  ///
+  fn put(&mut self, pos: Pos, glyph: char, fg: RGB, bg: RGB);
+
+  ///
+  /// Print a string starting at a cell, leaving the backgrounds alone
+  ///
+  fn print(&mut self, pos: Pos, text: &str, fg: RGB);
+
+  ///
+  /// Present everything drawn since the last flush
+  ///
+  fn flush(&mut self);
+
+}
+
+///
+/// The tcod root console is the backend used when actually playing
+///
+/// `Console` has methods with the same names as `Backend`, so they're called explicitly here
+///
+impl Backend for console::Root {
+
+  #[inline]
+  fn size(&self) -> Pos {
+    Pos::new(Console::width(self) as isize, Console::height(self) as isize)
+  }
+
+  #[inline]
+  fn clear(&mut self) {
+    Console::clear(self);
+  }
+
+  #[inline]
+  fn put(&mut self, pos: Pos, glyph: char, fg: RGB, bg: RGB) {
+    self.put_char_ex(pos.x as i32, pos.y as i32, glyph, fg.to_tcod(), bg.to_tcod());
+  }
+
+  #[inline]
+  fn print(&mut self, pos: Pos, text: &str, fg: RGB) {
+    self.set_default_foreground(fg.to_tcod());
+    Console::print(self, pos.x as i32, pos.y as i32, text);
+  }
+
+  #[inline]
+  fn flush(&mut self) {
+    console::Root::flush(self);
+  }
+
+}
@@ -0,0 +1,135 @@
+//!
+//! Off-screen backend that renders into an in-memory character buffer
+//!
+//! Nothing here touches tcod's windowing, so the camera view, side panel and log can be rendered on a machine with no
+//! GPU or X server. Frames can then be compared against known good ones in tests, or just thrown away when running
+//! simulations.
+//!
+
+use core::renderer::{Backend, RGB};
+use core::world::dungeon::map::Pos;
+
+///
+/// A single character cell
+///
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Cell {
+  pub glyph: char,
+  pub fg: RGB,
+  pub bg: RGB
+}
+
+impl Cell {
+
+  ///
+  /// Return a blank `Cell`
+  ///
+  #[inline]
+  pub fn blank() -> Self {
+    Cell { glyph: ' ', fg: RGB(255, 255, 255), bg: RGB(0, 0, 0) }
+  }
+
+}
+
+///
+/// Headless backend
+///
+/// Cells are stored column by column like `Grid`s are, so they're indexed with `[x * h + y]`
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Headless {
+  size: Pos,
+  cells: Vec<Cell>,
+  // Copy of the cells as of the last flush, as that is what would be on screen
+  frame: Vec<Cell>,
+  // How many frames have been flushed
+  pub frames: usize
+}
+
+impl Headless {
+
+  ///
+  /// Get a cell of the last flushed frame
+  ///
+  /// Panics if `pos` is off the buffer
+  ///
+  pub fn cell(&self, pos: Pos) -> Cell {
+    assert!(self.in_bounds(pos));
+    self.frame[(pos.x * self.size.y + pos.y) as usize]
+  }
+
+  ///
+  /// Return the glyphs of the last flushed frame as a string, one line per row
+  ///
+  /// Colors are dropped, so this is what golden frames should be compared against
+  ///
+  pub fn frame_to_string(&self) -> String {
+    let mut string = String::new();
+    for y in 0..self.size.y {
+      for x in 0..self.size.x {
+        string.push(self.frame[(x * self.size.y + y) as usize].glyph);
+      }
+      // Trailing whitespace is never interesting
+      while string.ends_with(' ') {
+        string.pop();
+      }
+      string.push('\n');
+    }
+    return string;
+  }
+
+  ///
+  /// Check if a position is on the buffer
+  ///
+  #[inline]
+  fn in_bounds(&self, pos: Pos) -> bool {
+    pos.x >= 0 && pos.y >= 0 && pos.x < self.size.x && pos.y < self.size.y
+  }
+
+  ///
+  /// Return a new, blank `Headless` backend
+  ///
+  /// * `size` - `Pos` that holds the dimensions of the buffer, like the screen dimensions for a real console
+  ///
+  pub fn new(size: Pos) -> Self {
+    let cells = vec![Cell::blank(); (size.x * size.y) as usize];
+    Headless { size, cells: cells.clone(), frame: cells, frames: 0 }
+  }
+
+}
+
+impl Backend for Headless {
+
+  #[inline]
+  fn size(&self) -> Pos {
+    self.size
+  }
+
+  fn clear(&mut self) {
+    for cell in &mut self.cells {
+      *cell = Cell::blank();
+    }
+  }
+
+  fn put(&mut self, pos: Pos, glyph: char, fg: RGB, bg: RGB) {
+    // tcod silently ignores anything drawn off the console, so we do too
+    if !self.in_bounds(pos) { return; }
+    self.cells[(pos.x * self.size.y + pos.y) as usize] = Cell { glyph, fg, bg };
+  }
+
+  fn print(&mut self, pos: Pos, text: &str, fg: RGB) {
+    for (idx, glyph) in text.chars().enumerate() {
+      let npos = Pos::new(pos.x + idx as isize, pos.y);
+      if !self.in_bounds(npos) { break; }
+      let cell = &mut self.cells[(npos.x * self.size.y + npos.y) as usize];
+      cell.glyph = glyph;
+      cell.fg = fg;
+    }
+  }
+
+  fn flush(&mut self) {
+    self.frame = self.cells.clone();
+    self.frames += 1;
+  }
+
+}
@@ -12,14 +12,24 @@ use std::char;
 use core::tcod::{Console, console};
 
 use core::GlobalLog;
+use core::creature::Creature;
 use core::world::World;
+use core::world::sanity::Madness;
 use core::world::dungeon::Dungeon;
 // Used to expliclty reference constants
-use core::world::dungeon::map::{tile, Pos, Tile};
+use core::world::dungeon::map::{fov, tile, Pos, Tile};
 
 mod renderable;
 pub use self::renderable::Renderable;
 
+// Things that can be rendered to
+mod backend;
+pub use self::backend::Backend;
+
+// Render without a window
+pub mod headless;
+pub use self::headless::Headless;
+
 // Use camera privately
 mod camera;
 use self::camera::Camera;
@@ -28,6 +38,8 @@ use self::camera::Camera;
 pub mod rgb;
 pub use self::rgb::RGB;
 
+mod renderer_tests;
+
 ///
 /// Configuration
 ///
@@ -47,6 +59,18 @@ pub const DARKEN_FAC : RGB = RGB(10, 10, 10);
 // Used to lighten tiles that are in the FoV
 pub const YELLOW_FAC : RGB = RGB(27, 24, 22);
 
+// Scents weaker than this are too faint for the player to smell
+const SMELL_MIN : u8 = 10;
+
+// Most a tile gets tinted by the strongest scent on it, out of 1
+const SMELL_TINT : f32 = 0.4;
+
+// What things can look like to a mind that's going
+const HALLUCINATION_GLYPHS : [char; 12] = ['a', 'b', 'c', 'd', 'g', 'k', 's', 'w', '&', '?', '%', '~'];
+
+// Width of the inventory screen
+const INVENTORY_WIDTH : isize = 50;
+
 ///
 /// Modify a tile's fg and bg color
 ///
@@ -81,6 +105,37 @@ fn yellowish(tile: &Tile) -> Tile {
   amplify_col(tile, YELLOW_FAC)
 }
 
+///
+/// Get the strongest scent the player can smell on a tile that isn't their own, as the color to tint the tile with and
+/// how much to tint it by
+///
+fn smelled(world: &World, x: usize, y: usize) -> Option<(RGB, f32)> {
+
+  let player = &world.player;
+  if (Pos::from_usize(x, y) ^ player.actor.pos) > player.stats.smell_radius() as f32 {
+    return None;
+  }
+
+  world.floor.dun[x][y].scents.iter()
+    .filter(|scent| scent.scent_type != player.stats.scent_type && scent.val >= SMELL_MIN)
+    .max_by_key(|scent| scent.val)
+    .map(|scent| (scent.scent_type.color(), scent.val as f32 / 255.0 * SMELL_TINT))
+
+}
+
+///
+/// Make something look like something it isn't
+///
+/// Only the screen is fooled, so like the shimmer on water and crystal this uses `thread_rng()`
+///
+fn hallucinate<R: Renderable + Clone>(renderable: &R) -> R {
+  let mut rng = rand::thread_rng();
+  let mut replace = renderable.clone();
+  replace.set_glyph(*rng.choose(&HALLUCINATION_GLYPHS).unwrap());
+  replace.set_fg(RGB(rng.gen(), rng.gen(), rng.gen()));
+  return replace;
+}
+
 ///
 /// The renderer
 ///
@@ -88,6 +143,9 @@ fn yellowish(tile: &Tile) -> Tile {
 /// This will never try to draw things outside of the given dimensions due to the way it handles
 /// determining whether something should be drawn or not.
 ///
+/// Everything is drawn onto a `Backend`, which is the tcod root console when playing or a `Headless` buffer
+/// when there is no display.
+///
 pub struct Renderer {
   camera: Camera,
   screen: Pos,
@@ -103,7 +161,7 @@ impl Renderer {
   ///
   /// Render for each monster as a visible colored entity
   ///
-  fn debug_render_scent_map(&mut self, con: &mut console::Root, world: &World, dungeon: &Dungeon) {
+  fn debug_render_scent_map<B: Backend>(&mut self, con: &mut B, world: &World, dungeon: &Dungeon) {
 
     for x in 0..dungeon.width {
       for y in 0..dungeon.height {
@@ -124,7 +182,7 @@ impl Renderer {
   ///
   /// Render sound as a transparent blue entity
   ///
-  fn debug_render_sound_map(&mut self, con: &mut console::Root, dungeon: &Dungeon) {
+  fn debug_render_sound_map<B: Backend>(&mut self, con: &mut B, dungeon: &Dungeon) {
 
     for x in 0..dungeon.width {
       for y in 0..dungeon.height {
@@ -151,7 +209,7 @@ impl Renderer {
   ///
   /// Draw all.
   ///
-  pub fn draw_all(&mut self, con: &mut console::Root, world: &mut World) {
+  pub fn draw_all<B: Backend>(&mut self, con: &mut B, world: &mut World) {
     
     //
     // Console prep
@@ -191,7 +249,7 @@ impl Renderer {
   ///
   /// We need to directly manipulate the GlobalLog object so here we use the mutex lock
   ///
-  fn draw_log(&self, con: &mut console::Root) {
+  fn draw_log<B: Backend>(&self, con: &mut B) {
 
     // Mutable reference to the mutex
     let log = GlobalLog.lock().unwrap();
@@ -202,7 +260,7 @@ impl Renderer {
       let y = self.screen.y - ((log.get_last_n_messages(self.console_height as usize).len() as isize) - idx as isize);
       // Color and string is determined by the content of the slice at that index
       let color = pair.1;
-      let string = pair.0;
+      let string = &pair.0;
       let message;
 
       // Determine if the counter must be added
@@ -214,8 +272,7 @@ impl Renderer {
 
       // They are then combined to render to the screen at a specific y axis such that the most
       // recent message will appear at the bottom
-      con.set_default_foreground(color.to_tcod());
-      con.print(0, y as i32, message);
+      con.print(Pos::new(0, y), &message, color);
     }
 
     // Explicitly drop ref
@@ -223,10 +280,134 @@ impl Renderer {
 
   }
   
+  ///
+  /// Draw a creature's inventory in a box over the map
+  ///
+  /// Items are listed with the letters used to pick them. This flushes on its own since it's drawn over a finished frame
+  ///
+  pub fn draw_inventory<B: Backend>(&self, con: &mut B, creature: &Creature, prompt: &str) {
+
+    let white = RGB(255, 255, 255);
+    let grey = RGB(150, 150, 150);
+    let black = RGB(0, 0, 0);
+
+    // Room for the prompt, every item (or a line saying there aren't any), the weight and some padding
+    let lines = if creature.inventory.is_empty() { 1 } else { creature.inventory.len() as isize };
+    let height = lines + 6;
+    let corner = Pos::new(2, 2);
+
+    // Blank out the box and put a border around it
+    for x in 0..INVENTORY_WIDTH {
+      for y in 0..height {
+        let glyph = if x == 0 || x == INVENTORY_WIDTH - 1 {
+          '|'
+        } else if y == 0 || y == height - 1 {
+          '-'
+        } else {
+          ' '
+        };
+        con.put(Pos::new(corner.x + x, corner.y + y), glyph, white, black);
+      }
+    }
+
+    con.print(Pos::new(corner.x + 2, corner.y + 1), prompt, white);
+
+    if creature.inventory.is_empty() {
+      con.print(Pos::new(corner.x + 2, corner.y + 3), "Nothing", grey);
+    }
+
+    for (idx, item) in creature.inventory.iter().enumerate() {
+      let letter = (b'a' + idx as u8) as char;
+      con.print(
+        Pos::new(corner.x + 2, corner.y + 3 + idx as isize),
+        &format!("{} - {} ({})", letter, item.describe(), item.total_weight()),
+        item.get_fg()
+      );
+    }
+
+    con.print(
+      Pos::new(corner.x + 2, corner.y + height - 2),
+      &format!("{}: {}/{}", "Weight", creature.carried_weight(), creature.stats.carry_capacity()),
+      white
+    );
+
+    con.flush();
+
+  }
+
+  ///
+  /// Draw a box of text over the map, with a colored title on top
+  ///
+  /// Used for prompts and for the end of game screens. Like the inventory, this flushes on its own
+  ///
+  pub fn draw_text_box<B: Backend>(&self, con: &mut B, title: &str, color: RGB, lines: &[String]) {
+
+    let white = RGB(255, 255, 255);
+    let black = RGB(0, 0, 0);
+
+    // Wide enough for the longest line, with room for the border and some padding
+    let longest = lines.iter().map(|line| line.len()).chain(Some(title.len())).max().unwrap_or(0) as isize;
+    let width = longest + 4;
+    let height = lines.len() as isize + 4;
+    let corner = Pos::new(2, 2);
+
+    for x in 0..width {
+      for y in 0..height {
+        let glyph = if x == 0 || x == width - 1 {
+          '|'
+        } else if y == 0 || y == height - 1 {
+          '-'
+        } else {
+          ' '
+        };
+        con.put(Pos::new(corner.x + x, corner.y + y), glyph, white, black);
+      }
+    }
+
+    con.print(Pos::new(corner.x + 2, corner.y + 1), title, color);
+
+    for (idx, line) in lines.iter().enumerate() {
+      con.print(Pos::new(corner.x + 2, corner.y + 2 + idx as isize), line, white);
+    }
+
+    con.flush();
+
+  }
+
+  ///
+  /// Draw a targeting cursor over the map, along with the line from the player to it
+  ///
+  /// The cursor is somewhere on the map, so it goes through the camera like everything else. Like the inventory, this
+  /// flushes on its own since it's drawn over a finished frame
+  ///
+  pub fn draw_target<B: Backend>(&self, con: &mut B, world: &World, target: Pos, prompt: &str) {
+
+    let yellow = RGB(255, 255, 0);
+    let black = RGB(0, 0, 0);
+
+    for pos in fov::line(world.player.actor.pos, target).into_iter().skip(1) {
+
+      if !self.camera.is_in_camera(pos) { continue }
+
+      let glyph = if pos == target { 'X' } else { '*' };
+      con.put(pos + self.camera.pos, glyph, yellow, black);
+
+    }
+
+    // Blank out a line for the prompt
+    for x in 0..prompt.len() as isize {
+      con.put(Pos::new(x, 0), ' ', black, black);
+    }
+    con.print(Pos::origin(), prompt, RGB(255, 255, 255));
+
+    con.flush();
+
+  }
+
   ///
   /// Put an `Renderable` on the console
   ///
-  fn draw_renderable(&self, con: &mut console::Root, pos: Pos, renderable: &dyn Renderable) {
+  fn draw_renderable<B: Backend>(&self, con: &mut B, pos: Pos, renderable: &dyn Renderable) {
 
     // Check if it's in the camera first
     if !self.camera.is_in_camera(pos) { return }
@@ -234,12 +415,11 @@ impl Renderer {
     // New pos with respect to camera
     let pos = pos + self.camera.pos;
 
-    con.put_char_ex(
-      pos.x as i32,
-      pos.y as i32,
+    con.put(
+      pos,
       renderable.get_glyph(),
-      renderable.get_fg().to_tcod(),
-      renderable.get_bg().to_tcod()
+      renderable.get_fg(),
+      renderable.get_bg()
     );
 
   }
@@ -247,30 +427,29 @@ impl Renderer {
   ///
   /// Draw renderables with "transparent" backgrounds
   ///
-  fn draw_renderable_transparent(&self, con: &mut console::Root, pos: Pos, renderable: &dyn Renderable, world: &World) {
+  fn draw_renderable_transparent<B: Backend>(&self, con: &mut B, pos: Pos, renderable: &dyn Renderable, world: &World) {
     // Check if it's in the camera first
     if !self.camera.is_in_camera(pos) { return }
 
     // New pos with respect to camera
     let npos = pos + self.camera.pos;
   
-    con.put_char_ex(
-      npos.x as i32,
-      npos.y as i32,
+    con.put(
+      npos,
       renderable.get_glyph(),
-      renderable.get_fg().to_tcod(),
+      renderable.get_fg(),
       // Backgrounds are just inherited from the world.
       if self.fov {
-        (world.get_bg_color_at(pos) + YELLOW_FAC).to_tcod()
+        world.get_bg_color_at(pos) + YELLOW_FAC
       } else {
-        (world.get_bg_color_at(pos)).to_tcod()
+        world.get_bg_color_at(pos)
       }
     );
 
   }
 
   /// Draw scent
-  fn draw_scent(&self, con: &mut console::Root, dungeon: &Dungeon, x: usize, y: usize) {
+  fn draw_scent<B: Backend>(&self, con: &mut B, dungeon: &Dungeon, x: usize, y: usize) {
     // Pretty much just random, Player is red, bugs are green, cats are yellow and dogs are blue
     let color = RGB(
       dungeon[x][y].scents[0].val + 50 + dungeon[x][y].scents[3].val, 
@@ -297,39 +476,36 @@ impl Renderer {
   /// 
   /// NOTE: This function is super basic and is intended to be revised/removed
   /// 
-  fn draw_ui(&self, con: &mut console::Root, world: &mut World) {
+  fn draw_ui<B: Backend>(&self, con: &mut B, world: &mut World) {
     
     // Draw horizontal line to split game from the log console
     for x in 0..self.screen.x {
-      con.put_char_ex(
-        x as i32,
-        (self.screen.y - self.console_height - 1) as i32,
+      con.put(
+        Pos::new(x, self.screen.y - self.console_height - 1),
         '-',
-        RGB(255, 255, 255).to_tcod(),
-        RGB(0, 0, 0).to_tcod()
+        RGB(255, 255, 255),
+        RGB(0, 0, 0)
       );
     }
 
     // Draw horizontal line to split game from the panel
     for y in 0..(self.screen.y - self.console_height - 1) {
-      con.put_char_ex(
-        (self.screen.x - self.panel_width - 1) as i32,
-        y as i32,
+      con.put(
+        Pos::new(self.screen.x - self.panel_width - 1, y),
         '|',
-        RGB(255, 255, 255).to_tcod(),
-        RGB(0, 0, 0).to_tcod()
+        RGB(255, 255, 255),
+        RGB(0, 0, 0)
       );
     }
 
     // Pretty sure this places a piece at the intersection of the panel and console lines
     // but god damn
-    con.put_char_ex(
-      (self.screen.x - self.panel_width - 1) as i32,
-      (self.screen.y - self.console_height - 1) as i32,
+    con.put(
+      Pos::new(self.screen.x - self.panel_width - 1, self.screen.y - self.console_height - 1),
       // hyperthonk
       char::from_u32(193).unwrap(),
-      RGB(255, 255, 255).to_tcod(),
-      RGB(0, 0, 0).to_tcod()
+      RGB(255, 255, 255),
+      RGB(0, 0, 0)
     );
 
     // Tile player is on
@@ -340,77 +516,115 @@ impl Renderer {
     // 
     
     // White on black because I'm lazy
-    con.set_default_foreground(RGB(255, 255, 255).to_tcod());
+    let white = RGB(255, 255, 255);
 
     // Gotta remind people what game theyre playing
     con.print(
-      (self.screen.x - self.panel_width + 1) as i32,
-      1,
-      "Edgequest"
+      Pos::new(self.screen.x - self.panel_width + 1, 1),
+      "Edgequest",
+      white
     );
 
     // Paying my respects to a legend
     con.print(
-      (self.screen.x - self.panel_width + 1) as i32,
-      2,
-      "This is where we live"
+      Pos::new(self.screen.x - self.panel_width + 1, 2),
+      "This is where we live",
+      white
+    );
+
+    // What the player is
+    con.print(
+      Pos::new(self.screen.x - self.panel_width + 1, 3),
+      &world.character.title(),
+      white
     );
 
     // Biome
     con.print(
-      (self.screen.x - self.panel_width + 1) as i32,
-      4,
-      format!("{}: {}", "Biome", tile.biome)
+      Pos::new(self.screen.x - self.panel_width + 1, 4),
+      &format!("{}: {}", "Biome", tile.biome),
+      white
     );
 
     // Scent of non-players
     let mut non_player_scent = 0;
     for scent in &tile.scents {
-      if scent.scent_type != tile::Scent::Player { 
+      if scent.scent_type != world.player.stats.scent_type { 
         non_player_scent += scent.val; // BUG: Panics here with overflow
       }
     }
 
     con.print(
-      (self.screen.x - self.panel_width + 1) as i32,
-      5,
-      format!("{}: {}", "Non-player Scent", non_player_scent)
+      Pos::new(self.screen.x - self.panel_width + 1, 5),
+      &format!("{}: {}", "Non-player Scent", non_player_scent),
+      white
     );
 
     // Sound
     con.print(
-      (self.screen.x - self.panel_width + 1) as i32,
-      6,
-      format!("{}: {}", "Sound", tile.sound)
+      Pos::new(self.screen.x - self.panel_width + 1, 6),
+      &format!("{}: {}", "Sound", tile.sound),
+      white
     );
 
     // Tile
     con.print(
-      (self.screen.x - self.panel_width + 1) as i32,
-      7,
-      format!("{}: {}", "Tile", tile.get_id())
+      Pos::new(self.screen.x - self.panel_width + 1, 7),
+      &format!("{}: {}", "Tile", tile.get_id()),
+      white
     );
 
     // Floor number
     con.print(
-      (self.screen.x - self.panel_width + 1) as i32,
-      8,
-      format!("{}: {}", "Floor", world.floor_num)
+      Pos::new(self.screen.x - self.panel_width + 1, 8),
+      &format!("{}: {}", world.branch.name(), world.floor_num),
+      white
     );
 
     // Wallet
     con.print(
-      (self.screen.x - self.panel_width + 1) as i32,
-      9,
-      format!("{}: {}", "AU", world.player.wallet)
+      Pos::new(self.screen.x - self.panel_width + 1, 9),
+      &format!("{}: {}", "AU", world.player.wallet),
+      white
+    );
+
+    // Seed, so that it can be put in bug reports
+    con.print(
+      Pos::new(self.screen.x - self.panel_width + 1, 10),
+      &format!("{}: {}", "Seed", world.seed),
+      white
+    );
+
+    // Health
+    con.print(
+      Pos::new(self.screen.x - self.panel_width + 1, 11),
+      &format!("{}: {}", "HP", world.player.stats.health_points),
+      white
+    );
+
+    // Sanity, along with how far gone the player is
+    let madness = Madness::of(&world.player.stats);
+    con.print(
+      Pos::new(self.screen.x - self.panel_width + 1, 12),
+      &format!("SP: {} ({})", world.player.stats.sanity_points, madness.name()),
+      madness.color()
     );
 
+    // Status effects, with how many turns they have left
+    for (idx, status) in world.player.statuses.iter().enumerate() {
+      con.print(
+        Pos::new(self.screen.x - self.panel_width + 1, 13 + idx as isize),
+        &format!("{} ({})", status.effect.name(), status.turns),
+        status.effect.color()
+      );
+    }
+
   }
 
   ///
   /// Draw the contents of the world from the player's point of view
   /// 
-  fn draw_world(&mut self, con: &mut console::Root, world: &mut World) {
+  fn draw_world<B: Backend>(&mut self, con: &mut B, world: &mut World) {
 
     // Draw the world in three steps:
     //
@@ -423,6 +637,10 @@ impl Renderer {
     // Move camera to player's position
     self.camera.move_to(world.player.actor.pos);
 
+    // The less sane the player is, the more of what's on screen looks wrong
+    let chance = Madness::of(&world.player.stats).hallucination_chance();
+    let looks_wrong = || rand::thread_rng().gen_range(0, 100) < chance;
+
     //
     // Draw tiles
     //
@@ -436,6 +654,9 @@ impl Renderer {
           if world.tcod_map.is_in_fov(x as i32, y as i32) {
 
             // Update tile if possible
+            //
+            // This shimmer is purely cosmetic, so it's fine for it to use `thread_rng()` rather than the world's
+            // seeded RNG. Doing so would make the world's dice depend on what happens to be on screen.
             match &world.floor.dun[x][y].tiletype {
               tile::Type::Water => {
                 world.floor.dun[x][y].set_bg(
@@ -458,7 +679,12 @@ impl Renderer {
             }
 
             // Draw a tile slightly more vibrant than it actually is to emulate torchlight
-            self.draw_renderable(con, Pos::from_usize(x, y), &yellowish(&world.floor.dun[x][y]));
+            let lit = yellowish(&world.floor.dun[x][y]);
+            if looks_wrong() {
+              self.draw_renderable(con, Pos::from_usize(x, y), &hallucinate(&lit));
+            } else {
+              self.draw_renderable(con, Pos::from_usize(x, y), &lit);
+            }
 
             // Mark tile as seen if it's in the FoV
             world.floor.dun[x][y].seen = true;
@@ -467,8 +693,23 @@ impl Renderer {
 
           // And the tile has been seen...
           else if world.floor.dun[x][y].seen {
-            // Draw a tile, but darker
-            self.draw_renderable(con, Pos::from_usize(x, y), &darken(&world.floor.dun[x][y]));
+            // Draw a tile, but darker, tinted by whatever the player can smell on it
+            let mut dark = darken(&world.floor.dun[x][y]);
+            if let Some((color, amount)) = smelled(world, x, y) {
+              dark.bg = RGB::transition_between(&dark.bg, &color, amount);
+            }
+            self.draw_renderable(con, Pos::from_usize(x, y), &dark);
+          }
+
+          // Noses don't need line of sight, so scents show up even where the player has never been
+          else if let Some((color, amount)) = smelled(world, x, y) {
+            self.draw_renderable(con, Pos::from_usize(x, y), &Tile::new(
+              "Scent",
+              ' ',
+              RGB(255, 255, 255),
+              RGB::transition_between(&RGB(0, 0, 0), &color, amount),
+              tile::Type::Debug
+            ));
           }
 
         }
@@ -516,12 +757,23 @@ impl Renderer {
     // Draw creatures
     //
 
+    // Phantoms go under real creatures, since they aren't really there
+    for phantom in &world.phantoms {
+      if !self.fov || world.tcod_map.is_in_fov(phantom.actor.pos.x as i32, phantom.actor.pos.y as i32) {
+        self.draw_renderable_transparent(con, phantom.actor.pos, &phantom.actor, world);
+      }
+    }
+
     for creature in &world.floor.creatures {
       // If fov is on...
       if self.fov {
         // And its in the fov...
         if world.tcod_map.is_in_fov(creature.actor.pos.x as i32, creature.actor.pos.y as i32) {
-          self.draw_renderable_transparent(con, creature.actor.pos, &creature.actor, world);
+          if looks_wrong() {
+            self.draw_renderable_transparent(con, creature.actor.pos, &hallucinate(&creature.actor), world);
+          } else {
+            self.draw_renderable_transparent(con, creature.actor.pos, &creature.actor, world);
+          }
         }
       } else {
         self.draw_renderable_transparent(con, creature.actor.pos, &creature.actor, world);
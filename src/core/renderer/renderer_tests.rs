@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod tests {
+
+  use core::creature::MONSTERS;
+  use core::item;
+  use core::renderer::{Backend, Headless, Renderer};
+  use core::world::World;
+  use core::world::dungeon::map::{tile, Pos, TILES};
+
+  #[test]
+  fn test_headless_frame() {
+
+    let map_dim = Pos::new(80, 50);
+    let mut world = World::new(map_dim, 1337);
+
+    // A walled room with a few things in it, which is all the camera will show
+    for x in 0..world.floor.dun.width {
+      for y in 0..world.floor.dun.height {
+        world.floor.dun[x][y] = if x > 0 && x < 11 && y > 0 && y < 6 { tile::generic_floor() } else { tile::generic_wall() };
+      }
+    }
+    world.floor.dun[2][1] = TILES.tile("tall_grass", &tile::Biome::Dungeon);
+    world.floor.dun[9][4] = TILES.tile("down_stair", &tile::Biome::Dungeon);
+
+    world.floor.items = vec![item::from_id("rock", Pos::new(8, 2), &mut world.rng).unwrap()];
+    world.floor.creatures = vec![MONSTERS.get("ant").unwrap().create(Pos::new(3, 5))];
+    world.phantoms.clear();
+
+    // Make sure the whole room is in sight
+    world.player.actor.pos = Pos::new(5, 3);
+    world.player.stats.perception = 40;
+    world.floor.reindex(Some(world.player.actor.pos));
+    world.tcod_map = World::new_tcod_map(map_dim, &world.floor.dun);
+    world.update_fov();
+
+    // Leave just enough room next to the panel and above the log for the room
+    let screen_dim = Pos::new(23, 11);
+    let mut ren = Renderer::new(map_dim, screen_dim, 3, 10);
+    let mut headless = Headless::new(screen_dim);
+
+    ren.draw_world(&mut headless, &mut world);
+    headless.flush();
+
+    // Walls and floors are drawn as blanks
+    assert_eq!(headless.frame_to_string(), "\n  \"\n        *\n     @\n         >\n   a\n\n\n\n\n\n");
+    assert_eq!(headless.frames, 1);
+    assert!(world.floor.dun[5][3].seen);
+
+  }
+
+}
@@ -22,7 +22,7 @@ use core::tcod::colors;
 /// 
 /// Naturally since RGB colors don't exceed values of 255, the RGB struct holds 3 u8 values.
 /// 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub struct RGB (pub u8, pub u8, pub u8);
 
 impl RGB {
@@ -0,0 +1,274 @@
+//!
+//! Record games as they're played, and play them back
+//!
+//! A replay is the seed the world was made from, the character the player made, and every command the player gave, along with what they picked at any
+//! prompts those commands brought up. The world rolls all of its dice with its own seeded RNG, so feeding the same
+//! commands into a world made from the same seed plays out the exact same game, crashes included.
+//!
+//! Replays are written a line of JSON at a time as the game goes on, so everything up until a crash makes it to disk.
+//! The first line is a `Header`, and every line after it is an `Event`:
+//!
+//! ```text
+//! {"version":5,"seed":1234,"map_dim":{"x":99,"y":49},"character":{"race":"estmere","background":"seer"}}
+//! {"command":"move_north"}
+//! {"command":"drop"}
+//! {"item":0}
+//! ```
+//!
+
+// Serde
+extern crate serde_json;
+
+// Read and write files
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, prelude::*};
+use std::path::Path;
+
+use core::creature::Character;
+use core::init::keymap::Command;
+use core::world::dungeon::map::Pos;
+
+///
+/// Configuration
+///
+
+// Version of the replay format. Bump it whenever commands or events change in a way old replays can't follow
+pub const REPLAY_VERSION : u32 = 5;
+
+// Where every new game is recorded to
+pub const REPLAY_PATH : &str = "replay/last.replay";
+
+///
+/// Things that can go wrong while recording or loading a replay
+///
+#[derive(Debug)]
+pub enum ReplayError {
+  // Couldn't read or write the file
+  Io(io::Error),
+  // A line of the file isn't valid, along with the line number
+  Format(usize, serde_json::Error),
+  // Replay was made by a different version of the game
+  Version(u32),
+  // There's no header
+  Empty
+}
+
+impl fmt::Display for ReplayError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      ReplayError::Io(ref err)           => write!(fmt, "Could not access replay file: {}", err),
+      ReplayError::Format(line, ref err) => write!(fmt, "Replay file is corrupted on line {}: {}", line, err),
+      ReplayError::Version(version)      => write!(fmt, "Replay file is version {}, expected version {}", version, REPLAY_VERSION),
+      ReplayError::Empty                 => write!(fmt, "Replay file is empty")
+    }
+  }
+}
+
+impl From<io::Error> for ReplayError {
+  fn from(err: io::Error) -> Self {
+    ReplayError::Io(err)
+  }
+}
+
+///
+/// First line of a replay, with what's needed to make the world again
+///
+#[derive(Serialize, Deserialize)]
+struct Header {
+  version: u32,
+  seed: usize,
+  map_dim: Pos,
+  character: Character
+}
+
+///
+/// Something the player did
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+  // A command a key was bound to
+  Command(Command),
+  // Item picked on the inventory screen, if any
+  Item(Option<usize>),
+  // Direction picked when asked which door to use, if any
+  Direction(Option<(isize, isize)>),
+  // Tile picked with the targeting cursor, if any
+  Target(Option<Pos>)
+}
+
+///
+/// Writes events to a replay file as they happen
+///
+pub struct Recorder {
+  file: File
+}
+
+impl Recorder {
+
+  ///
+  /// Start a new replay at `path` for a world made from `seed` with a player made from `character`, replacing whatever
+  /// was there
+  ///
+  pub fn create(path: &str, seed: usize, map_dim: Pos, character: &Character) -> Result<Recorder, ReplayError> {
+
+    // Make sure the replay directory is there
+    if let Some(dir) = Path::new(path).parent() {
+      fs::create_dir_all(dir)?;
+    }
+
+    let mut recorder = Recorder { file: File::create(path)? };
+    let header = Header { version: REPLAY_VERSION, seed, map_dim, character: character.clone() };
+    // Headers and events are plain data, so they always serialize
+    recorder.write_line(&serde_json::to_string(&header).unwrap())?;
+
+    return Ok(recorder);
+
+  }
+
+  ///
+  /// Write an event to the end of the replay
+  ///
+  pub fn record(&mut self, event: Event) -> Result<(), ReplayError> {
+    self.write_line(&serde_json::to_string(&event).unwrap())
+  }
+
+  ///
+  /// Write a line out straight away, so that it's there even if the game crashes right after
+  ///
+  fn write_line(&mut self, line: &str) -> Result<(), ReplayError> {
+    self.file.write_all(line.as_bytes())?;
+    self.file.write_all(b"\n")?;
+    self.file.flush()?;
+    return Ok(());
+  }
+
+}
+
+///
+/// A replay being played back
+///
+pub struct Playback {
+  pub seed: usize,
+  pub map_dim: Pos,
+  pub character: Character,
+  events: Vec<Event>,
+  // Index of the next event to be played
+  next: usize
+}
+
+impl Playback {
+
+  ///
+  /// Load a replay from `path`
+  ///
+  /// A crash can cut the last line of a replay short, so a last line that doesn't parse is dropped instead of being an
+  /// error
+  ///
+  pub fn load(path: &str) -> Result<Playback, ReplayError> {
+
+    // Load file to String
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+
+    let lines : Vec<&str> = contents.lines().filter(|line| !line.trim().is_empty()).collect();
+
+    let header_line = match lines.first() {
+      Some(line) => line,
+      None => return Err(ReplayError::Empty)
+    };
+
+    // Check the version before trying to make sense of anything else, since the rest of the layout may have changed
+    #[derive(Deserialize)]
+    struct Version { version: u32 }
+    let version : Version = serde_json::from_str(header_line).map_err(|err| ReplayError::Format(1, err))?;
+    if version.version != REPLAY_VERSION {
+      return Err(ReplayError::Version(version.version));
+    }
+
+    let header : Header = serde_json::from_str(header_line).map_err(|err| ReplayError::Format(1, err))?;
+
+    let mut events : Vec<Event> = vec![];
+    for (idx, line) in lines.iter().enumerate().skip(1) {
+      match serde_json::from_str(line) {
+        Ok(event) => events.push(event),
+        Err(_) if idx == lines.len() - 1 => break,
+        Err(err) => return Err(ReplayError::Format(idx + 1, err))
+      }
+    }
+
+    return Ok(Playback { seed: header.seed, map_dim: header.map_dim, character: header.character, events, next: 0 });
+
+  }
+
+  ///
+  /// Get the next command to play
+  ///
+  /// Answers to prompts that come up without a command are left over from prompts that didn't come up this time
+  /// around, so they're skipped
+  ///
+  pub fn next_command(&mut self) -> Option<Command> {
+    while let Some(event) = self.events.get(self.next).cloned() {
+      self.next += 1;
+      if let Event::Command(command) = event {
+        return Some(command);
+      }
+    }
+    return None;
+  }
+
+  ///
+  /// Get the item that was picked at the inventory screen the command that was just played brought up
+  ///
+  pub fn next_item(&mut self) -> Option<usize> {
+    match self.events.get(self.next) {
+      Some(&Event::Item(item)) => {
+        self.next += 1;
+        item
+      },
+      _ => None
+    }
+  }
+
+  ///
+  /// Get the direction that was picked at the prompt the command that was just played brought up
+  ///
+  pub fn next_direction(&mut self) -> Option<(isize, isize)> {
+    match self.events.get(self.next) {
+      Some(&Event::Direction(direction)) => {
+        self.next += 1;
+        direction
+      },
+      _ => None
+    }
+  }
+
+  ///
+  /// Get the tile that was picked with the targeting cursor the command that was just played brought up
+  ///
+  pub fn next_target(&mut self) -> Option<Pos> {
+    match self.events.get(self.next) {
+      Some(&Event::Target(target)) => {
+        self.next += 1;
+        target
+      },
+      _ => None
+    }
+  }
+
+}
+
+///
+/// How a replay should be played back
+///
+pub struct Options {
+  // Replay file to play
+  pub path: String,
+  // Play without a window, as fast as possible
+  pub headless: bool,
+  // Don't wait between turns in a window
+  pub fast: bool,
+  // Stop once the world reaches this turn
+  pub stop_at: Option<usize>
+}
@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+
+  extern crate serde_json;
+  use self::serde_json::Value;
+
+  use std::env;
+
+  use core::{Engine, Screen, State};
+  use core::creature::Character;
+  use core::init::{self, keymap::Command};
+  use core::renderer::Headless;
+  use core::replay::{Event, Playback, Recorder};
+  use core::save;
+  use core::world::World;
+  use core::world::dungeon::map::Pos;
+
+  ///
+  /// Everything about a world that gets saved, other than the log, since every test shares the log
+  ///
+  fn state(world: &World) -> Value {
+    let mut value : Value = serde_json::from_str(&save::to_string(world, 0).unwrap()).unwrap();
+    value.as_object_mut().unwrap().remove("log");
+    return value;
+  }
+
+  fn headless() -> Screen {
+    Screen::Headless(Headless::new(Pos::from_tup(init::screen_dimensions())))
+  }
+
+  #[test]
+  fn test_playback_matches_recording() {
+
+    let path = env::temp_dir().join("edgequest_playback_test.replay");
+    let path = path.to_str().unwrap();
+    let (seed, map_dim) = (1337, Pos::new(80, 50));
+
+    // Play a short game the way `play()` does, drawing before every command
+    let mut engine = Engine::with_screen(headless(), map_dim, seed);
+    engine.world.set_character(Character::default());
+    engine.recorder = Some(Recorder::create(path, seed, map_dim, &engine.world.character).unwrap());
+
+    let commands = [Command::MoveNorth, Command::MoveEast, Command::Wait, Command::MoveSouthWest, Command::MoveSouth,
+                    Command::MoveWest, Command::MoveNorthEast, Command::Wait];
+    for &command in commands.iter().cycle().take(60) {
+      engine.ren.draw_all(&mut engine.screen, &mut engine.world);
+      engine.record(Event::Command(command));
+      engine.process_command(command);
+      engine.update();
+      engine.check_ending();
+      match engine.state {
+        State::Dead | State::Won => break,
+        _ => ()
+      }
+    }
+    engine.recorder = None;
+
+    // Then play it back the way `watch()` does
+    let mut replayed = Engine::with_playback(headless(), Playback::load(path).unwrap());
+    loop {
+      replayed.ren.draw_all(&mut replayed.screen, &mut replayed.world);
+      if !replayed.play_next() {
+        break;
+      }
+    }
+
+    assert!(engine.world.turn > 0);
+    assert_eq!(replayed.world.turn, engine.world.turn);
+    assert_eq!(replayed.world.player.actor.pos, engine.world.player.actor.pos);
+    assert_eq!(state(&replayed.world), state(&engine.world));
+
+  }
+
+}
@@ -0,0 +1,297 @@
+//!
+//! Save and load the whole game to and from disk
+//!
+//! Everything in the `World` that can't be rebuilt is written out: every floor in the stack (tiles, with their scents
+//! and whether or not they've been seen), the creatures on them along with their AIs, items, the player and their wallet,
+//! and the message log. The tcod FOV map is not saved since it is rebuilt from the current floor.
+//!
+//! Save files carry a version number. Whenever something that gets saved changes shape, bump `SAVE_VERSION` so that
+//! old saves are rejected with an error instead of loading into a garbage world.
+//!
+
+// Serde
+extern crate serde;
+extern crate serde_json;
+use self::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+extern crate rand;
+use self::rand::{Rng, SeedableRng, StdRng};
+
+// Read and write files
+use std::fmt;
+use std::sync::Mutex;
+use std::fs::{self, File};
+use std::io::{self, prelude::*};
+use std::path::Path;
+
+use core::creature::{Character, Creature};
+use core::creature::registry::MONSTERS;
+use core::log::GlobalLog;
+use core::renderer::RGB;
+use core::world::{Floor, World};
+use core::world::sanity::Phantom;
+use core::world::dungeon::DungeonKind;
+use core::world::dungeon::map::registry::TILES;
+
+///
+/// Configuration
+///
+
+// Version of the save format
+pub const SAVE_VERSION : u32 = 5;
+
+// Where the game is saved to
+pub const SAVE_PATH : &str = "save/edgequest.sav";
+
+// Names that aren't in any registry, like stained tiles and items, that have been loaded before
+lazy_static! {
+  static ref NAMES : Mutex<Vec<&'static str>> = Mutex::new(vec![]);
+}
+
+///
+/// Get a `&'static str` back for a name read out of a save
+///
+/// Tile and monster names are looked up in their registries. Anything else comes from a string literal somewhere in
+/// the game, so there are only a handful of them; each one is leaked the first time it is loaded and reused after
+/// that, so loading a floor doesn't cost an allocation per tile.
+///
+fn intern(name: String) -> &'static str {
+
+  if let Some(known) = TILES.name(&name).or_else(|| MONSTERS.name(&name)) {
+    return known;
+  }
+
+  let mut names = NAMES.lock().unwrap();
+  if let Some(&known) = names.iter().find(|&&known| known == name) {
+    return known;
+  }
+
+  let known : &'static str = Box::leak(name.into_boxed_str());
+  names.push(known);
+  return known;
+
+}
+
+///
+/// A `&'static str` that can survive a round trip through a save file
+///
+/// Names of tiles, actors and items are `&'static str`s, which serde can't hand back out of a file that is
+/// dropped once loading is done. So when one is loaded, it's looked up with `intern()`.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct StaticStr(pub &'static str);
+
+impl Serialize for StaticStr {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(self.0)
+  }
+}
+
+impl<'de> Deserialize<'de> for StaticStr {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<StaticStr, D::Error> {
+    String::deserialize(deserializer).map(|string| StaticStr(intern(string)))
+  }
+}
+
+///
+/// Things that can go wrong while saving or loading
+///
+#[derive(Debug)]
+pub enum SaveError {
+  // Couldn't read or write the file
+  Io(io::Error),
+  // File isn't a save, or is corrupted
+  Format(serde_json::Error),
+  // Save was made by a different version of the game
+  Version(u32)
+}
+
+impl fmt::Display for SaveError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      SaveError::Io(ref err)      => write!(fmt, "Could not access save file: {}", err),
+      SaveError::Format(ref err)  => write!(fmt, "Save file is corrupted: {}", err),
+      SaveError::Version(version) => write!(fmt, "Save file is version {}, expected version {}", version, SAVE_VERSION)
+    }
+  }
+}
+
+impl From<io::Error> for SaveError {
+  fn from(err: io::Error) -> Self {
+    SaveError::Io(err)
+  }
+}
+
+impl From<serde_json::Error> for SaveError {
+  fn from(err: serde_json::Error) -> Self {
+    SaveError::Format(err)
+  }
+}
+
+///
+/// Everything that goes into a save file
+///
+#[derive(Serialize, Deserialize)]
+struct SaveGame {
+  version: u32,
+  // Seed the world was originally created with
+  seed: usize,
+  // The RNG itself can't be serialized, so the world's RNG is reseeded with this on save and on load
+  rng_seed: usize,
+  player: Creature,
+  #[serde(default)]
+  character: Character,
+  floor: Floor,
+  floor_stack: Vec<Floor>,
+  floor_num: usize,
+  branch: DungeonKind,
+  #[serde(default)]
+  turn: usize,
+  #[serde(default)]
+  phantoms: Vec<Phantom>,
+  log: Vec<(String, RGB, u32)>
+}
+
+///
+/// Check if there is a save to continue from
+///
+pub fn exists() -> bool {
+  Path::new(SAVE_PATH).exists()
+}
+
+///
+/// Throw away the save, if there is one
+///
+/// Games that are over can't be continued, so their saves go once they end
+///
+pub fn delete() -> Result<(), SaveError> {
+  if exists() {
+    fs::remove_file(SAVE_PATH)?;
+  }
+  return Ok(());
+}
+
+///
+/// Save the world to `SAVE_PATH`
+///
+/// The world's RNG is reseeded as part of saving, so that playing on after saving behaves exactly the same as
+/// loading the save and playing on from there.
+///
+pub fn save(world: &mut World) -> Result<(), SaveError> {
+
+  // Reseed the RNG from itself so the save stays deterministic. A save that doesn't make it to disk leaves the RNG
+  // alone, so playing on is the same as if it was never tried
+  let rng_seed = world.rng.clone().gen();
+  write(&to_string(world, rng_seed)?)?;
+  world.rng = StdRng::from_seed(&[rng_seed][..]);
+
+  return Ok(());
+
+}
+
+///
+/// Write a save out to `SAVE_PATH`
+///
+fn write(contents: &str) -> Result<(), SaveError> {
+
+  // Make sure the save directory is there
+  if let Some(dir) = Path::new(SAVE_PATH).parent() {
+    fs::create_dir_all(dir)?;
+  }
+
+  let mut file = File::create(SAVE_PATH)?;
+  file.write_all(contents.as_bytes())?;
+
+  return Ok(());
+
+}
+
+///
+/// Load a world from `SAVE_PATH`, restoring the log along with it
+///
+pub fn load() -> Result<World, SaveError> {
+
+  // Load file to String
+  let mut file = File::open(SAVE_PATH)?;
+  let mut contents = String::new();
+  file.read_to_string(&mut contents)?;
+
+  return from_str(&contents);
+
+}
+
+///
+/// Write the world out to a string, where loading it seeds the RNG with `rng_seed`
+///
+pub fn to_string(world: &World, rng_seed: usize) -> Result<String, SaveError> {
+
+  // Copy out the log
+  let log = GlobalLog.lock().unwrap();
+  let messages = log.data.iter().map(|&(ref msg, col, count)| (msg.to_string(), col, count)).collect();
+  drop(log);
+
+  let save = SaveGame {
+    version: SAVE_VERSION,
+    seed: world.seed,
+    rng_seed,
+    player: world.player.clone(),
+    character: world.character.clone(),
+    floor: world.floor.clone(),
+    floor_stack: world.floor_stack.clone(),
+    floor_num: world.floor_num,
+    branch: world.branch,
+    turn: world.turn,
+    phantoms: world.phantoms.clone(),
+    log: messages
+  };
+
+  return Ok(serde_json::to_string(&save)?);
+
+}
+
+///
+/// Read a world back out of a string made by `to_string()`, restoring the log along with it
+///
+pub fn from_str(contents: &str) -> Result<World, SaveError> {
+
+  // Check the version before trying to make sense of anything else, since the rest of the layout may have changed
+  #[derive(Deserialize)]
+  struct Version { version: u32 }
+  let version : Version = serde_json::from_str(contents)?;
+  if version.version != SAVE_VERSION {
+    return Err(SaveError::Version(version.version));
+  }
+
+  let save : SaveGame = serde_json::from_str(contents)?;
+
+  // Put the old messages back into the log
+  let mut log = GlobalLog.lock().unwrap();
+  log.data = save.log.into_iter().map(|(msg, col, count)| (msg.into(), col, count)).collect();
+  drop(log);
+
+  let tcod_map = World::new_tcod_map(save.floor.dun.get_bounds_pos(), &save.floor.dun);
+
+  let mut world = World {
+    player: save.player,
+    character: save.character,
+    floor: save.floor,
+    floor_stack: save.floor_stack,
+    floor_num: save.floor_num,
+    branch: save.branch,
+    turn: save.turn,
+    // Dead players aren't saved
+    cause_of_death: None,
+    phantoms: save.phantoms,
+    tcod_map,
+    seed: save.seed,
+    rng: StdRng::from_seed(&[save.rng_seed][..])
+  };
+
+  // The occupancy index isn't saved either
+  let player_pos = world.player.actor.pos;
+  world.floor.reindex(Some(player_pos));
+  world.update_fov();
+
+  return Ok(world);
+
+}
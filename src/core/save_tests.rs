@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+
+  extern crate serde_json;
+  use self::serde_json::Value;
+
+  extern crate rand;
+  use self::rand::{Rng, SeedableRng, StdRng};
+
+  use core::save;
+  use core::world::World;
+  use core::world::dungeon::map::Pos;
+
+  ///
+  /// Turn a save into JSON without the log, since every test shares the log
+  ///
+  fn without_log(contents: &str) -> Value {
+    let mut value : Value = serde_json::from_str(contents).unwrap();
+    value.as_object_mut().unwrap().remove("log");
+    return value;
+  }
+
+  #[test]
+  fn test_round_trip() {
+
+    let mut world = World::new(Pos::new(80, 50), 1337);
+    world.go_down();
+
+    let saved = save::to_string(&world, 42).unwrap();
+    let mut loaded = save::from_str(&saved).unwrap();
+
+    assert_eq!(loaded.seed, world.seed);
+    assert_eq!(loaded.floor_num, world.floor_num);
+    assert_eq!(loaded.branch, world.branch);
+    assert_eq!(loaded.player.actor.pos, world.player.actor.pos);
+    assert_eq!(loaded.floor.creatures.len(), world.floor.creatures.len());
+    assert_eq!(loaded.floor_stack.len(), world.floor_stack.len());
+
+    // Names come back as the same strings, whether they're in a registry or not
+    assert_eq!(loaded.floor.dun[0][0], world.floor.dun[0][0]);
+
+    // Saving the loaded world again has to give the same thing
+    assert_eq!(without_log(&save::to_string(&loaded, 42).unwrap()), without_log(&saved));
+
+    // And it picks up with the RNG the save asked for
+    assert_eq!(loaded.rng.gen::<usize>(), StdRng::from_seed(&[42][..]).gen::<usize>());
+
+  }
+
+  #[test]
+  fn test_to_string_leaves_rng() {
+
+    // Only `save()` reseeds the RNG, and only once the save is on disk
+    let mut world = World::new(Pos::new(80, 50), 1337);
+    let mut rng = world.rng.clone();
+    save::to_string(&world, 42).unwrap();
+    assert_eq!(world.rng.gen::<usize>(), rng.gen::<usize>());
+
+  }
+
+  #[test]
+  fn test_wrong_version() {
+    let world = World::new(Pos::new(80, 50), 1337);
+    let saved = save::to_string(&world, 0).unwrap().replacen(&format!("\"version\":{}", save::SAVE_VERSION), "\"version\":0", 1);
+    match save::from_str(&saved) {
+      Err(save::SaveError::Version(0)) => {},
+      _ => panic!("save from another version was loaded")
+    }
+  }
+
+}
@@ -1,12 +1,108 @@
+//!
+//! Energy based turn scheduling
+//!
+//! Everything that takes turns holds some `Energy`. Time passes in ticks, and every tick things regain energy based on
+//! their speed. Once something has at least `TURN` energy it gets to act, and acting spends energy based on what was
+//! done (see `cost()`). Something twice as fast as normal then gets to act twice for every normal turn, and something
+//! half as fast acts every other turn.
+//!
 
-use core::creature::Creature;
-use core::world::dungeon::map::{self, Tile};
+extern crate rand;
+use self::rand::StdRng;
+
+use core::creature::{ai, Actions};
+use core::world::dungeon::map::path;
+
+mod time_tests;
+
+///
+/// Configuration
+///
+
+// Energy needed to act, and what a normal action costs
+pub const TURN : isize = 100;
+
+// Energy regained per tick by something of normal speed
+pub const SPEED_NORMAL : isize = 100;
+
+// Speed gained per point of agility
+const SPEED_PER_AGILITY : isize = 10;
+
+// Slowest something can be, so that everything eventually gets to act
+const SPEED_MIN : isize = 20;
 
 ///
 /// Time
-/// 
+///
 pub trait Time {
-  
-  fn take_turn(&mut self, map: &map::Grid<Tile>, player: &Creature);
 
-}
\ No newline at end of file
+  fn take_turn(&mut self, ctx: &ai::Context, paths: &mut path::Paths, rng: &mut StdRng) -> ai::Intent;
+
+  ///
+  /// Energy regained every tick
+  ///
+  fn speed(&self) -> isize {
+    SPEED_NORMAL
+  }
+
+}
+
+///
+/// Energy that is spent by acting and regained as time passes
+///
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Energy(pub isize);
+
+impl Energy {
+
+  ///
+  /// Check if there's enough energy to act
+  ///
+  #[inline]
+  pub fn ready(&self) -> bool {
+    self.0 >= TURN
+  }
+
+  ///
+  /// Regain energy for a tick
+  ///
+  #[inline]
+  pub fn gain(&mut self, speed: isize) {
+    self.0 += speed;
+  }
+
+  ///
+  /// Pay for an action
+  ///
+  #[inline]
+  pub fn spend(&mut self, action: &Actions) {
+    self.0 -= cost(action);
+  }
+
+}
+
+///
+/// How much energy an action costs
+///
+pub fn cost(action: &Actions) -> isize {
+  match *action {
+    // Blinking is tiring
+    Actions::Blink => TURN + TURN / 2,
+    // Quick actions
+    Actions::Talk | Actions::Pickup | Actions::Drop => TURN / 2,
+    // Dying is free
+    Actions::Die => 0,
+    _ => TURN
+  }
+}
+
+///
+/// Speed of a creature with some agility
+///
+pub fn speed_from_agility(agility: isize) -> isize {
+  let speed = SPEED_NORMAL + agility * SPEED_PER_AGILITY;
+  if speed < SPEED_MIN {
+    return SPEED_MIN;
+  }
+  return speed;
+}
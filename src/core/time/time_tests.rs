@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+
+  use core::creature::Actions;
+  use core::time::{self, Energy, SPEED_NORMAL, TURN};
+
+  ///
+  /// Run ticks the way the world does for something with some speed that always takes normal actions, and count how
+  /// many times it gets to act on each one
+  ///
+  fn schedule(speed: isize, ticks: usize) -> Vec<usize> {
+    let mut energy = Energy::default();
+    let mut acts = vec![];
+    for _ in 0..ticks {
+      energy.gain(speed);
+      let mut count = 0;
+      while energy.ready() {
+        energy.spend(&Actions::Move);
+        count += 1;
+      }
+      acts.push(count);
+    }
+    return acts;
+  }
+
+  #[test]
+  fn test_normal_speed() {
+    assert_eq!(schedule(SPEED_NORMAL, 4), vec![1, 1, 1, 1]);
+  }
+
+  #[test]
+  fn test_fast() {
+    assert_eq!(time::speed_from_agility(10), 200);
+    assert_eq!(schedule(200, 4), vec![2, 2, 2, 2]);
+  }
+
+  #[test]
+  fn test_slow() {
+    assert_eq!(time::speed_from_agility(-5), 50);
+    assert_eq!(schedule(50, 6), vec![0, 1, 0, 1, 0, 1]);
+  }
+
+  #[test]
+  fn test_speed_floor() {
+    // However clumsy something is, it still gets a turn eventually
+    assert!(time::speed_from_agility(-100) > 0);
+  }
+
+  #[test]
+  fn test_costs() {
+
+    assert_eq!(time::cost(&Actions::Move), TURN);
+    assert!(time::cost(&Actions::Blink) > TURN);
+    assert!(time::cost(&Actions::Pickup) < TURN);
+
+    // The dead don't pay anything, which is why the world stops giving them turns
+    assert_eq!(time::cost(&Actions::Die), 0);
+    let mut energy = Energy(TURN);
+    energy.spend(&Actions::Die);
+    assert!(energy.ready());
+
+  }
+
+}
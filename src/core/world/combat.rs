@@ -0,0 +1,193 @@
+//!
+//! Combat between creatures, driven by their `Stats`
+//!
+//! This only decides what happens when one creature swings at or shoots another, and where projectiles fly. The
+//! world is in charge of deciding when that happens, telling the player about it, and cleaning up the mess afterwards.
+//!
+
+extern crate rand;
+use self::rand::{Rng, StdRng};
+
+use std::cmp;
+
+use core::creature::{Actions, Creature, Effect};
+use core::world::dungeon::map::{self, fov, Measurable, Occupancy, Pos, tile, Tile};
+
+///
+/// Configuration
+///
+
+// Chance out of 100 to hit a creature with no evasion when both creatures are equally agile
+const BASE_HIT_CHANCE : isize = 75;
+
+// How much each point of evasion lowers the chance to be hit
+const EVASION_FAC : isize = 5;
+
+// How much each point of agility over the defender's raises the chance to hit
+const AGILITY_FAC : isize = 2;
+
+// Hits can always miss and misses can always hit
+pub const MIN_HIT_CHANCE : isize = 5;
+pub const MAX_HIT_CHANCE : isize = 95;
+
+// Highest damage roll of a creature with no fortitude
+const BASE_DAMAGE : isize = 3;
+
+// Anything hurt down to this much health gets a rush of adrenaline, and how many turns it lasts
+const ADRENALINE_HEALTH : isize = 5;
+const ADRENALINE_TURNS : usize = 5;
+
+// Chance out of 100 to hit a creature with no evasion with a projectile, before accuracy and distance
+const BASE_AIM_CHANCE : isize = 45;
+
+// How much each point of accuracy raises the chance to hit with a projectile
+const ACCURACY_FAC : isize = 3;
+
+// How much each tile a projectile flies lowers the chance to hit with it
+const DISTANCE_FAC : isize = 3;
+
+///
+/// What came of an attack
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Hit {
+  // Attack didn't connect
+  Miss,
+  // Attack connected and did some amount of damage, which can be 0 if armor soaked all of it
+  Hit(isize),
+  // Attack connected, did some amount of damage and killed the defender
+  Kill(isize)
+}
+
+///
+/// Chance out of 100 for `attacker` to hit `defender`
+///
+pub fn hit_chance(attacker: &Creature, defender: &Creature) -> isize {
+  clamp_chance(
+    BASE_HIT_CHANCE
+    + (attacker.stats.agility - defender.stats.agility) * AGILITY_FAC
+    - defender.stats.evasion_value * EVASION_FAC
+  )
+}
+
+///
+/// Chance out of 100 for a projectile from `attacker` to hit `defender` after flying `distance` tiles
+pub fn aim_chance(attacker: &Creature, defender: &Creature, distance: isize) -> isize {
+  clamp_chance(
+    BASE_AIM_CHANCE
+    + attacker.stats.accuracy() * ACCURACY_FAC
+    - distance * DISTANCE_FAC
+    - defender.stats.evasion_value * EVASION_FAC
+  )
+}
+
+///
+/// Keep a chance to hit between the lowest and highest it can be
+///
+fn clamp_chance(chance: isize) -> isize {
+  if chance < MIN_HIT_CHANCE {
+    return MIN_HIT_CHANCE;
+  } else if chance > MAX_HIT_CHANCE {
+    return MAX_HIT_CHANCE;
+  }
+
+  return chance;
+}
+
+///
+/// Have `attacker` attack `defender` in melee
+///
+/// Rolls to hit against the defender's evasion, then rolls damage which is reduced by the defender's armor.
+/// If the defender runs out of health points their state becomes `Actions::Die`.
+///
+pub fn melee(attacker: &Creature, defender: &mut Creature, rng: &mut StdRng) -> Hit {
+
+  // Roll to hit
+  if rng.gen_range(0, 100) >= hit_chance(attacker, defender) {
+    return Hit::Miss;
+  }
+
+  // Fortitude makes hits heavier
+  wound(defender, BASE_DAMAGE + attacker.stats.fortitude / 2, rng)
+
+}
+
+///
+/// Have a projectile from `attacker` that can do up to `max_damage` hit `defender` after flying `distance` tiles
+///
+/// Same as `melee()`, except that it's the attacker's senses and not their agility that decide if it hits, and the
+/// projectile and not the attacker's strength that decides how hard
+///
+pub fn ranged(attacker: &Creature, defender: &mut Creature, max_damage: isize, distance: isize, rng: &mut StdRng) -> Hit {
+
+  // Roll to hit
+  if rng.gen_range(0, 100) >= aim_chance(attacker, defender, distance) {
+    return Hit::Miss;
+  }
+
+  wound(defender, max_damage, rng)
+
+}
+
+///
+/// Roll up to `max_damage` against `defender`, which is reduced by their armor
+///
+/// Wounds that leave the defender close to death haste them for a little while
+///
+fn wound(defender: &mut Creature, max_damage: isize, rng: &mut StdRng) -> Hit {
+
+  let max_damage = cmp::max(max_damage, 1);
+  let mut damage = rng.gen_range(1, max_damage + 1) - defender.stats.armor_value;
+  if damage < 0 {
+    damage = 0;
+  }
+
+  defender.stats.health_points -= damage;
+
+  if defender.stats.health_points <= 0 {
+    defender.state = Actions::Die;
+    return Hit::Kill(damage);
+  }
+
+  // Getting badly hurt gets the heart racing
+  if damage > 0 && defender.stats.health_points <= ADRENALINE_HEALTH {
+    defender.statuses.apply(Effect::Hasted, ADRENALINE_TURNS);
+  }
+
+  return Hit::Hit(damage);
+
+}
+
+///
+/// Trace the path of a projectile from `start` to `end`, going no further than `range` tiles
+///
+/// The path follows `fov::line()`, leaving out `start`. Projectiles can't go through anything opaque or that can't be
+/// walked through, so the path stops short of those. It stops on the first tile that something is standing on,
+/// since that's what gets hit (or missed)
+///
+pub fn trace(grid: &map::Grid<Tile>, occupancy: &Occupancy, start: Pos, end: Pos, range: usize) -> Vec<Pos> {
+
+  let mut path = vec![];
+
+  for pos in fov::line(start, end).into_iter().skip(1).take(range) {
+
+    // Out of the map
+    if pos.x < 0 || pos.y < 0 || pos.x >= grid.width() as isize || pos.y >= grid.height() as isize {
+      break;
+    }
+
+    if tile::opaque(&grid[pos]) || !tile::walkable(&grid[pos]) {
+      break;
+    }
+
+    path.push(pos);
+
+    if occupancy.is_occupied(pos) {
+      break;
+    }
+
+  }
+
+  return path;
+
+}
@@ -3,7 +3,7 @@
 //! 
 
 extern crate rand;
-use self::rand::Rng;
+use self::rand::{Rng, StdRng};
 
 use core::world::dungeon::map;
 use core::world::dungeon::map::Measurable;
@@ -37,15 +37,17 @@ pub trait Automaton {
   /// It is implied that you should initialize each builder with it's `new()` method
   /// then call this function
   /// 
-  fn apply(&self, grid: &mut map::Grid<Self::Output>, starting_pos: Option<map::Pos>, find: Option<Self::Output>, replace: Self::Output, iterations: u32);
+  /// All randomness must come from `rng` so that the same seed always produces the same grid
+  /// 
+  fn apply(&self, grid: &mut map::Grid<Self::Output>, starting_pos: Option<map::Pos>, find: Option<Self::Output>, replace: Self::Output, iterations: u32, rng: &mut StdRng);
 
   /// 
   /// Automatically unwrap a `Pos` in a way thats suitable for most automata
   /// 
-  fn unwrap_pos(&self, grid: &map::Grid<Self::Output>, pos: Option<map::Pos>) -> map::Pos {
+  fn unwrap_pos(&self, grid: &map::Grid<Self::Output>, pos: Option<map::Pos>, rng: &mut StdRng) -> map::Pos {
     match pos {
       Some(pos) => pos,
-      None => map::Pos::from_usize(rand::thread_rng().gen_range(1, grid.width() - 2), rand::thread_rng().gen_range(1, grid.height() - 2))
+      None => map::Pos::from_usize(rng.gen_range(1, grid.width() - 2), rng.gen_range(1, grid.height() - 2))
     }
   } 
 
@@ -64,29 +66,29 @@ pub trait Automaton {
   ///
   /// Get chaos. Basically just a random number between 0 and 1
   /// 
-  fn get_chaos(&self) -> f32 {
-    rand::thread_rng().gen::<f32>()
+  fn get_chaos(&self, rng: &mut StdRng) -> f32 {
+    rng.gen::<f32>()
   }
 
   ///
   /// Get a d4 for cartesian movement
   /// 
-  fn get_d4(&self) -> usize {
-    rand::thread_rng().gen_range(1, 5)
+  fn get_d4(&self, rng: &mut StdRng) -> usize {
+    rng.gen_range(1, 5)
   }
 
   ///
   /// Get a d8 for all direction cartesian movement
   /// 
-  fn get_d8(&self) -> usize {
-    rand::thread_rng().gen_range(1, 9)
+  fn get_d8(&self, rng: &mut StdRng) -> usize {
+    rng.gen_range(1, 9)
   }
 
   ///
   /// Get a d9 for all direction cartesian movement, plus a 9th spot to represent the currently 'stood on' tile
   /// 
-  fn get_d9(&self) -> usize {
-    rand::thread_rng().gen_range(1, 10)
+  fn get_d9(&self, rng: &mut StdRng) -> usize {
+    rng.gen_range(1, 10)
   }
 
 }
\ No newline at end of file
@@ -0,0 +1,252 @@
+//!
+//! Branches of the dungeon, each with its own way of being generated
+//!
+//! Every floor belongs to a branch. The main dungeon goes down forever, while side branches are entered from special
+//! stairs on a single floor of the main dungeon and only go a few floors deep. A branch decides:
+//!
+//! * The steps `Dungeon::build` goes through, in order (see `Step`)
+//! * The palette floors and walls start out with
+//! * Which monsters live there
+//!
+
+extern crate rand;
+use self::rand::{Rng, StdRng};
+
+use core::creature::MONSTERS;
+use core::creature::registry::Monster;
+use core::renderer::RGB;
+use core::world::dungeon::map::{tile, TILES};
+
+///
+/// One step of building a floor
+///
+/// Steps run in order, and every step works on whatever the steps before it left behind.
+/// Things that show up earlier are more likely to be eroded by the steps after them.
+///
+#[derive(Clone, PartialEq, Debug)]
+pub enum Step {
+  // Rooms and corridors from the `Simple` filter
+  Rooms,
+  // Drunkard's walk carving floors out of walls, with some chaos and a number of iterations
+  Walk(f32, u32),
+  // 1 - 3 structures from `.eqm` files
+  Structures,
+  // Noise that turns everything over the threshold into a biome, recolored with the biome's palette
+  Biome(tile::Biome, f32),
+  // Noise that floods floors over the threshold
+  Water(f32),
+  // Noise that turns floors and walls over the threshold into crystal
+  Crystal(f32),
+  // Noise that grows tall grass on floors over the threshold
+  TallGrass(f32),
+  // Noise that grows vines on floors over the threshold
+  Vines(f32),
+  // Turn some walls into hard walls
+  HardWalls,
+  // Scatter rocks and foliage on floors
+  FloorFeatures,
+  // Up and down stairs, along with the entrances to any branches on the floor
+  Stairs,
+  // A handful of traps
+  Traps,
+  // Stains
+  Stain
+}
+
+///
+/// Colors that tiles start out with, or get recolored to when a biome is applied
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Palette {
+  pub wall_fg: RGB,
+  pub wall_bg: RGB,
+  pub floor_fg: RGB,
+  pub floor_bg: RGB
+}
+
+impl Palette {
+
+  ///
+  /// Get the palette of a biome, which comes from the colors of walls and floors in the tile registry
+  ///
+  pub fn of(biome: &tile::Biome) -> Palette {
+    let (wall_fg, wall_bg) = TILES.colors("wall", biome);
+    let (floor_fg, floor_bg) = TILES.colors("floor", biome);
+    Palette { wall_fg, wall_bg, floor_fg, floor_bg }
+  }
+
+}
+
+///
+/// Every branch of the dungeon
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DungeonKind {
+  Main,
+  Crystal,
+  Sunken
+}
+
+impl Default for DungeonKind {
+  fn default() -> Self {
+    DungeonKind::Main
+  }
+}
+
+// Every side branch, in the order their entrances get placed
+pub const BRANCHES : [DungeonKind; 2] = [DungeonKind::Crystal, DungeonKind::Sunken];
+
+impl DungeonKind {
+
+  ///
+  /// Name shown to the player
+  ///
+  pub fn name(&self) -> &'static str {
+    match *self {
+      DungeonKind::Main    => "Dungeon",
+      DungeonKind::Crystal => "Crystal Caverns",
+      DungeonKind::Sunken  => "Sunken Halls"
+    }
+  }
+
+  ///
+  /// Floor of the main dungeon holding the entrance to the branch. The branch's first floor is one deeper
+  ///
+  /// `None` for the main dungeon, which has no entrance
+  ///
+  pub fn entrance(&self) -> Option<usize> {
+    match *self {
+      DungeonKind::Main    => None,
+      DungeonKind::Crystal => Some(2),
+      DungeonKind::Sunken  => Some(4)
+    }
+  }
+
+  ///
+  /// Deepest floor of the branch, which has no way further down
+  ///
+  /// `None` if the branch goes on forever
+  ///
+  pub fn bottom(&self) -> Option<usize> {
+    match *self {
+      DungeonKind::Main    => None,
+      DungeonKind::Crystal => Some(5),
+      DungeonKind::Sunken  => Some(8)
+    }
+  }
+
+  ///
+  /// Biome every tile starts out in
+  ///
+  pub fn biome(&self) -> tile::Biome {
+    match *self {
+      DungeonKind::Main    => tile::Biome::Dungeon,
+      DungeonKind::Crystal => tile::Biome::Crystal,
+      DungeonKind::Sunken  => tile::Biome::Sunken
+    }
+  }
+
+  ///
+  /// Colors every tile starts out with
+  ///
+  pub fn palette(&self) -> Palette {
+    Palette::of(&self.biome())
+  }
+
+  ///
+  /// Everything `Dungeon::build` does for a floor of the branch, in order
+  ///
+  pub fn pipeline(&self) -> Vec<Step> {
+    match *self {
+
+      // Rooms eroded into caves, with patches of other biomes
+      DungeonKind::Main => vec![
+        Step::Rooms,
+        // Total randomness - Really centralized areas that are mostly opened since it walks over itself a lot
+        Step::Walk(1.0, 800),
+        // Semi random - A mixture of the previous and next option
+        Step::Walk(0.5, 1000),
+        // Mostly orderly - Long corridors that occasionally deviate
+        Step::Walk(0.25, 1000),
+        Step::Biome(tile::Biome::Cave, 1.2),
+        Step::Biome(tile::Biome::Crypt, 1.2),
+        // After biomes, so that structures which only belong in some biomes can find them
+        Step::Structures,
+        Step::Water(1.4),
+        Step::TallGrass(1.5),
+        Step::Vines(1.55),
+        Step::HardWalls,
+        Step::FloorFeatures,
+        Step::Stairs,
+        Step::Traps,
+        Step::Stain
+      ],
+
+      // Open caverns with no rooms, overgrown with crystal
+      DungeonKind::Crystal => vec![
+        Step::Walk(1.0, 1500),
+        Step::Walk(0.5, 1500),
+        Step::Structures,
+        Step::Crystal(1.1),
+        Step::Vines(1.6),
+        Step::FloorFeatures,
+        Step::Stairs,
+        Step::Traps,
+        Step::Stain
+      ],
+
+      // Rooms joined by long corridors, mostly flooded
+      DungeonKind::Sunken => vec![
+        Step::Rooms,
+        Step::Walk(0.25, 1200),
+        Step::Water(1.0),
+        Step::Biome(tile::Biome::Crypt, 1.4),
+        Step::Structures,
+        Step::TallGrass(1.6),
+        Step::HardWalls,
+        Step::Stairs,
+        Step::Traps,
+        Step::Stain
+      ]
+
+    }
+  }
+
+  ///
+  /// Monsters that live in the branch by registry id, and how often they spawn compared to each other
+  ///
+  /// Empty means the registry's own spawn rules are used
+  ///
+  pub fn monsters(&self) -> &'static [(&'static str, usize)] {
+    match *self {
+      DungeonKind::Main    => &[],
+      DungeonKind::Crystal => &[("bee", 6), ("ant", 4), ("echidna", 2)],
+      DungeonKind::Sunken  => &[("echidna", 5), ("cat", 3), ("dog", 2)]
+    }
+  }
+
+  ///
+  /// Pick a random monster to spawn on a floor of the branch
+  ///
+  pub fn choose_monster(&self, depth: usize, rng: &mut StdRng) -> Option<&'static Monster> {
+
+    let table = self.monsters();
+
+    if table.is_empty() {
+      return MONSTERS.choose(depth, rng);
+    }
+
+    let total : usize = table.iter().map(|&(_, frequency)| frequency).sum();
+    let mut roll = rng.gen_range(0, total);
+    for &(id, frequency) in table {
+      if roll < frequency {
+        return MONSTERS.get(id);
+      }
+      roll -= frequency;
+    }
+
+    unreachable!();
+
+  }
+
+}
@@ -1,6 +1,9 @@
 extern crate fuss;
 use self::fuss::Simplex;
 
+extern crate rand;
+use self::rand::{Rng, StdRng};
+
 use core::world::dungeon::builder::Buildable;
 use core::world::dungeon::map;
 use core::world::dungeon::map::Measurable;
@@ -36,14 +39,16 @@ impl Fussy {
   ///
   /// Return a new `Fussy`
   /// 
-  pub fn new(grid: map::Grid<u8>, threshold: f32) -> Self {
+  /// The noise is seeded from `rng` so that the same world seed always produces the same noise map
+  /// 
+  pub fn new(grid: map::Grid<u8>, threshold: f32, rng: &mut StdRng) -> Self {
 
     // Make a new dungeon with our fresh grid of size `w` by `h`
     let fussy = Fussy { 
       grid: grid.clone(), 
       w: grid.width(), 
       h: grid.height(),
-      noise: Simplex::new(),
+      noise: Simplex::from_seed(vec![rng.gen(), rng.gen(), rng.gen(), rng.gen()]),
       threshold
     };
 
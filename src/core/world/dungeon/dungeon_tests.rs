@@ -1,3 +1,139 @@
 
 #[cfg(test)]
-mod tests {}
\ No newline at end of file
+mod tests {
+
+  use std::env;
+  use std::fs;
+  use std::path::Path;
+
+  use core::renderer::Renderable;
+  use core::world::dungeon::branch::{DungeonKind, Step};
+  use core::world::dungeon::filter::eqm::{self, Cell, EqmError, Spawn};
+  use core::world::dungeon::map::tile::Biome;
+
+  ///
+  /// Parse a structure, expecting it to be broken, and get where it broke
+  ///
+  fn parse_err(contents: &str) -> (usize, usize) {
+    match eqm::parse(Path::new("test.eqm"), contents) {
+      Err(EqmError::Parse { line, col, .. }) => (line, col),
+      Err(err) => panic!("expected a parse error, got {}", err),
+      Ok(_) => panic!("expected a parse error, but the structure parsed")
+    }
+  }
+
+  #[test]
+  fn test_eqm_header() {
+
+    let strct = eqm::parse(Path::new("grove.eqm"), "name: Grove\n\
+                                                    rarity: 5\n\
+                                                    depths: 2-6\n\
+                                                    biomes: dungeon, cave\n\
+                                                    rotate: no\n\
+                                                    mirror: yes\n\
+                                                    legend: ~ water\n\
+                                                    spawn: c monster ant\n\
+                                                    spawn: $ item gold\n\
+                                                    ---\n\
+                                                    #~#\n\
+                                                    c.$\n").unwrap();
+
+    assert_eq!(strct.name, "Grove");
+    assert_eq!(strct.rarity, 5);
+    assert!(!strct.allowed_at(1));
+    assert!(strct.allowed_at(2));
+    assert!(strct.allowed_at(6));
+    assert!(!strct.allowed_at(7));
+    assert_eq!(strct.biomes, vec![Biome::Dungeon, Biome::Cave]);
+    assert!(strct.allowed_in(&Biome::Cave));
+    assert!(!strct.allowed_in(&Biome::Crypt));
+    assert!(!strct.rotate);
+    assert!(strct.mirror);
+
+    // Indexed `[x][y]`
+    assert_eq!(strct.cells.len(), 3);
+    assert_eq!(strct.cells[0].len(), 2);
+    match strct.cells[1][0] {
+      Cell::Tile(ref tile) => assert_eq!(tile.get_id(), eqm::tile_from_id("water").unwrap().get_id()),
+      _ => panic!("legend wasn't used")
+    }
+    match strct.cells[0][1] {
+      Cell::Spawn(ref spawn) => assert_eq!(*spawn, Spawn::Monster("ant".to_string())),
+      _ => panic!("monster spawn wasn't used")
+    }
+    match strct.cells[2][1] {
+      Cell::Spawn(ref spawn) => assert_eq!(*spawn, Spawn::Item("gold".to_string())),
+      _ => panic!("item spawn wasn't used")
+    }
+
+  }
+
+  #[test]
+  fn test_eqm_no_header() {
+
+    let strct = eqm::parse(Path::new("strct/Pillars.eqm"), "#.#\n. .\n\n").unwrap();
+
+    // Everything falls back to the defaults, and trailing blank lines are dropped
+    assert_eq!(strct.name, "Pillars");
+    assert_eq!(strct.rarity, eqm::DEFAULT_RARITY);
+    assert!(strct.allowed_at(100));
+    assert!(strct.allowed_in(&Biome::Crypt));
+    assert_eq!(strct.cells.len(), 3);
+    assert_eq!(strct.cells[0].len(), 2);
+
+  }
+
+  #[test]
+  fn test_eqm_errors() {
+
+    // Header lines and columns
+    assert_eq!(parse_err("name: Bad\ncolor: red\n---\n#\n"), (2, 1));
+    assert_eq!(parse_err("no colon here\n---\n#\n"), (1, 1));
+    assert_eq!(parse_err("biomes: dungeon, swamp\n---\n#\n"), (1, 9));
+    assert_eq!(parse_err("rarity:   lots\n---\n#\n"), (1, 11));
+    assert_eq!(parse_err("depths: 6-2\n---\n#\n"), (1, 9));
+    assert_eq!(parse_err("legend: ~ lava\n---\n#\n"), (1, 11));
+    assert_eq!(parse_err("spawn: c monster dragon\n---\n#\n"), (1, 10));
+
+    // Structure lines count from the top of the file
+    assert_eq!(parse_err("name: Bad\n---\n###\n#x#\n"), (4, 2));
+    assert_eq!(parse_err("###\n##\n"), (2, 1));
+    assert_eq!(parse_err("name: Empty\n---\n\n"), (3, 1));
+
+  }
+
+  #[test]
+  fn test_eqm_broken_file() {
+
+    let dir = env::temp_dir().join("edgequest_eqm_test");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a_good.eqm"), "#.#\n").unwrap();
+    fs::write(dir.join("b_bad.eqm"), "name: Bad\n---\n#x#\n").unwrap();
+    fs::write(dir.join("c_good.eqm"), "name: Fine\n---\n...\n").unwrap();
+
+    // The broken file is the only one that fails
+    let files = eqm::load_dir(dir.to_str().unwrap()).unwrap();
+    assert_eq!(files.len(), 3);
+    assert_eq!(files[0].as_ref().unwrap().name, "a_good");
+    assert!(files[1].is_err());
+    assert_eq!(files[2].as_ref().unwrap().name, "Fine");
+
+  }
+
+  #[test]
+  fn test_structures_after_biomes() {
+
+    // Structures can ask for biomes, so every biome has to be in place before they're placed
+    for kind in &[DungeonKind::Main, DungeonKind::Crystal, DungeonKind::Sunken] {
+      let pipeline = kind.pipeline();
+      let structures = pipeline.iter().position(|step| *step == Step::Structures).unwrap();
+      for (idx, step) in pipeline.iter().enumerate() {
+        if let Step::Biome(..) = *step {
+          assert!(idx < structures, "{:?} places structures before biomes", kind);
+        }
+      }
+    }
+
+  }
+
+}
@@ -0,0 +1,366 @@
+//!
+//! Read `.eqm` structure files
+//!
+//! An `.eqm` file is a picture of a structure drawn with characters, optionally preceded by a header of
+//! `key: value` lines that is ended by a line holding only `---`:
+//!
+//! ```text
+//! name: Grove
+//! rarity: 5
+//! depths: 0-10
+//! biomes: dungeon, cave
+//! rotate: yes
+//! mirror: no
+//! legend: ~ water
+//! spawn: c monster cat
+//! spawn: $ item gold
+//! ---
+//! ##~~##
+//! #.c.$#
+//! ##..##
+//! ```
+//!
+//! * `name` - Name shown in debug output. Defaults to the file name
+//! * `rarity` - How likely the structure is to be picked compared to others. Defaults to `DEFAULT_RARITY`
+//! * `depths` - Floors the structure can show up on, like `2-6`, or `4-` for anything from 4 down. Defaults to all
+//! * `biomes` - Biomes that every tile under the structure must be in. Defaults to any biome
+//! * `rotate`, `mirror` - Whether the structure can be rotated or mirrored when placed. Default to yes and no
+//! * `legend` - A character, then the id of the tile it stands for (see `config/tiles.yml`). Overrides the default legend
+//! * `spawn` - A character, then `monster` or `item`, then the id of what spawns there. Spawns stand on floor
+//!
+//! Without a header, the whole file is the structure, drawn with the default legend:
+//! `#` wall, `.` and whitespace floor, `"` tall grass, `&` fountain, `<` up stair and `>` down stair.
+//!
+
+// Read files
+use std::fmt;
+use std::fs;
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
+
+use core::creature::MONSTERS;
+use core::item;
+use core::world::dungeon::map::{self, tile, Tile, TILES};
+
+///
+/// Configuration
+///
+
+// Rarity of structures that don't give one
+pub const DEFAULT_RARITY : usize = 10;
+
+// Line separating the header from the structure
+const HEADER_END : &str = "---";
+
+///
+/// Something for the world to spawn once the floor is built
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Spawn {
+  // A monster by its id in the monster registry
+  Monster(String),
+  // An item by its id (see `item::from_id()`)
+  Item(String)
+}
+
+///
+/// What a character in the structure stands for
+///
+#[derive(Clone, Debug)]
+pub enum Cell {
+  Tile(Tile),
+  Spawn(Spawn)
+}
+
+///
+/// A structure that has been read from a file
+///
+#[derive(Clone, Debug)]
+pub struct Eqm {
+  pub name: String,
+  pub rarity: usize,
+  pub min_depth: usize,
+  pub max_depth: Option<usize>,
+  // Empty means any biome
+  pub biomes: Vec<tile::Biome>,
+  pub rotate: bool,
+  pub mirror: bool,
+  // Indexed `[x][y]` like every other grid
+  pub cells: map::Grid<Cell>
+}
+
+impl Eqm {
+
+  ///
+  /// Check if the structure can show up on a floor
+  ///
+  pub fn allowed_at(&self, depth: usize) -> bool {
+    depth >= self.min_depth && self.max_depth.map_or(true, |max| depth <= max)
+  }
+
+  ///
+  /// Check if the structure can be placed on a tile of some biome
+  ///
+  pub fn allowed_in(&self, biome: &tile::Biome) -> bool {
+    self.biomes.is_empty() || self.biomes.contains(biome)
+  }
+
+}
+
+///
+/// Things that can go wrong while reading a structure
+///
+#[derive(Debug)]
+pub enum EqmError {
+  // Couldn't read the file or directory
+  Io(PathBuf, io::Error),
+  // Something in the file doesn't make sense. Lines and columns start at 1
+  Parse { file: PathBuf, line: usize, col: usize, msg: String }
+}
+
+impl fmt::Display for EqmError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      EqmError::Io(ref file, ref err) => write!(fmt, "{}: {}", file.display(), err),
+      EqmError::Parse { ref file, line, col, ref msg } => write!(fmt, "{}:{}:{}: {}", file.display(), line, col, msg)
+    }
+  }
+}
+
+///
+/// Get a tile by the id used for it in legends, which is its id in the tile registry
+///
+/// Structures are drawn in the colors of the dungeon biome, whatever biome they end up in
+///
+pub fn tile_from_id(id: &str) -> Option<Tile> {
+  TILES.get(id).map(|kind| kind.create(&tile::Biome::Dungeon))
+}
+
+///
+/// Legend every file starts with
+///
+fn default_legend() -> Vec<(char, Cell)> {
+  let mut legend = vec![];
+  for &(ch, id) in &[('#', "wall"), ('.', "floor"), (' ', "floor"), ('\t', "floor"), ('"', "tall_grass"),
+                     ('&', "fountain"), ('<', "up_stair"), ('>', "down_stair")] {
+    legend.push((ch, Cell::Tile(tile_from_id(id).unwrap())));
+  }
+  return legend;
+}
+
+///
+/// Read every `.eqm` file in a directory, sorted by path so that the same seed always picks the same structures
+///
+/// Every file gets its own result, so that one broken file doesn't take the rest down with it
+///
+pub fn load_dir(dir: &str) -> Result<Vec<Result<Eqm, EqmError>>, EqmError> {
+
+  let dir = Path::new(dir);
+
+  let mut paths = vec![];
+  for entry in fs::read_dir(dir).map_err(|err| EqmError::Io(dir.to_path_buf(), err))? {
+    let path = entry.map_err(|err| EqmError::Io(dir.to_path_buf(), err))?.path();
+    if path.extension().and_then(|ext| ext.to_str()) == Some("eqm") {
+      paths.push(path);
+    }
+  }
+  paths.sort();
+
+  return Ok(paths.iter().map(|path| load(path)).collect());
+
+}
+
+///
+/// Read a single `.eqm` file
+///
+pub fn load(path: &Path) -> Result<Eqm, EqmError> {
+
+  let mut contents = String::new();
+  fs::File::open(path)
+    .and_then(|mut file| file.read_to_string(&mut contents))
+    .map_err(|err| EqmError::Io(path.to_path_buf(), err))?;
+
+  parse(path, &contents)
+
+}
+
+///
+/// Parse the contents of an `.eqm` file. `path` is only used for errors and the default name
+///
+pub fn parse(path: &Path, contents: &str) -> Result<Eqm, EqmError> {
+
+  let error = |line: usize, col: usize, msg: String| EqmError::Parse { file: path.to_path_buf(), line, col, msg };
+
+  // Windows line endings sneak into structure files, so drop them
+  let lines : Vec<&str> = contents.lines().map(|line| line.trim_end_matches('\r')).collect();
+
+  // Everything up to the header end is header, if there is one
+  let (header, body, body_start) = match lines.iter().position(|&line| line == HEADER_END) {
+    Some(idx) => (&lines[..idx], &lines[idx + 1..], idx + 1),
+    None => (&lines[..0], &lines[..], 0)
+  };
+
+  let mut eqm = Eqm {
+    name: path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned()),
+    rarity: DEFAULT_RARITY,
+    min_depth: 0,
+    max_depth: None,
+    biomes: vec![],
+    rotate: true,
+    mirror: false,
+    cells: vec![]
+  };
+
+  let mut legend = default_legend();
+
+  //
+  // Header
+  //
+
+  for (idx, &line) in header.iter().enumerate() {
+
+    let line_num = idx + 1;
+
+    // Blank lines and comments
+    if line.trim().is_empty() || line.trim_start().starts_with("//") {
+      continue;
+    }
+
+    let colon = match line.find(':') {
+      Some(colon) => colon,
+      None => return Err(error(line_num, 1, format!("Expected `key: value`, found `{}`", line)))
+    };
+
+    let key = line[..colon].trim();
+    let value = line[colon + 1..].trim();
+    // Column the value starts at, for errors
+    let value_col = colon + 2 + (line[colon + 1..].len() - line[colon + 1..].trim_start().len());
+
+    match key {
+
+      "name" => eqm.name = value.to_string(),
+
+      "rarity" => {
+        eqm.rarity = value.parse().map_err(|_| error(line_num, value_col, format!("Rarity must be a whole number, found `{}`", value)))?;
+      },
+
+      "depths" => {
+        let (min, max) = match value.find('-') {
+          Some(dash) => (value[..dash].trim(), value[dash + 1..].trim()),
+          None => return Err(error(line_num, value_col, format!("Depths must look like `2-6` or `4-`, found `{}`", value)))
+        };
+        eqm.min_depth = min.parse().map_err(|_| error(line_num, value_col, format!("Bad shallowest depth `{}`", min)))?;
+        if !max.is_empty() {
+          let max_depth = max.parse().map_err(|_| error(line_num, value_col, format!("Bad deepest depth `{}`", max)))?;
+          if max_depth < eqm.min_depth {
+            return Err(error(line_num, value_col, format!("Depths `{}` go backwards", value)));
+          }
+          eqm.max_depth = Some(max_depth);
+        }
+      },
+
+      "biomes" => {
+        for name in value.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+          match tile::Biome::from_name(name) {
+            Some(biome) => eqm.biomes.push(biome),
+            None => return Err(error(line_num, value_col, format!("Unknown biome `{}`", name)))
+          }
+        }
+      },
+
+      "rotate" | "mirror" => {
+        let flag = match value {
+          "yes" | "true" => true,
+          "no" | "false" => false,
+          _ => return Err(error(line_num, value_col, format!("`{}` must be yes or no, found `{}`", key, value)))
+        };
+        if key == "rotate" { eqm.rotate = flag } else { eqm.mirror = flag }
+      },
+
+      "legend" | "spawn" => {
+
+        // The character comes first, then whatever it means
+        let mut chars = value.chars();
+        let ch = match chars.next() {
+          Some(ch) => ch,
+          None => return Err(error(line_num, value_col, format!("`{}` needs a character", key)))
+        };
+        let rest = chars.as_str().trim();
+        let rest_col = value_col + value.len() - rest.len();
+
+        let cell = if key == "legend" {
+          match tile_from_id(rest) {
+            Some(tile) => Cell::Tile(tile),
+            None => return Err(error(line_num, rest_col, format!("Unknown tile `{}`", rest)))
+          }
+        } else {
+          let mut words = rest.split_whitespace();
+          match (words.next(), words.next(), words.next()) {
+            (Some("monster"), Some(id), None) => {
+              if MONSTERS.get(id).is_none() {
+                return Err(error(line_num, rest_col, format!("Unknown monster `{}`", id)));
+              }
+              Cell::Spawn(Spawn::Monster(id.to_string()))
+            },
+            (Some("item"), Some(id), None) => {
+              if !item::ITEM_IDS.contains(&id) {
+                return Err(error(line_num, rest_col, format!("Unknown item `{}`, expected one of {}", id, item::ITEM_IDS.join(", "))));
+              }
+              Cell::Spawn(Spawn::Item(id.to_string()))
+            },
+            _ => return Err(error(line_num, rest_col, format!("Spawns must look like `monster <id>` or `item <id>`, found `{}`", rest)))
+          }
+        };
+
+        legend.retain(|&(legend_ch, _)| legend_ch != ch);
+        legend.push((ch, cell));
+
+      },
+
+      _ => return Err(error(line_num, 1, format!("Unknown key `{}`", key)))
+
+    }
+
+  }
+
+  //
+  // Structure
+  //
+
+  // Trailing blank lines aren't part of the structure
+  let mut rows = body;
+  while rows.last().map_or(false, |row| row.is_empty()) {
+    rows = &rows[..rows.len() - 1];
+  }
+
+  if rows.is_empty() {
+    return Err(error(body_start + 1, 1, "Structure is empty".to_string()));
+  }
+
+  let width = rows[0].chars().count();
+
+  // Start with columns so the grid can be indexed with `[x][y]`
+  let mut cells : map::Grid<Cell> = (0..width).map(|_| Vec::with_capacity(rows.len())).collect();
+
+  for (idx, row) in rows.iter().enumerate() {
+
+    let line_num = body_start + idx + 1;
+
+    if row.chars().count() != width {
+      return Err(error(line_num, 1, format!("Row is {} wide but the first row is {} wide", row.chars().count(), width)));
+    }
+
+    for (x, ch) in row.chars().enumerate() {
+      match legend.iter().find(|&&(legend_ch, _)| legend_ch == ch) {
+        Some(&(_, ref cell)) => cells[x].push(cell.clone()),
+        None => return Err(error(line_num, x + 1, format!("Character `{}` is not in the legend", ch)))
+      }
+    }
+
+  }
+
+  eqm.cells = cells;
+
+  return Ok(eqm);
+
+}
@@ -1,8 +1,13 @@
+extern crate rand;
+use self::rand::StdRng;
+
 use core::world::dungeon::map;
 
 pub mod stain;
 pub use self::stain::Stain;
 
+pub mod eqm;
+
 pub mod structure;
 pub use self::structure::Structure;
 
@@ -19,6 +24,11 @@ pub trait Filter {
   /// 
   type Output : Clone;
 
-  fn apply(&mut self, grid: &mut map::Grid<Self::Output>);
+  ///
+  /// Apply the filter to the grid
+  /// 
+  /// All randomness must come from `rng` so that the same seed always produces the same grid
+  /// 
+  fn apply(&mut self, grid: &mut map::Grid<Self::Output>, rng: &mut StdRng);
 
 }
\ No newline at end of file
@@ -1,12 +1,11 @@
 extern crate rand;
-use self::rand::Rng;
+use self::rand::{Rng, StdRng};
 
-use core::renderer::RGB;
 
 use super::Filter;
 
 use super::map::construct::{Corr, Rect};
-use super::map::{Grid, Measurable, Pos, tile, Tile};
+use super::map::{Grid, Measurable, Pos, tile, Tile, TILES};
 
 ///
 /// Configuration
@@ -18,11 +17,17 @@ const MIN_ROOM_DIM: isize = 3;
 // Maximum length to any side of a room
 const MAX_ROOM_DIM: isize = 12;
 
+// Out of 100, the chance of a doorway getting each kind of door. Whatever is left over stays an open doorway
+const CLOSED_DOOR_CHANCE: isize = 45;
+const OPEN_DOOR_CHANCE: isize = 20;
+const LOCKED_DOOR_CHANCE: isize = 8;
+const SECRET_DOOR_CHANCE: isize = 4;
+
 ///
 /// Simple dungeon builder
 /// 
 /// This builder places a number of small rooms (respective to map size)
-/// all connected by corridors.
+/// all connected by corridors, with doors where the corridors meet the rooms.
 /// 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Simple {
@@ -33,8 +38,8 @@ pub struct Simple {
   // Private vector to hold rooms
   rooms: Vec<Rect>,
   
-  // Private vector to hold floor type
-  floor: Tile
+  // Floor rooms and corridors are carved with
+  pub floor: Tile
 
 }
 
@@ -43,13 +48,11 @@ impl Simple {
   ///
   /// Add rooms to the `rooms` vec and build them on the `grid`
   /// 
-  fn add_rooms(&mut self, grid: &mut Grid<Tile>) {
+  fn add_rooms(&mut self, grid: &mut Grid<Tile>, rng: &mut StdRng) {
 
     // Clear rooms
     self.rooms = Vec::<Rect>::new();
 
-    let mut rng = rand::thread_rng();
-
     // Number of rooms correspond to map size
     let n = (self.w + self.h) / 10;
 
@@ -154,6 +157,63 @@ impl Simple {
   }
 
 
+  ///
+  /// Put doors where corridors break into rooms
+  ///
+  /// A doorway is a floor tile just outside a room with walls on either side of it along the room's edge, so corridors
+  /// that run along a room's wall or rooms that overlap don't get doors. Every doorway is found before any door goes in,
+  /// since closed doors would otherwise look like walls to the doorways next to them
+  ///
+  fn add_doors(&mut self, grid: &mut Grid<Tile>, rng: &mut StdRng) {
+
+    let mut doorways = vec![];
+
+    for room in &self.rooms {
+
+      // Top and bottom edges, where the sides of a doorway are left and right of it
+      for x in room.x..room.x + room.w {
+        for &y in &[room.y - 1, room.y + room.h] {
+          let (pos, a, b) = (Pos::new(x, y), Pos::new(x - 1, y), Pos::new(x + 1, y));
+          if tile::walkable(&grid[pos]) && !tile::walkable(&grid[a]) && !tile::walkable(&grid[b]) {
+            doorways.push(pos);
+          }
+        }
+      }
+
+      // Left and right edges, where the sides are above and below
+      for y in room.y..room.y + room.h {
+        for &x in &[room.x - 1, room.x + room.w] {
+          let (pos, a, b) = (Pos::new(x, y), Pos::new(x, y - 1), Pos::new(x, y + 1));
+          if tile::walkable(&grid[pos]) && !tile::walkable(&grid[a]) && !tile::walkable(&grid[b]) {
+            doorways.push(pos);
+          }
+        }
+      }
+
+    }
+
+    for pos in doorways {
+
+      let roll = rng.gen_range(0, 100);
+
+      let id = if roll < CLOSED_DOOR_CHANCE {
+        "closed_door"
+      } else if roll < CLOSED_DOOR_CHANCE + OPEN_DOOR_CHANCE {
+        "open_door"
+      } else if roll < CLOSED_DOOR_CHANCE + OPEN_DOOR_CHANCE + LOCKED_DOOR_CHANCE {
+        "locked_door"
+      } else if roll < CLOSED_DOOR_CHANCE + OPEN_DOOR_CHANCE + LOCKED_DOOR_CHANCE + SECRET_DOOR_CHANCE {
+        "secret_door"
+      } else {
+        continue;
+      };
+
+      grid[pos] = TILES.tile(id, &self.floor.biome);
+
+    }
+
+  }
+
   /// 
   /// Return a new `Simple`
   /// 
@@ -165,7 +225,7 @@ impl Simple {
       w: grid.width(), 
       h: grid.height(),
       // Floor type. Doesn't need to be changed right now, after all this is the 'simple' dungeon builder
-      floor: Tile::new("Floor", ' ', RGB(7, 7, 7),  RGB(0, 0, 0), tile::Type::Floor(tile::Floor::Normal))
+      floor: TILES.tile("floor", &tile::Biome::Dungeon)
     };
     
     return simple;
@@ -178,13 +238,14 @@ impl Filter for Simple {
 
   type Output = Tile;
 
-  fn apply(&mut self, grid: &mut Grid<Self::Output>) {
+  fn apply(&mut self, grid: &mut Grid<Self::Output>, rng: &mut StdRng) {
 
     debugln!("simple", "building a simple dungeon...");
 
     // Generate the dungeon
-    self.add_rooms(grid);
+    self.add_rooms(grid, rng);
     self.connect_rooms(grid);
+    self.add_doors(grid, rng);
 
   }
 
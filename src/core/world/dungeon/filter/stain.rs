@@ -1,5 +1,5 @@
 extern crate rand;
-use self::rand::Rng;
+use self::rand::{Rng, StdRng};
 
 use core::renderer::RGB;
 
@@ -45,9 +45,12 @@ impl Stain {
   ///
   /// Add stain on a tile 
   ///
-  pub fn stain_linear(x: usize, y: usize, d: usize, grid: &mut Grid<Tile>, chance: usize, stain_type: StainType) {
+  pub fn stain_linear(x: usize, y: usize, d: usize, grid: &mut Grid<Tile>, chance: usize, stain_type: StainType, rng: &mut StdRng) {
 
-    let mut rng = rand::thread_rng();
+    // Stains spilled near the edge of the map (like blood from a fight against a wall) just don't land
+    if x < d/2 || y < d/2 || x - (d/2) >= grid.width() || y - (d/2) >= grid.height() {
+      return;
+    }
 
     // Coinflip to decide if a tile gets blood
     if rng.gen_range(0, chance) == 0 {
@@ -76,6 +79,7 @@ impl Stain {
             },
             StainType::Corruption => {
               grid[x - (d/2)][y - (d/2)].set_name("Corrupted Floor");
+              grid[x - (d/2)][y - (d/2)].corrupted = true;
               let fg = rng.gen_range(0, CORRUPTION_FG);
               let bg = rng.gen_range(0, CORRUPTION_BG);
               grid[x - (d/2)][y - (d/2)].fg -= RGB(fg, fg, fg);
@@ -102,6 +106,7 @@ impl Stain {
             StainType::Fungus => (),
             StainType::Corruption => {
               grid[x - (d/2)][y - (d/2)].set_name("Corrupted Wall");
+              grid[x - (d/2)][y - (d/2)].corrupted = true;
               let fg = rng.gen_range(0, CORRUPTION_FG);
               let bg = rng.gen_range(0, CORRUPTION_BG);
               grid[x - (d/2)][y - (d/2)].fg -= RGB(fg, fg, fg);
@@ -117,34 +122,34 @@ impl Stain {
 
   }
 
-  pub fn add_viscera(x: usize, y: usize, d: usize, grid: &mut Grid<Tile>) {
+  pub fn add_viscera(x: usize, y: usize, d: usize, grid: &mut Grid<Tile>, rng: &mut StdRng) {
     for i in 0..d {
       for j in 0..d {
-        Stain::stain_linear(x + i, y + j, d, grid, 2, StainType::Viscera);
+        Stain::stain_linear(x + i, y + j, d, grid, 2, StainType::Viscera, rng);
       }
     }
   }
 
-    pub fn add_moss(x: usize, y: usize, d: usize, grid: &mut Grid<Tile>) {
+    pub fn add_moss(x: usize, y: usize, d: usize, grid: &mut Grid<Tile>, rng: &mut StdRng) {
     for i in 0..d {
       for j in 0..d {
-        Stain::stain_linear(x + i, y + j, d, grid, 2, StainType::Moss);
+        Stain::stain_linear(x + i, y + j, d, grid, 2, StainType::Moss, rng);
       }
     }
   }
 
-    pub fn add_fungus(x: usize, y: usize, d: usize, grid: &mut Grid<Tile>) {
+    pub fn add_fungus(x: usize, y: usize, d: usize, grid: &mut Grid<Tile>, rng: &mut StdRng) {
     for i in 0..d {
       for j in 0..d {
-        Stain::stain_linear(x + i, y + j, d, grid, 3, StainType::Fungus);
+        Stain::stain_linear(x + i, y + j, d, grid, 3, StainType::Fungus, rng);
       }
     }
   }
 
-  pub fn add_corruption(x: usize, y: usize, d: usize, grid: &mut Grid<Tile>) {
+  pub fn add_corruption(x: usize, y: usize, d: usize, grid: &mut Grid<Tile>, rng: &mut StdRng) {
     for i in 0..d {
       for j in 0..d {
-        Stain::stain_linear(x + i, y + j, d, grid, 1, StainType::Corruption);
+        Stain::stain_linear(x + i, y + j, d, grid, 1, StainType::Corruption, rng);
       }
     }
   }
@@ -162,12 +167,10 @@ impl Filter for Stain {
 
   type Output = Tile;
 
-  fn apply(&mut self, grid: &mut Grid<Self::Output>) {
+  fn apply(&mut self, grid: &mut Grid<Self::Output>, rng: &mut StdRng) {
 
     debugln!("stain", "spreading gore randomly...");
 
-    let mut rng = rand::thread_rng();
-
     // Generate moss  
     for _ in 0..rng.gen_range(1, 3) {
 
@@ -175,7 +178,7 @@ impl Filter for Stain {
       let x = rng.gen_range(15, grid.width() - 15 - 1);
       let y = rng.gen_range(15, grid.height() - 15 - 1);
 
-      Stain::add_moss(x, y, 15, grid);
+      Stain::add_moss(x, y, 15, grid, rng);
 
     }
 
@@ -186,7 +189,7 @@ impl Filter for Stain {
       let x = rng.gen_range(7, grid.width() - 7 - 1);
       let y = rng.gen_range(7, grid.height() - 7 - 1);
 
-      Stain::add_fungus(x, y, 7, grid);
+      Stain::add_fungus(x, y, 7, grid, rng);
 
     }
 
@@ -197,7 +200,7 @@ impl Filter for Stain {
       let x = rng.gen_range(9, grid.width() - 9 - 1);
       let y = rng.gen_range(9, grid.height() - 9 - 1);
 
-      Stain::add_corruption(x, y, 9, grid);
+      Stain::add_corruption(x, y, 9, grid, rng);
 
     }
 
@@ -208,7 +211,7 @@ impl Filter for Stain {
       let x = rng.gen_range(VISCERA_DIAMETER, grid.width() - VISCERA_DIAMETER - 1);
       let y = rng.gen_range(VISCERA_DIAMETER, grid.height() - VISCERA_DIAMETER - 1);
 
-      Stain::add_viscera(x, y, VISCERA_DIAMETER, grid);
+      Stain::add_viscera(x, y, VISCERA_DIAMETER, grid, rng);
 
     }
 
@@ -1,118 +1,138 @@
 extern crate rand;
-use self::rand::Rng;
-
-// Read files
-use std::io::prelude::*;
-use std::fs;
-
+use self::rand::{Rng, StdRng};
 
+use core::log;
 use core::renderer::RGB;
 
 use super::Filter;
-use core::world::dungeon::map::{self, Measurable, tile, Tile};
+use super::eqm::{self, Cell, Spawn};
+use core::world::dungeon::map::{self, Measurable, Pos, Tile};
+
+// Where structure files live
+const STRUCT_DIR : &str = "./strct";
+
+lazy_static! {
+  // Every structure that can be placed, read the first time a floor needs one. Broken files are reported in the log
+  // and left out
+  static ref STRUCTURES : Vec<eqm::Eqm> = load_structures();
+}
 
 ///
-/// Structure placer
-/// 
-/// Generate prefab structures based on files and place them on the grid
+/// Read the structure files, keeping the ones that aren't broken
 ///
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct Structure {}
+fn load_structures() -> Vec<eqm::Eqm> {
 
-impl Structure {
+  let files = match eqm::load_dir(STRUCT_DIR) {
+    Ok(files) => files,
+    Err(err) => {
+      log!(err.to_string(), RGB(255, 0, 0));
+      return vec![];
+    }
+  };
 
-  ///
-  /// Add a random structure
-  ///
-  fn add_rand_struct(&mut self, grid: &mut map::Grid<Tile>) {
-
-    // RNG
-    let mut rng = rand::thread_rng();
-
-    // Create a vector out of collecting the read_dir by mapping the unwrapped paths
-    let paths : Vec<_> = fs::read_dir("./strct").unwrap().map(|res| res.unwrap().path()).collect();
-
-    // Choose a random element (aka file from paths)
-    let fname = rng.choose(&paths).unwrap();
-    let mut file = fs::File::open(fname).unwrap();
-
-    debugln!("struct", format!("adding struct {}", fname.display()));
-
-    // Create empty string and read to it
-    let mut string = String::new();
-    file.read_to_string(&mut string).unwrap();
-
-    // Prepare method to store data read from file
-    let mut strct : map::Grid<Tile> = vec![];
-    let mut line : Vec<Tile> = vec![];
-
-    // Read file as characters
-    for ch in string.chars() {
-      // If not a newline
-      if ch != '\n' {
-        // Match tile based on character
-        let tile = {
-          match ch {
-            '#' => Tile::new("Wall", ' ', RGB(40, 40, 40), RGB(33, 33, 33), tile::Type::Wall(tile::Wall::Normal)),
-            // So on windows I've noticed that in the text files for structures, random escape characters get added in so might as well
-            // just make them floors.
-            '.' | ' ' | '\t' | '\r' => Tile::new("Floor", ' ', RGB(27, 27, 27), RGB(20, 20, 20), tile::Type::Floor(tile::Floor::Normal)),
-            '"' => Tile::new("Tall Grass", '"', RGB(76, 74, 75), RGB(20, 20, 20), tile::Type::TallGrass),
-            '&' => Tile::new("Fountain", '&', RGB(201, 195, 195), RGB(20, 20, 20), tile::Type::ArtStructure),
-            '<' => Tile::new("Up Stair", '<', RGB(255, 255, 255), RGB(0, 0, 0), tile::Type::Stair(tile::Stair::UpStair(tile::UpStair::Normal))),
-            '>' => Tile::new("Down Stair", '>', RGB(255, 255, 255), RGB(0, 0, 0), tile::Type::Stair(tile::Stair::DownStair(tile::DownStair::Normal))),
-            _ => panic!("Unknown character: {}", ch)
-          }
-        };
-        // Push character to the line
-        line.push(tile);
-      // If we hit a new line we need to push the line to the tile struct, and empty the line
-      } else {
-        strct.push(line);
-        line = vec![];
-      }
+  let mut structures = vec![];
+  for file in files {
+    match file {
+      Ok(strct) => structures.push(strct),
+      Err(err) => log!(err.to_string(), RGB(255, 0, 0))
     }
+  }
 
-    // Rotate randomly
+  return structures;
 
-    // (x, y) rotated 90 degrees around (0, 0) is (-y, x).
-    // However, vectors are sized in a way that won't allow for negative indexing.
-    // Our formula for point tranformation should be:
-    // (-y + total x length, x)
+}
 
-    let rot90 = | grid: map::Grid<Tile> | -> map::Grid<Tile> {
+///
+/// Rotate a grid 90 degrees
+///
+/// (x, y) rotated 90 degrees around (0, 0) is (-y, x).
+/// However, vectors are sized in a way that won't allow for negative indexing.
+/// Our formula for point tranformation should be:
+/// (-y + total x length, x)
+///
+fn rot90<T: Clone>(grid: &map::Grid<T>) -> map::Grid<T> {
 
-      // We could clone but I feel like this way is faster
-      let mut rot_grid = map::Grid::<Tile>::new();
+  // We could clone but I feel like this way is faster
+  let mut rot_grid = map::Grid::<T>::new();
 
-      // Measure x on y axis
-      for x in 0..grid.height() {
+  // Measure x on y axis
+  for x in 0..grid.height() {
 
-        // Fill new vecs with init
-        let mut vec = Vec::<Tile>::new();
+    // Fill new vecs with init
+    let mut vec = Vec::<T>::new();
 
-        // Measure y on x axis
-        for y in 0..grid.width() {
-          vec.push(
-            
-            // Rotation performed by following above function
-            grid[grid.width() - 1 - y][x].clone()
+    // Measure y on x axis
+    for y in 0..grid.width() {
+      vec.push(
+        
+        // Rotation performed by following above function
+        grid[grid.width() - 1 - y][x].clone()
 
-          );
-        }
+      );
+    }
+
+    rot_grid.push(vec);
+
+  }
+
+  return rot_grid;
+
+}
 
-        rot_grid.push(vec);
+///
+/// Structure placer
+/// 
+/// Generate prefab structures based on `.eqm` files (see `eqm`) and place them on the grid
+///
+/// Monsters and items that placed structures want are collected in `spawns`, since filters only ever deal with tiles
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Structure {
+  // Depth of the floor being built, as structures can be limited to some depths
+  depth: usize,
+  pub spawns: Vec<(Pos, Spawn)>
+}
+
+impl Structure {
+
+  ///
+  /// Add a random structure
+  ///
+  fn add_rand_struct(&mut self, grid: &mut map::Grid<Tile>, rng: &mut StdRng) {
+
+    // Pick a structure that can show up this deep, weighted by rarity
+    let depth = self.depth;
+    let choices : Vec<&eqm::Eqm> = STRUCTURES.iter().filter(|strct| strct.allowed_at(depth)).collect();
+    let total : usize = choices.iter().map(|strct| strct.rarity).sum();
 
+    if total == 0 {
+      return;
+    }
+
+    let mut roll = rng.gen_range(0, total);
+    let mut chosen = choices[0];
+    for strct in choices {
+      if roll < strct.rarity {
+        chosen = strct;
+        break;
       }
+      roll -= strct.rarity;
+    }
 
-      return rot_grid;
+    debugln!("struct", format!("adding struct {}", chosen.name));
 
-    };
+    let mut strct = chosen.cells.clone();
 
-    // Perform 1 - 4 rotations
-    // NOTE: 4 rotations = starting positon. Might be a good idea to improve this
-    for _ in 0..rng.gen_range(0, 4) {
-      strct = rot90(strct);
+    // Flip horizontally half the time
+    if chosen.mirror && rng.gen() {
+      strct.reverse();
+    }
+
+    // Perform 0 - 3 rotations
+    if chosen.rotate {
+      for _ in 0..rng.gen_range(0, 4) {
+        strct = rot90(&strct);
+      }
     }
 
     // Read details of vector
@@ -129,11 +149,26 @@ impl Structure {
     
     // Break with no change
     if x + w > total_w - 1 || y + h > total_h - 1 { return; }
+
+    // Break with no change if the structure doesn't belong in the biomes underneath
+    for tx in x..x+w {
+      for ty in y..y+h {
+        if !chosen.allowed_in(&grid[tx][ty].biome) { return; }
+      }
+    }
     
-    // Apply change
+    // Apply change. Tiles keep the biome they had, so a structure in a crypt is still in the crypt
     for tx in x..x+w {
       for ty in y..y+h {
-        grid[tx][ty] = strct[tx-x][ty-y].clone();
+        let biome = grid[tx][ty].biome.clone();
+        match strct[tx-x][ty-y] {
+          Cell::Tile(ref tile) => grid[tx][ty] = tile.clone(),
+          Cell::Spawn(ref spawn) => {
+            grid[tx][ty] = eqm::tile_from_id("floor").unwrap();
+            self.spawns.push((Pos::from_usize(tx, ty), spawn.clone()));
+          }
+        }
+        grid[tx][ty].biome = biome;
       }
     }
 
@@ -142,8 +177,10 @@ impl Structure {
   ///
   /// Return a new `Structure`
   ///
-  pub fn new() -> Self {
-    Structure {}
+  /// * `depth` - Depth of the floor the structures are going on
+  ///
+  pub fn new(depth: usize) -> Self {
+    Structure { depth, spawns: vec![] }
   }
 
 }
@@ -152,8 +189,8 @@ impl Filter for Structure {
 
   type Output = Tile;
 
-  fn apply(&mut self, grid: &mut map::Grid<Self::Output>) {
-    self.add_rand_struct(grid);
+  fn apply(&mut self, grid: &mut map::Grid<Self::Output>, rng: &mut StdRng) {
+    self.add_rand_struct(grid, rng);
   }
 
 }
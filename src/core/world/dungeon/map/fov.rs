@@ -0,0 +1,123 @@
+//!
+//! Line of sight for anything that isn't the player
+//!
+//! The player's field of view lives in the world's tcod map, which the renderer reads from. Creatures get their own
+//! `Vision` instead, so that working out what a monster sees never touches what the player sees.
+//!
+//! A tile is visible if it's within the radius and a straight line (see `line()`) reaches it without passing through
+//! anything `tile::opaque`. The opaque tile at the end of a line, like a wall, is visible itself.
+//!
+
+use core::world::dungeon::map::{self, Measurable, Pos, tile, Tile};
+
+///
+/// Get every point on a straight line from `start` to `end` with Bresenham's algorithm, both ends included
+///
+pub fn line(start: Pos, end: Pos) -> Vec<Pos> {
+
+  let dx = (end.x - start.x).abs();
+  let dy = -(end.y - start.y).abs();
+  let sx = if start.x < end.x { 1 } else { -1 };
+  let sy = if start.y < end.y { 1 } else { -1 };
+
+  let mut err = dx + dy;
+  let mut cur = start;
+  let mut points = vec![cur];
+
+  while cur != end {
+    let e2 = 2 * err;
+    if e2 >= dy {
+      err += dy;
+      cur.x += sx;
+    }
+    if e2 <= dx {
+      err += dx;
+      cur.y += sy;
+    }
+    points.push(cur);
+  }
+
+  return points;
+
+}
+
+///
+/// Everything a creature can see from where it stands
+///
+#[derive(Clone, Debug)]
+pub struct Vision {
+  origin: Pos,
+  radius: isize,
+  // Square of side `2 * radius + 1` centered on `origin`
+  visible: Vec<bool>
+}
+
+impl Vision {
+
+  ///
+  /// Work out what can be seen from `origin` up to `radius` tiles away
+  ///
+  pub fn new(grid: &map::Grid<Tile>, origin: Pos, radius: usize) -> Self {
+
+    let radius = radius as isize;
+    let side = 2 * radius + 1;
+
+    let mut vision = Vision {
+      origin,
+      radius,
+      visible: vec![false; (side * side) as usize]
+    };
+
+    for x in origin.x - radius..origin.x + radius + 1 {
+      for y in origin.y - radius..origin.y + radius + 1 {
+
+        let target = Pos::new(x, y);
+
+        // Out of the map or past the radius
+        if x < 0 || y < 0 || x >= grid.width() as isize || y >= grid.height() as isize || origin ^ target > radius as f32 {
+          continue;
+        }
+
+        // Everything between here and there has to be see through
+        let points = line(origin, target);
+        let clear = points.len() < 3 || points[1..points.len() - 1].iter().all(|pos| !tile::opaque(&grid[*pos]));
+
+        if clear {
+          let idx = vision.index(target).unwrap();
+          vision.visible[idx] = true;
+        }
+
+      }
+    }
+
+    return vision;
+
+  }
+
+  ///
+  /// Where a position is stored in `visible`, if it's within the square at all
+  ///
+  fn index(&self, pos: Pos) -> Option<usize> {
+    let (x, y) = (pos.x - self.origin.x + self.radius, pos.y - self.origin.y + self.radius);
+    let side = 2 * self.radius + 1;
+    if x < 0 || y < 0 || x >= side || y >= side {
+      return None;
+    }
+    return Some((x * side + y) as usize);
+  }
+
+  ///
+  /// Check if a position can be seen
+  ///
+  pub fn can_see(&self, pos: Pos) -> bool {
+    self.index(pos).map_or(false, |idx| self.visible[idx])
+  }
+
+  ///
+  /// How far the creature can see
+  ///
+  pub fn radius(&self) -> usize {
+    self.radius as usize
+  }
+
+}
@@ -0,0 +1,319 @@
+#[cfg(test)]
+mod tests {
+
+  extern crate rand;
+  use self::rand::{SeedableRng, StdRng};
+
+  use core::world::dungeon::Dungeon;
+  use core::world::dungeon::map::{self, Occupancy, Occupant, Pos, Tile, TILES};
+  use core::world::dungeon::map::fov::{self, Vision};
+  use core::world::dungeon::map::path::{self, Costs, FlowMap, Goal, Paths};
+  use core::world::dungeon::map::tile::{self, Biome};
+
+  ///
+  /// Make an open floor
+  ///
+  fn open(w: usize, h: usize) -> map::Grid<Tile> {
+    vec![vec![tile::generic_floor(); h]; w]
+  }
+
+  fn set(grid: &mut map::Grid<Tile>, id: &str, x: usize, y: usize) {
+    grid[x][y] = TILES.tile(id, &Biome::Dungeon);
+  }
+
+  ///
+  /// Check that every step of a path is next to the last, and walkable
+  ///
+  fn assert_connected(grid: &map::Grid<Tile>, start: Pos, path: &[Pos]) {
+    let mut prev = start;
+    for &step in path {
+      assert!((step.x - prev.x).abs() <= 1 && (step.y - prev.y).abs() <= 1, "{:?} doesn't follow {:?}", step, prev);
+      assert!(tile::walkable(&grid[step]), "{:?} isn't walkable", step);
+      prev = step;
+    }
+  }
+
+  //
+  // Pathfinding
+  //
+
+  #[test]
+  fn test_astar_around_wall() {
+
+    // A wall down x = 3 with a gap at the bottom
+    let mut grid = open(7, 7);
+    for y in 0..6 {
+      set(&mut grid, "wall", 3, y);
+    }
+
+    let (start, goal) = (Pos::new(1, 1), Pos::new(5, 1));
+    let route = path::astar(&grid, start, goal, &Costs::default()).unwrap();
+
+    assert_connected(&grid, start, &route);
+    assert_eq!(*route.last().unwrap(), goal);
+    assert!(route.contains(&Pos::new(3, 6)));
+    // Five steps down to the gap and five back up
+    assert_eq!(route.len(), 10);
+    assert_eq!(path::next_step(&grid, start, goal, &Costs::default()), Some(route[0]));
+
+    // Already there
+    assert_eq!(path::astar(&grid, start, start, &Costs::default()), Some(vec![]));
+
+  }
+
+  #[test]
+  fn test_astar_unreachable() {
+    let mut grid = open(7, 7);
+    for y in 0..7 {
+      set(&mut grid, "wall", 3, y);
+    }
+    assert_eq!(path::astar(&grid, Pos::new(1, 1), Pos::new(5, 1), &Costs::default()), None);
+    assert_eq!(path::next_step(&grid, Pos::new(1, 1), Pos::new(5, 1), &Costs::default()), None);
+  }
+
+  #[test]
+  fn test_astar_gives_up() {
+
+    // The only way past the wall is at the far end, which means searching most of the floor
+    let mut grid = open(100, 100);
+    for y in 0..99 {
+      set(&mut grid, "wall", 50, y);
+    }
+
+    let (start, goal) = (Pos::new(49, 0), Pos::new(51, 0));
+    assert_eq!(path::astar(&grid, start, goal, &Costs::default()), None);
+
+    // Even though there is a way
+    let flow = FlowMap::new(&grid, &[goal], &Costs::default());
+    assert!(flow.distance(start).is_some());
+
+  }
+
+  #[test]
+  fn test_astar_costs() {
+
+    // Vines along the top, water in the middle, and floor along the bottom
+    let mut grid = open(5, 3);
+    for x in 1..4 {
+      set(&mut grid, "vine", x, 0);
+      set(&mut grid, "water", x, 1);
+    }
+
+    let (start, goal) = (Pos::new(0, 1), Pos::new(4, 1));
+    let route = path::astar(&grid, start, goal, &Costs::default()).unwrap();
+    assert_connected(&grid, start, &route);
+    assert_eq!(route, vec![Pos::new(1, 2), Pos::new(2, 2), Pos::new(3, 2), goal]);
+
+    // Swimmers go straight through
+    let swimmer = Costs { water: 1, ..Costs::default() };
+    let route = path::astar(&grid, start, goal, &swimmer).unwrap();
+    assert_eq!(route.len(), 4);
+    assert!(route[..3].iter().all(|step| step.y != 0));
+
+    // Walls can't be walked through whatever they cost
+    assert_eq!(Costs::default().cost(&tile::generic_wall()), None);
+    assert_eq!(Costs::default().cost(&grid[2][1]), Some(Costs::default().water));
+
+  }
+
+  #[test]
+  fn test_flow_map_downhill() {
+
+    let mut grid = open(9, 9);
+    for y in 0..8 {
+      set(&mut grid, "wall", 4, y);
+    }
+    let goal = Pos::new(7, 1);
+    let flow = FlowMap::new(&grid, &[goal], &Costs::default());
+
+    // Walls are never reached
+    assert_eq!(flow.distance(Pos::new(4, 0)), None);
+    assert_eq!(flow.distance(goal), Some(0));
+
+    // Every step goes downhill, all the way to the goal
+    let mut pos = Pos::new(1, 1);
+    let mut steps = 0;
+    while let Some(next) = flow.next_step(&grid, pos, &Costs::default()) {
+      assert!(flow.distance(next).unwrap() < flow.distance(pos).unwrap());
+      assert!(tile::walkable(&grid[next]));
+      pos = next;
+      steps += 1;
+    }
+    assert_eq!(pos, goal);
+    assert_eq!(steps, flow.distance(Pos::new(1, 1)).unwrap());
+
+  }
+
+  #[test]
+  fn test_paths_cache() {
+
+    let mut grid = open(9, 9);
+    let (player, moved, start) = (Pos::new(7, 4), Pos::new(7, 5), Pos::new(1, 4));
+    let costs = Costs::default();
+    let mut paths = Paths::default();
+
+    assert_eq!(paths.flow(&grid, Goal::Player(player), &costs).distance(start), Some(6));
+
+    // The floor changes, but the cached map doesn't know
+    for y in 0..8 {
+      set(&mut grid, "wall", 4, y);
+    }
+    assert_eq!(paths.flow(&grid, Goal::Player(player), &costs).distance(start), Some(6));
+
+    // Once the player moves, the old map is thrown out, so going back makes a fresh one
+    assert!(paths.flow(&grid, Goal::Player(moved), &costs).distance(start).unwrap() > 6);
+    assert!(paths.flow(&grid, Goal::Player(player), &costs).distance(start).unwrap() > 6);
+
+    // Maps towards stairs stay until they're cleared
+    set(&mut grid, "down_stair", 7, 7);
+    assert_eq!(paths.flow(&grid, Goal::DownStairs, &costs).distance(Pos::new(7, 7)), Some(0));
+    set(&mut grid, "floor", 7, 7);
+    set(&mut grid, "down_stair", 8, 8);
+    assert_eq!(paths.flow(&grid, Goal::DownStairs, &costs).distance(Pos::new(7, 7)), Some(0));
+    paths.clear();
+    assert_eq!(paths.flow(&grid, Goal::DownStairs, &costs).distance(Pos::new(7, 7)), Some(1));
+
+  }
+
+
+  //
+  // Line of sight
+  //
+
+  #[test]
+  fn test_line() {
+
+    assert_eq!(fov::line(Pos::new(0, 0), Pos::new(5, 2)),
+               vec![Pos::new(0, 0), Pos::new(1, 0), Pos::new(2, 1), Pos::new(3, 1), Pos::new(4, 2), Pos::new(5, 2)]);
+
+    let start = Pos::new(3, 3);
+    assert_eq!(fov::line(start, start), vec![start]);
+
+    // One end in each octant
+    for &(dx, dy) in &[(5, 2), (2, 5), (-2, 5), (-5, 2), (-5, -2), (-2, -5), (2, -5), (5, -2)] {
+
+      let end = Pos::new(start.x + dx, start.y + dy);
+      let points = fov::line(start, end);
+
+      assert_eq!(points[0], start);
+      assert_eq!(*points.last().unwrap(), end);
+      assert_eq!(points.len() as isize, dx.abs().max(dy.abs()) + 1);
+
+      // Every point is a single step further along, never doubling back
+      for pair in points.windows(2) {
+        let (step_x, step_y) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+        assert!(step_x == 0 || step_x == dx.signum(), "line to {:?} steps back", end);
+        assert!(step_y == 0 || step_y == dy.signum(), "line to {:?} steps back", end);
+        assert!(step_x != 0 || step_y != 0);
+      }
+
+    }
+
+  }
+
+  #[test]
+  fn test_vision_blocked() {
+
+    let mut grid = open(9, 9);
+    set(&mut grid, "wall", 3, 4);
+    let vision = Vision::new(&grid, Pos::new(1, 4), 6);
+
+    assert!(vision.can_see(Pos::new(1, 4)));
+    assert!(vision.can_see(Pos::new(2, 4)));
+    // Walls can be seen, but not what's behind them
+    assert!(vision.can_see(Pos::new(3, 4)));
+    assert!(!vision.can_see(Pos::new(4, 4)));
+    assert!(!vision.can_see(Pos::new(6, 4)));
+    assert!(vision.can_see(Pos::new(1, 0)));
+
+  }
+
+  #[test]
+  fn test_vision_radius() {
+
+    let grid = open(9, 9);
+    let vision = Vision::new(&grid, Pos::new(4, 4), 2);
+
+    assert_eq!(vision.radius(), 2);
+    assert!(vision.can_see(Pos::new(6, 4)));
+    assert!(vision.can_see(Pos::new(4, 2)));
+    assert!(!vision.can_see(Pos::new(7, 4)));
+    // Corners of the square are further than the radius
+    assert!(!vision.can_see(Pos::new(6, 6)));
+    // Off the map
+    assert!(!vision.can_see(Pos::new(-1, 4)));
+
+  }
+
+  //
+  // Occupancy
+  //
+
+  #[test]
+  fn test_occupancy_place() {
+
+    let mut occupancy = Occupancy::new(5, 5);
+    assert!(!occupancy.is_occupied(Pos::new(2, 2)));
+
+    occupancy.place(Pos::new(2, 2), Occupant::Creature(3));
+    assert_eq!(occupancy.get(Pos::new(2, 2)), Some(Occupant::Creature(3)));
+    assert!(occupancy.is_occupied(Pos::new(2, 2)));
+    assert!(!occupancy.is_occupied(Pos::new(2, 3)));
+
+    // Placing replaces whatever was there
+    occupancy.place(Pos::new(2, 2), Occupant::Player);
+    assert_eq!(occupancy.get(Pos::new(2, 2)), Some(Occupant::Player));
+
+    // Off the floor is never occupied, and placing there does nothing
+    occupancy.place(Pos::new(-1, 2), Occupant::Player);
+    occupancy.place(Pos::new(5, 2), Occupant::Player);
+    assert_eq!(occupancy.get(Pos::new(-1, 2)), None);
+    assert_eq!(occupancy.get(Pos::new(5, 2)), None);
+
+  }
+
+  #[test]
+  fn test_occupancy_move() {
+
+    let mut occupancy = Occupancy::new(5, 5);
+    occupancy.place(Pos::new(1, 1), Occupant::Creature(0));
+
+    occupancy.move_occupant(Pos::new(1, 1), Pos::new(2, 1));
+    assert_eq!(occupancy.get(Pos::new(1, 1)), None);
+    assert_eq!(occupancy.get(Pos::new(2, 1)), Some(Occupant::Creature(0)));
+
+    // Moving nothing leaves the destination alone
+    occupancy.move_occupant(Pos::new(4, 4), Pos::new(2, 1));
+    assert_eq!(occupancy.get(Pos::new(2, 1)), Some(Occupant::Creature(0)));
+
+  }
+
+  #[test]
+  fn test_occupancy_remove() {
+
+    let mut occupancy = Occupancy::new(5, 5);
+    occupancy.place(Pos::new(3, 3), Occupant::Player);
+
+    assert_eq!(occupancy.remove(Pos::new(3, 3)), Some(Occupant::Player));
+    assert!(!occupancy.is_occupied(Pos::new(3, 3)));
+    assert_eq!(occupancy.remove(Pos::new(3, 3)), None);
+    assert_eq!(occupancy.remove(Pos::new(-1, -1)), None);
+
+  }
+
+  #[test]
+  fn test_occupancy_blocks_spawns() {
+
+    // Spawns are only ever picked away from the edges, which leaves (1, 1) and (2, 1) here
+    let grid = open(5, 4);
+    let mut occupancy = Occupancy::new(5, 4);
+    occupancy.place(Pos::new(1, 1), Occupant::Creature(0));
+
+    let mut rng = StdRng::from_seed(&[1337][..]);
+    for _ in 0..20 {
+      assert_eq!(Dungeon::get_valid_location(&grid, &occupancy, &mut rng), Pos::new(2, 1));
+    }
+
+  }
+
+}
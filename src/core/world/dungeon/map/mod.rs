@@ -4,6 +4,13 @@
 
 pub mod construct;
 
+pub mod fov;
+
+pub mod occupancy;
+pub use self::occupancy::{Occupancy, Occupant};
+
+pub mod path;
+
 pub mod grid;
 pub use self::grid::Grid;
 pub use self::grid::Measurable;
@@ -12,4 +19,9 @@ pub mod pos;
 pub use self::pos::Pos;
 
 pub mod tile;
-pub use self::tile::Tile;
\ No newline at end of file
+pub use self::tile::Tile;
+
+pub mod registry;
+pub use self::registry::TILES;
+
+mod map_tests;
\ No newline at end of file
@@ -0,0 +1,96 @@
+//!
+//! Who is standing where on a floor
+//!
+//! Only one thing gets to stand on a tile at a time. The world keeps an `Occupancy` for the current floor up to date
+//! as things move, so that checking if a tile is taken doesn't mean looking through every creature on the floor.
+//!
+//! Creatures are stored by their index into the floor's creatures, so the index is rebuilt whenever a floor is entered
+//! (see `Floor::reindex()`) and fixed up whenever dead creatures are cleared out (see `World::check_death()`).
+//!
+
+use core::world::dungeon::map::{self, Pos};
+
+///
+/// Something standing on a tile
+///
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Occupant {
+  Player,
+  // Index into the floor's creatures
+  Creature(usize)
+}
+
+///
+/// Index of every occupied tile on a floor
+///
+/// The default index is empty and treats every tile as free, which is what building a floor starts out with
+///
+#[derive(Clone, Debug, Default)]
+pub struct Occupancy {
+  grid: map::Grid<Option<Occupant>>
+}
+
+impl Occupancy {
+
+  ///
+  /// Return a new index with nothing in it
+  ///
+  pub fn new(width: usize, height: usize) -> Self {
+    Occupancy {
+      grid: vec![vec![None; height]; width]
+    }
+  }
+
+  ///
+  /// Get whatever is standing at `pos`
+  ///
+  pub fn get(&self, pos: Pos) -> Option<Occupant> {
+    if pos.x < 0 || pos.y < 0 {
+      return None;
+    }
+    self.grid.get(pos.x as usize).and_then(|col| col.get(pos.y as usize)).and_then(|occupant| *occupant)
+  }
+
+  ///
+  /// Check if something is standing at `pos`
+  ///
+  pub fn is_occupied(&self, pos: Pos) -> bool {
+    self.get(pos).is_some()
+  }
+
+  ///
+  /// Put something at `pos`, replacing whatever was there
+  ///
+  pub fn place(&mut self, pos: Pos, occupant: Occupant) {
+    if let Some(cell) = self.cell_mut(pos) {
+      *cell = Some(occupant);
+    }
+  }
+
+  ///
+  /// Clear `pos`, returning whatever was standing there
+  ///
+  pub fn remove(&mut self, pos: Pos) -> Option<Occupant> {
+    self.cell_mut(pos).and_then(|cell| cell.take())
+  }
+
+  ///
+  /// Move whatever is at `from` over to `to`
+  ///
+  pub fn move_occupant(&mut self, from: Pos, to: Pos) {
+    if let Some(occupant) = self.remove(from) {
+      self.place(to, occupant);
+    }
+  }
+
+  ///
+  /// Get the spot for `pos` if it's on the floor
+  ///
+  fn cell_mut(&mut self, pos: Pos) -> Option<&mut Option<Occupant>> {
+    if pos.x < 0 || pos.y < 0 {
+      return None;
+    }
+    self.grid.get_mut(pos.x as usize).and_then(|col| col.get_mut(pos.y as usize))
+  }
+
+}
@@ -0,0 +1,336 @@
+//!
+//! Pathfinding over a `Grid<Tile>`
+//!
+//! There are two ways to get somewhere:
+//!
+//! * `astar()` finds a path from one spot to another, which is good for one-off trips like following a smell
+//! * `FlowMap`s hold the distance from every tile to the nearest of some goals (Dijkstra maps). They cost more to make
+//! but any number of creatures can follow one downhill, so they get cached per floor in `Paths`
+//!
+//! Movement is in all 8 directions, and diagonals cost the same as anything else. How much a tile costs to walk
+//! through depends on the creature, see `Costs`.
+//!
+
+extern crate rand;
+use self::rand::{Rng, StdRng};
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use core::world::dungeon::map::{self, Measurable, Pos, tile, Tile};
+
+///
+/// Configuration
+///
+
+// How many tiles A* looks at before giving up, so that unreachable goals don't search the whole floor every turn
+const MAX_SEARCH : usize = 2000;
+
+// Every direction something can step in
+const DIRECTIONS : [(isize, isize); 8] = [(0, -1), (1, 0), (0, 1), (-1, 0), (1, -1), (1, 1), (-1, 1), (-1, -1)];
+
+///
+/// How much walking onto a tile costs a creature
+///
+/// Anything walkable that isn't listed costs 1. Unwalkable tiles can never be walked on, whatever the costs, except for
+/// closed doors, which creatures that know how to open doors can go through
+///
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Costs {
+  pub water: usize,
+  pub tall_grass: usize,
+  pub vine: usize,
+  // What going through a closed door costs, `None` for creatures that can't open doors
+  pub door: Option<usize>
+}
+
+impl Default for Costs {
+  fn default() -> Self {
+    Costs { water: 3, tall_grass: 2, vine: 2, door: None }
+  }
+}
+
+impl Costs {
+
+  ///
+  /// Cost of stepping onto a tile, `None` if it can't be walked on
+  ///
+  pub fn cost(&self, t: &Tile) -> Option<usize> {
+    if t.tiletype == tile::Type::Door(tile::Door::Closed) {
+      return self.door;
+    }
+    if !tile::walkable(t) {
+      return None;
+    }
+    match t.tiletype {
+      tile::Type::Water => Some(self.water),
+      tile::Type::TallGrass => Some(self.tall_grass),
+      tile::Type::Vine => Some(self.vine),
+      _ => Some(1)
+    }
+  }
+
+}
+
+///
+/// Get the in bounds neighbours of a spot along with what they cost to step onto
+///
+fn neighbours(grid: &map::Grid<Tile>, (x, y): (usize, usize), costs: &Costs) -> Vec<((usize, usize), usize)> {
+  DIRECTIONS.iter()
+    .map(|&(dx, dy)| (x as isize + dx, y as isize + dy))
+    .filter(|&(nx, ny)| nx >= 0 && ny >= 0 && (nx as usize) < grid.width() && (ny as usize) < grid.height())
+    .filter_map(|(nx, ny)| costs.cost(&grid[nx as usize][ny as usize]).map(|cost| ((nx as usize, ny as usize), cost)))
+    .collect()
+}
+
+///
+/// Pick a random walkable spot next to `pos`, for wandering around
+///
+pub fn wander(grid: &map::Grid<Tile>, pos: Pos, rng: &mut StdRng) -> Option<Pos> {
+  let steps : Vec<Pos> = neighbours(grid, (pos.x as usize, pos.y as usize), &Costs::default()).into_iter()
+    .map(|((x, y), _)| Pos::from_usize(x, y))
+    .collect();
+  rng.choose(&steps).cloned()
+}
+
+///
+/// Find the cheapest path from `start` to `goal`
+///
+/// The path leaves out `start` and ends on `goal`. `None` if there's no way there, or if it's too far to bother
+///
+pub fn astar(grid: &map::Grid<Tile>, start: Pos, goal: Pos, costs: &Costs) -> Option<Vec<Pos>> {
+
+  let (w, h) = (grid.width(), grid.height());
+  let start = (start.x as usize, start.y as usize);
+  let goal = (goal.x as usize, goal.y as usize);
+
+  if start == goal {
+    return Some(vec![]);
+  }
+
+  // Diagonals cost the same as anything else, so the number of steps is a heuristic that never overestimates
+  let heuristic = |(x, y): (usize, usize)| {
+    let dx = if x > goal.0 { x - goal.0 } else { goal.0 - x };
+    let dy = if y > goal.1 { y - goal.1 } else { goal.1 - y };
+    if dx > dy { dx } else { dy }
+  };
+
+  let mut best = vec![vec![usize::max_value(); h]; w];
+  let mut came_from : map::Grid<Option<(usize, usize)>> = vec![vec![None; h]; w];
+  let mut open = BinaryHeap::new();
+  let mut searched = 0;
+
+  best[start.0][start.1] = 0;
+  open.push(Reverse((heuristic(start), 0, start)));
+
+  while let Some(Reverse((_, dist, pos))) = open.pop() {
+
+    if pos == goal {
+      // Walk back to the start
+      let mut path = vec![];
+      let mut cur = goal;
+      while cur != start {
+        path.push(Pos::from_usize(cur.0, cur.1));
+        cur = came_from[cur.0][cur.1].unwrap();
+      }
+      path.reverse();
+      return Some(path);
+    }
+
+    // Stale entry, a cheaper way here was already found
+    if dist > best[pos.0][pos.1] {
+      continue;
+    }
+
+    searched += 1;
+    if searched > MAX_SEARCH {
+      return None;
+    }
+
+    for (next, cost) in neighbours(grid, pos, costs) {
+      let next_dist = dist + cost;
+      if next_dist < best[next.0][next.1] {
+        best[next.0][next.1] = next_dist;
+        came_from[next.0][next.1] = Some(pos);
+        open.push(Reverse((next_dist + heuristic(next), next_dist, next)));
+      }
+    }
+
+  }
+
+  return None;
+
+}
+
+///
+/// Get the first step of the cheapest path from `start` to `goal`
+///
+pub fn next_step(grid: &map::Grid<Tile>, start: Pos, goal: Pos, costs: &Costs) -> Option<Pos> {
+  astar(grid, start, goal, costs).and_then(|path| path.first().cloned())
+}
+
+///
+/// Distance from every tile to the nearest goal
+///
+#[derive(Clone, Debug)]
+pub struct FlowMap {
+  // `None` where no goal can be reached from
+  dist: map::Grid<Option<usize>>
+}
+
+impl FlowMap {
+
+  ///
+  /// Make a flow map towards some goals
+  ///
+  pub fn new(grid: &map::Grid<Tile>, goals: &[Pos], costs: &Costs) -> Self {
+
+    let mut dist : map::Grid<Option<usize>> = vec![vec![None; grid.height()]; grid.width()];
+    let mut open = BinaryHeap::new();
+
+    for goal in goals {
+      dist[goal.x as usize][goal.y as usize] = Some(0);
+      open.push(Reverse((0, (goal.x as usize, goal.y as usize))));
+    }
+
+    while let Some(Reverse((d, pos))) = open.pop() {
+
+      // Stale entry
+      if dist[pos.0][pos.1].map_or(false, |best| d > best) {
+        continue;
+      }
+
+      // Costs are for stepping onto a tile, and flow maps are walked away from the goals,
+      // so the cost of a step is the cost of the tile being left
+      let cost = match costs.cost(&grid[pos.0][pos.1]) {
+        Some(cost) => cost,
+        None => continue
+      };
+
+      for (next, _) in neighbours(grid, pos, costs) {
+        let next_dist = d + cost;
+        if dist[next.0][next.1].map_or(true, |best| next_dist < best) {
+          dist[next.0][next.1] = Some(next_dist);
+          open.push(Reverse((next_dist, next)));
+        }
+      }
+
+    }
+
+    return FlowMap { dist };
+
+  }
+
+  ///
+  /// Distance from a spot to the nearest goal, `None` if it can't get there
+  ///
+  pub fn distance(&self, pos: Pos) -> Option<usize> {
+    self.dist[pos.x as usize][pos.y as usize]
+  }
+
+  ///
+  /// Get the step from `pos` that goes the furthest downhill, `None` if it's already at a goal or stuck
+  ///
+  pub fn next_step(&self, grid: &map::Grid<Tile>, pos: Pos, costs: &Costs) -> Option<Pos> {
+
+    let mut best = self.distance(pos)?;
+    let mut step = None;
+
+    for (next, _) in neighbours(grid, (pos.x as usize, pos.y as usize), costs) {
+      if let Some(d) = self.dist[next.0][next.1] {
+        if d < best {
+          best = d;
+          step = Some(Pos::from_usize(next.0, next.1));
+        }
+      }
+    }
+
+    return step;
+
+  }
+
+}
+
+///
+/// Things flow maps can lead to
+///
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Goal {
+  // Wherever the player is standing
+  Player(Pos),
+  DownStairs,
+  UpStairs
+}
+
+///
+/// Flow maps that have been made for a floor
+///
+/// Maps towards stairs are kept for as long as the floor is, and maps towards the player until the player moves
+///
+#[derive(Clone, Debug, Default)]
+pub struct Paths {
+  maps: Vec<(Goal, Costs, FlowMap)>
+}
+
+impl Paths {
+
+  ///
+  /// Get a flow map towards a goal for a creature with some movement costs, making it if it isn't cached
+  ///
+  pub fn flow(&mut self, grid: &map::Grid<Tile>, goal: Goal, costs: &Costs) -> &FlowMap {
+
+    // The player moved, so every map towards them is out of date
+    if let Goal::Player(pos) = goal {
+      self.maps.retain(|&(cached, _, _)| match cached {
+        Goal::Player(cached_pos) => cached_pos == pos,
+        _ => true
+      });
+    }
+
+    let idx = match self.maps.iter().position(|&(cached, cached_costs, _)| cached == goal && cached_costs == *costs) {
+      Some(idx) => idx,
+      None => {
+        let goals = match goal {
+          Goal::Player(pos) => vec![pos],
+          Goal::DownStairs => Paths::find(grid, |t| match t.tiletype {
+            tile::Type::Stair(tile::Stair::DownStair(_)) => true,
+            _ => false
+          }),
+          Goal::UpStairs => Paths::find(grid, |t| match t.tiletype {
+            tile::Type::Stair(tile::Stair::UpStair(_)) => true,
+            _ => false
+          })
+        };
+        self.maps.push((goal, *costs, FlowMap::new(grid, &goals, costs)));
+        self.maps.len() - 1
+      }
+    };
+
+    return &self.maps[idx].2;
+
+  }
+
+  ///
+  /// Forget every flow map, for when the floor itself changes
+  ///
+  pub fn clear(&mut self) {
+    self.maps.clear();
+  }
+
+  ///
+  /// Find every tile that matches
+  ///
+  fn find<F: Fn(&Tile) -> bool>(grid: &map::Grid<Tile>, matches: F) -> Vec<Pos> {
+    let mut found = vec![];
+    for x in 0..grid.width() {
+      for y in 0..grid.height() {
+        if matches(&grid[x][y]) {
+          found.push(Pos::from_usize(x, y));
+        }
+      }
+    }
+    return found;
+  }
+
+}
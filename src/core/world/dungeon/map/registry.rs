@@ -0,0 +1,323 @@
+//!
+//! Registry of every tile the dungeon is built from, loaded from `config/tiles.yml`
+//!
+//! The file maps tile ids to tile definitions:
+//!
+//! ```yaml
+//! wall:
+//!   name: Wall
+//!   glyph: " "
+//!   type: wall
+//!   opaque: true
+//!   walkable: false
+//!   spawnable: false
+//!   colors:
+//!     default: { fg: [40, 40, 40], bg: [33, 33, 33] }
+//!     cave: { fg: [67, 57, 57], bg: [60, 50, 50] }
+//! ```
+//!
+//! `type` is what the game treats the tile as, like a stair or a trap (see `TYPES`).
+//! `opaque` tiles block vision, `walkable` tiles can be walked on, and `spawnable` tiles can have things spawned on them
+//! or be replaced by later steps of dungeon generation.
+//! `colors` holds the colors of the tile in each biome, by biome name. Biomes that aren't listed use `default`, which
+//! has to be there.
+//!
+//! Generators and `.eqm` structures refer to tiles by their id, so every id in `REQUIRED` has to be defined.
+//!
+
+// Serde
+extern crate serde_yaml;
+
+// Read files
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, prelude::*};
+
+use core::renderer::RGB;
+use core::world::dungeon::map::tile::{self, Biome, Flags, Tile};
+
+///
+/// Configuration
+///
+
+// Where tiles are defined
+pub const TILE_PATH : &str = "config/tiles.yml";
+
+// Tiles the game builds floors out of by id, which the file must define
+pub const REQUIRED : [&str; 20] = [
+  "wall", "hard_wall", "floor", "crystal_wall", "crystal_floor", "tall_grass", "vine", "water", "fountain", "up_stair",
+  "down_stair", "memory_loss_trap", "shaft_trap", "paint_bomb_trap", "teleport_trap", "spike_trap", "closed_door",
+  "open_door", "locked_door", "secret_door"
+];
+
+// Valid type strings, for error messages
+const TYPES : [&str; 20] = [
+  "wall", "hard_wall", "crystal_wall", "floor", "crystal_floor", "up_stair", "down_stair", "tall_grass", "vine", "water",
+  "art_structure", "memory_loss_trap", "shaft_trap", "paint_bomb_trap", "teleport_trap", "spike_trap", "closed_door",
+  "open_door", "locked_door", "secret_door"
+];
+
+// Every tile the game knows about. Loaded the first time it's used, which is when the first floor gets built
+lazy_static! {
+  pub static ref TILES : Registry = Registry::load(TILE_PATH).unwrap_or_else(|err| panic!("{}", err));
+}
+
+///
+/// Things that can go wrong while loading tiles
+///
+#[derive(Debug)]
+pub enum TileError {
+  // Couldn't read the file
+  Io(io::Error),
+  // File isn't valid YAML, or has missing or unknown keys
+  Format(serde_yaml::Error),
+  // Type string doesn't match any `tile::Type`
+  Type(String, String),
+  // Colors are given for a biome that doesn't exist
+  Biome(String, String),
+  // There are no default colors
+  NoDefault(String),
+  // A tile the game needs isn't defined
+  Missing(&'static str)
+}
+
+impl fmt::Display for TileError {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      TileError::Io(ref err) =>
+        write!(fmt, "{}: could not read tile file: {}", TILE_PATH, err),
+      TileError::Format(ref err) =>
+        write!(fmt, "{}: bad tile definition: {}", TILE_PATH, err),
+      TileError::Type(ref id, ref tiletype) =>
+        write!(fmt, "{}: tile `{}` has unknown type `{}`, expected one of {}", TILE_PATH, id, tiletype, TYPES.join(", ")),
+      TileError::Biome(ref id, ref biome) =>
+        write!(fmt, "{}: tile `{}` has colors for unknown biome `{}`", TILE_PATH, id, biome),
+      TileError::NoDefault(ref id) =>
+        write!(fmt, "{}: tile `{}` has no default colors", TILE_PATH, id),
+      TileError::Missing(id) =>
+        write!(fmt, "{}: tile `{}` is needed by the game but isn't defined", TILE_PATH, id)
+    }
+  }
+}
+
+///
+/// Turn a type string from the tile file into a `tile::Type`
+///
+fn type_from_str(tiletype: &str) -> Option<tile::Type> {
+  match tiletype {
+    "wall"             => Some(tile::Type::Wall(tile::Wall::Normal)),
+    "hard_wall"        => Some(tile::Type::Wall(tile::Wall::Hard)),
+    "crystal_wall"     => Some(tile::Type::Wall(tile::Wall::Crystal)),
+    "floor"            => Some(tile::Type::Floor(tile::Floor::Normal)),
+    "crystal_floor"    => Some(tile::Type::Floor(tile::Floor::Crystal)),
+    "up_stair"         => Some(tile::Type::Stair(tile::Stair::UpStair(tile::UpStair::Normal))),
+    "down_stair"       => Some(tile::Type::Stair(tile::Stair::DownStair(tile::DownStair::Normal))),
+    "tall_grass"       => Some(tile::Type::TallGrass),
+    "vine"             => Some(tile::Type::Vine),
+    "water"            => Some(tile::Type::Water),
+    "art_structure"    => Some(tile::Type::ArtStructure),
+    "memory_loss_trap" => Some(tile::Type::Trap(tile::Trap::MemoryLoss)),
+    "shaft_trap"       => Some(tile::Type::Trap(tile::Trap::Shaft)),
+    "paint_bomb_trap"  => Some(tile::Type::Trap(tile::Trap::PaintBomb)),
+    "teleport_trap"    => Some(tile::Type::Trap(tile::Trap::Teleport)),
+    "spike_trap"       => Some(tile::Type::Trap(tile::Trap::Spike)),
+    "closed_door"      => Some(tile::Type::Door(tile::Door::Closed)),
+    "open_door"        => Some(tile::Type::Door(tile::Door::Open)),
+    "locked_door"      => Some(tile::Type::Door(tile::Door::Locked)),
+    "secret_door"      => Some(tile::Type::Door(tile::Door::Secret)),
+    _ => None
+  }
+}
+
+///
+/// Colors of a tile in one biome
+///
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Colors {
+  fg: RGB,
+  bg: RGB
+}
+
+///
+/// Tile as written in the file
+///
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TileDef {
+  name: String,
+  glyph: char,
+  #[serde(rename = "type")]
+  tiletype: String,
+  opaque: bool,
+  walkable: bool,
+  spawnable: bool,
+  colors: BTreeMap<String, Colors>
+}
+
+///
+/// A tile that has been checked over and is ready to be put on a floor
+///
+#[derive(Clone)]
+pub struct TileKind {
+  pub id: String,
+  pub name: &'static str,
+  pub glyph: char,
+  pub tiletype: tile::Type,
+  pub flags: Flags,
+  // Colors for biomes that have their own, the rest use `default`
+  biomes: Vec<(Biome, RGB, RGB)>,
+  default: (RGB, RGB)
+}
+
+impl TileKind {
+
+  ///
+  /// Get the foreground and background colors of the tile in a biome
+  ///
+  pub fn colors(&self, biome: &Biome) -> (RGB, RGB) {
+    match self.biomes.iter().find(|&&(ref b, _, _)| b == biome) {
+      Some(&(_, fg, bg)) => (fg, bg),
+      None => self.default
+    }
+  }
+
+  ///
+  /// Make a `Tile` of this kind in a biome
+  ///
+  pub fn create(&self, biome: &Biome) -> Tile {
+    let (fg, bg) = self.colors(biome);
+    let mut t = Tile::new(self.name, self.glyph, fg, bg, self.tiletype.clone());
+    t.flags = self.flags;
+    t.biome = biome.clone();
+    return t;
+  }
+
+  ///
+  /// Turn a tile that's already on a floor into this kind
+  ///
+  /// The tile keeps its biome along with whatever has built up on it, like scents and whether it's been seen, which is
+  /// what things like doors opening want
+  ///
+  pub fn change(&self, t: &mut Tile) {
+    let (fg, bg) = self.colors(&t.biome);
+    t.set_name(self.name);
+    t.glyph = self.glyph;
+    t.fg = fg;
+    t.bg = bg;
+    t.tiletype = self.tiletype.clone();
+    t.flags = self.flags;
+  }
+
+}
+
+///
+/// Every loaded tile
+///
+pub struct Registry {
+  tiles: Vec<TileKind>
+}
+
+impl Registry {
+
+  ///
+  /// Get a tile kind by id
+  ///
+  pub fn get(&self, id: &str) -> Option<&TileKind> {
+    self.tiles.iter().find(|kind| kind.id == id)
+  }
+
+  ///
+  /// Find the name of a tile kind, so a name read back from a save can point at the registry's copy
+  ///
+  pub fn name(&self, name: &str) -> Option<&'static str> {
+    self.tiles.iter().find(|kind| kind.name == name).map(|kind| kind.name)
+  }
+
+  ///
+  /// Make a tile by id in a biome
+  ///
+  /// Only for the ids in `REQUIRED`, which are checked for when the registry is loaded. Anything else should go
+  /// through `get()`
+  ///
+  pub fn tile(&self, id: &str, biome: &Biome) -> Tile {
+    self.get(id).unwrap_or_else(|| panic!("{}: tile `{}` isn't defined", TILE_PATH, id)).create(biome)
+  }
+
+  ///
+  /// Turn a tile into another by id, with the same rules as `tile()`
+  ///
+  pub fn change(&self, id: &str, t: &mut Tile) {
+    self.get(id).unwrap_or_else(|| panic!("{}: tile `{}` isn't defined", TILE_PATH, id)).change(t)
+  }
+
+  ///
+  /// Get the colors of a tile by id in a biome, with the same rules as `tile()`
+  ///
+  pub fn colors(&self, id: &str, biome: &Biome) -> (RGB, RGB) {
+    self.get(id).unwrap_or_else(|| panic!("{}: tile `{}` isn't defined", TILE_PATH, id)).colors(biome)
+  }
+
+  ///
+  /// Load the tile file into a `Registry`
+  ///
+  pub fn load(path: &str) -> Result<Registry, TileError> {
+
+    // Load file to String
+    let mut contents = String::new();
+    File::open(path)
+      .and_then(|mut file| file.read_to_string(&mut contents))
+      .map_err(TileError::Io)?;
+
+    let defs : BTreeMap<String, TileDef> = serde_yaml::from_str(&contents).map_err(TileError::Format)?;
+
+    let mut tiles = vec![];
+
+    for (id, def) in defs {
+
+      let tiletype = match type_from_str(&def.tiletype) {
+        Some(tiletype) => tiletype,
+        None => return Err(TileError::Type(id, def.tiletype))
+      };
+
+      let mut default = None;
+      let mut biomes = vec![];
+      for (name, colors) in def.colors {
+        if name == "default" {
+          default = Some((colors.fg, colors.bg));
+          continue;
+        }
+        match Biome::from_name(&name) {
+          Some(biome) => biomes.push((biome, colors.fg, colors.bg)),
+          None => return Err(TileError::Biome(id, name))
+        }
+      }
+
+      let default = match default {
+        Some(default) => default,
+        None => return Err(TileError::NoDefault(id))
+      };
+
+      tiles.push(TileKind {
+        id,
+        // Tiles need `&'static str` names, and tiles are loaded only once
+        name: Box::leak(def.name.into_boxed_str()),
+        glyph: def.glyph,
+        tiletype,
+        flags: Flags { opaque: def.opaque, walkable: def.walkable, spawnable: def.spawnable },
+        biomes,
+        default
+      });
+
+    }
+
+    if let Some(id) = REQUIRED.iter().find(|id| !tiles.iter().any(|kind| kind.id == **id)) {
+      return Err(TileError::Missing(*id));
+    }
+
+    return Ok(Registry { tiles });
+
+  }
+
+}
@@ -2,11 +2,14 @@ use std::fmt;
 use std::slice::Iter;
 
 use core::renderer::{Renderable, RGB};
+use core::save::StaticStr;
+use core::world::dungeon::DungeonKind;
+use core::world::dungeon::map::TILES;
 
 ///
 /// Tiles have types
 ///
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Type {
   Wall(Wall),
   Floor(Floor),
@@ -16,6 +19,7 @@ pub enum Type {
   Water,
   Unseen,
   Trap(Trap),
+  Door(Door),
   ArtStructure,
   Debug
 }
@@ -23,7 +27,7 @@ pub enum Type {
 ///
 /// Floors have types
 ///
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Floor {
   Normal,
   Crystal
@@ -32,7 +36,7 @@ pub enum Floor {
 ///
 /// Walls have types
 /// 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Wall {
   Normal,
   Crystal,
@@ -42,7 +46,7 @@ pub enum Wall {
 ///
 /// Traps have types
 ///
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Trap {
   MemoryLoss,
   Shaft,
@@ -51,10 +55,23 @@ pub enum Trap {
   Spike
 }
 
+///
+/// Doors have states
+///
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Door {
+  Closed,
+  Open,
+  // Has to be forced open
+  Locked,
+  // Looks like a wall until it's found
+  Secret
+}
+
 ///
 /// Stairs have types
 /// 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Stair {
   DownStair(DownStair),
   UpStair(UpStair)
@@ -63,70 +80,53 @@ pub enum Stair {
 ///
 /// Up/Down stairs have types
 /// 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum DownStair {
-  Normal
+  Normal,
+  // Entrance to a side branch of the dungeon
+  Branch(DungeonKind)
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum UpStair {
   Normal
 }
 
 ///
 /// Properties
-/// 
-
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub enum Props {
-  Visibility(Visibility),
-  Traversability(Traversability)
-}
-
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub enum Visibility {
-  Opaque,
-  Transparent
-}
-
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub enum Traversability {
-  Walkable,
-  Blocking
+///
+/// Every tile in the tile registry sets these, see `registry`
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Flags {
+  // Blocks vision
+  pub opaque: bool,
+  // Can be walked on
+  pub walkable: bool,
+  // Stuff can be spawned on it, and later steps of generation can replace it
+  pub spawnable: bool
 }
 
 ///
-/// Tile type helper functions
-/// 
-/// The idea is that important or reusable matching patterns can be placed into these functions for a broad range
-/// of other resources to utilize without needing to update all of those patterns individually.
-/// 
-/// They are located in this file, as when tile types are added, the developer also ideally updates these lists at the same
-/// time, meaning new tile types can be introduced swiftly
-/// 
+/// Tile property helper functions
+///
+/// These used to be matches over tile types, but now they just read the flags each tile got from the registry.
+/// They stay around since everything that cares about tiles already goes through them
+///
 
 // Does the tile block vision?
 pub fn opaque(t: &Tile) -> bool {
-  match t.tiletype {
-    Type::Wall(_) | Type::TallGrass | Type::ArtStructure => true,
-    _ => false
-  }
+  t.flags.opaque
 }
 
 // Is it okay to spawn stuff on this tile / replace it?
 pub fn spawnable(t: &Tile) -> bool {
-  match t.tiletype {
-    Type::Floor(_) | Type::Water | Type::TallGrass | Type::Vine => true,
-    _ => false
-  }
+  t.flags.spawnable
 }
 
 // Is the tile able to be walked on?
 pub fn walkable(t: &Tile) -> bool {
-  match t.tiletype {
-    Type::Floor(_) | Type::Water | Type::Stair(_) | Type::Trap(_) | Type::TallGrass | Type::Vine => true,
-    _ => false
-  }
+  t.flags.walkable
 }
 
 ///
@@ -134,29 +134,17 @@ pub fn walkable(t: &Tile) -> bool {
 /// 
 
 pub fn generic_floor() -> Tile {
-  Tile::new(
-    "Generic Floor",
-    ' ',
-    RGB(0, 0, 0),
-    RGB(0, 0, 0),
-    Type::Floor(Floor::Normal)
-  )
+  TILES.tile("floor", &Biome::Dungeon)
 }
 
 pub fn generic_wall() -> Tile {
-  Tile::new(
-    "Generic Wall",
-    ' ',
-    RGB(0, 0, 0),
-    RGB(0, 0, 0),
-    Type::Wall(Wall::Normal)
-  )
+  TILES.tile("wall", &Biome::Dungeon)
 }
 
 ///
 /// Tiles have biomes
 ///
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Biome {
   Dungeon,
   Crypt,
@@ -165,6 +153,24 @@ pub enum Biome {
   Crystal
 }
 
+impl Biome {
+
+  ///
+  /// Get a biome by the name it goes by in data files
+  ///
+  pub fn from_name(name: &str) -> Option<Biome> {
+    match name {
+      "dungeon" => Some(Biome::Dungeon),
+      "crypt"   => Some(Biome::Crypt),
+      "cave"    => Some(Biome::Cave),
+      "sunken"  => Some(Biome::Sunken),
+      "crystal" => Some(Biome::Crystal),
+      _ => None
+    }
+  }
+
+}
+
 // Implement ability to turn the enum into a string
 impl fmt::Display for Biome {
   fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
@@ -181,7 +187,7 @@ impl fmt::Display for Biome {
 ///
 /// Scents
 /// 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Scent {
   Player = 0,
   Insectoid,
@@ -231,9 +237,27 @@ impl Scent {
     ];
     SCENT_TYPES.iter()
   }
+
+  ///
+  /// Color the scent tints tiles with when the player smells it
+  ///
+  pub fn color(&self) -> RGB {
+    match *self {
+      Scent::Player    => RGB(200, 60, 60),
+      Scent::Insectoid => RGB(60, 200, 60),
+      Scent::Canine    => RGB(60, 100, 220),
+      Scent::Feline    => RGB(220, 200, 60),
+      Scent::Reptilian => RGB(40, 160, 120),
+      Scent::Decay     => RGB(120, 140, 40),
+      Scent::Avian     => RGB(200, 200, 230),
+      Scent::Smoke     => RGB(130, 130, 130),
+      Scent::Incense   => RGB(200, 120, 220),
+      Scent::Num       => RGB(0, 0, 0)
+    }
+  }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct _Scent {
   pub val: u8,
   pub scent_type: Scent
@@ -254,9 +278,9 @@ impl _Scent {
 ///
 /// Tile represents an environmental entity
 /// 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Tile {
-  name: &'static str,
+  name: StaticStr,
   pub glyph: char,
   pub fg: RGB,
   pub bg: RGB,
@@ -264,18 +288,25 @@ pub struct Tile {
   pub scents: Vec<_Scent>,
   pub sound: usize,
   pub tiletype: Type,
-  pub seen: bool
+  pub flags: Flags,
+  pub seen: bool,
+  // Touched by corruption, which wears down the sanity of anyone nearby
+  #[serde(default)]
+  pub corrupted: bool
 }
 
 impl Tile {
 
   ///
   /// Return a new `Tile`
+  ///
+  /// Tiles made this way have no flags set, so they're see through and can't be walked on. That's fine for things
+  /// like debug overlays, but tiles that go on a floor should come from the registry with `TILES.tile()`
   /// 
   #[inline]
   pub fn new(name: &'static str, glyph: char, fg: RGB, bg: RGB, tiletype: Type) -> Self {
     Tile { 
-      name,
+      name: StaticStr(name),
       glyph,
       fg,
       bg,
@@ -290,12 +321,14 @@ impl Tile {
       },
       sound: 0,
       tiletype,
-      seen: false
+      flags: Flags::default(),
+      seen: false,
+      corrupted: false
     }
   }
 
   pub fn set_name(&mut self, name: &'static str) {
-    self.name = name;
+    self.name = StaticStr(name);
   }
 
 }
@@ -319,7 +352,7 @@ impl Renderable for Tile {
 
   #[inline]
   fn get_id(&self) -> &'static str {
-    self.name.clone()
+    self.name.0
   }
 
   #[inline]
@@ -339,7 +372,7 @@ impl Renderable for Tile {
 
   #[inline]
   fn set_id(&mut self, name: &'static str) {
-    self.name = name;
+    self.name = StaticStr(name);
   }
 
 }
\ No newline at end of file
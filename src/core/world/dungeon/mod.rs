@@ -1,18 +1,25 @@
 //!
 //! Generate a super simple dungeon
 //!
+//! How a floor gets generated depends on the branch of the dungeon it's in, see `branch`
+//!
 
 extern crate rand;
-use self::rand::Rng;
+use self::rand::{Rng, StdRng};
 
 use core::renderer::{Renderable, RGB};
 
 pub mod map;
-use self::map::{tile, Pos, Tile, Measurable};
+use self::map::{tile, Occupancy, Pos, Tile, TILES, Measurable};
+
+pub mod branch;
+pub use self::branch::DungeonKind;
+use self::branch::{BRANCHES, Palette, Step};
 
 // Privately use filter
 mod filter;
 use self::filter::{Filter, Structure, Simple, Stain};
+pub use self::filter::eqm::Spawn;
 
 // Privately use automata
 mod automata;
@@ -44,11 +51,19 @@ const VINE_GLYPHS : [char; 5] = [
 ///
 /// `Dungeon` struct to stitch together all builders and cellular automatons
 ///
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Dungeon {
   pub width: usize,
   pub height: usize,
   pub grid: map::Grid<Tile>,
+  // Branch of the dungeon the floor is in
+  pub kind: DungeonKind,
+  // How deep the floor is, the first floor being 0
+  #[serde(default)]
+  pub depth: usize,
+  // Monsters and items asked for by structures, left for the world to spawn
+  #[serde(skip)]
+  pub spawns: Vec<(Pos, Spawn)>
 }
 
 // Make Dungeon Indexable
@@ -94,12 +109,13 @@ impl Dungeon {
 
 
   ///
-  /// Add a tile to the gird and preserve the existing BG color of the spot
+  /// Add a tile to the gird and preserve the existing BG color and biome of the spot
   /// 
   fn add_tile(&mut self, g: &mut map::Grid<Tile>, t: &mut Tile, pos: Pos) {
     // Get the background color of the tile that the new one will be going on top of
     let bg_col = g[pos].get_bg();
     t.set_bg(bg_col);
+    t.biome = g[pos].biome.clone();
     // Replace grid tile with tile
     g[pos] = t.clone();
   }
@@ -107,233 +123,179 @@ impl Dungeon {
   ///
   /// Make the dungeon
   ///
-  pub fn build(mut self) -> Self {
+  /// Every random choice made while building is drawn from `rng`, so the same seed always builds the same floor
+  ///
+  pub fn build(mut self, rng: &mut StdRng) -> Self {
 
     // The purpose of this function is to create some basic grid object, 
     // and completely fill it out into a dungeon.
 
-    // Currently, it only builds on specific type of dungeon, and in the future,
-    // there should be some construct to generate types of dungeons.
+    // What actually happens depends on the branch the floor is in. Each branch has its own list of steps,
+    // see `DungeonKind::pipeline()`, which get run one after the other on the same grid.
 
-    // 'Types' here refers to dungeons with different themes, that have different terrain features -
-    // think of branches in DCSS. Coupled with this, the Dungeon struct should hold onto this type and
-    // the world should be able to access it to generate items and monsters in the type/biome combinations
-    // that will appear
+    debugln!("dungeon", format!("building floor {} of {}...", self.depth, self.kind.name()));
 
-    debugln!("dungeon", "building floor...");
+    let biome = self.kind.biome();
 
-    // We start with a basic grid object. We will pass references of this object into various functions to carve out a dungeon.
-    let mut grid : map::Grid<Tile>;
+    // Create several reusable constructs for walls and floors. Since passing these directly into functions
+    // will cause the references to be lost, clone them first, since Tile derives Clone.
+    let wall = TILES.tile("wall", &biome);
+    let floor = TILES.tile("floor", &biome);
+
+    // We start with a basic grid object, filled with walls. We will pass references of this object into
+    // various functions to carve out a dungeon.
+    let mut grid = Dungeon::generate_grid(self.width, self.height, wall.clone());
+
+    for step in self.kind.pipeline() {
+      match step {
+
+        // Creates a simple corridor/room dungeon based off the simple builder
+        Step::Rooms => {
+          let mut simple = Simple::new(&grid);
+          simple.floor = floor.clone();
+          simple.apply(&mut grid, rng);
+        },
+
+        // This is geared towards eating walls and replacing them with floors, so mainly just to flesh out the dungeon.
+        Step::Walk(chaos, iter) => {
+          DrunkardsWalkD4::new(chaos).apply(&mut grid, None, Some(wall.clone()), floor.clone(), iter, rng);
+        },
+
+        // Add 1 - 3 structures, weighted towards 1
+        // So like a normal person would use a normal distribution or something
+        // but I could not get it to work for the life of me, probably due to a version mismatch in rand
+        // This is like, pretty good, and I like the results, so, why not.
+        // ---
+        // An interesting property of dungeon generation is that, more often than not, structures get "corrupted"
+        // or "eroded" by the way generation occurs. The earlier something appears in the generation stage,
+        // the more likely it is to not make it out 100% intact.
+        // So, if structures need to be "preserved" better, move them down the pipeline, and the same principle
+        // holds for all features/filters/whatever
+        Step::Structures => {
+          let mut structure = Structure::new(self.depth);
+          for _ in 0..*rng.choose(&[1, 1, 1, 1, 2, 3]).unwrap() {
+            structure.apply(&mut grid, rng);
+          }
+          self.spawns.extend(structure.spawns);
+        },
 
-    // Fill the map with walls first
-    grid = Dungeon::generate_grid(self.width, self.height, Tile::new(
-      "Wall",
-      ' ',
-      RGB(40, 40, 40),
-      RGB(33, 33, 33),
-      tile::Type::Wall(tile::Wall::Normal))
-    );
+        Step::Biome(biome, threshold) => self.add_biome(&mut grid, biome, threshold, rng),
+        Step::Water(threshold) => self.add_water(&mut grid, threshold, rng),
+        Step::Crystal(threshold) => self.add_crystal(&mut grid, threshold, rng),
+        Step::TallGrass(threshold) => self.add_tall_grass(&mut grid, threshold, rng),
+        Step::Vines(threshold) => self.add_vines(&mut grid, threshold, rng),
 
-    // Apply simple builder. This creates a simple corridor/room dungeon based off the simple builder
-    // Note how the grid is being consumed to replace itself here, but we don't want this later on.
-    Simple::new(&grid).apply(&mut grid);
+        // Add pretty details last.
+        Step::HardWalls => self.add_hard_walls(&mut grid, rng),
+        Step::FloorFeatures => self.add_floor_features(&mut grid, rng),
 
-    // Create several reusable constructs for walls and floors. Since passing these directly into functions
-    // will cause the references to be lost, clone them first, since Tile derives Clone.
-    let wall = Tile::new(
-      "Wall",
-      ' ',
-      RGB(40, 40, 40),
-      RGB(33, 33, 33),
-      tile::Type::Wall(tile::Wall::Normal)
-    );
-
-    let floor = Tile::new(
-      "Floor",
-      ' ',
-      RGB(27, 27, 27),
-      RGB(20, 20, 20),
-      tile::Type::Floor(tile::Floor::Normal)
-    );
-
-    // Closure for generating drunkards walks. Basically just makes the process of adding these
-    // easy. And since we don't use it anywhere else, it doesn't really make sense to turn it into a function,
-    // though it may be in the future.
-    //
-    // This is geared towards eating walls and replacing them with floors, so mainly just to flesh out the dungeon.
-    let drunk_d4 = |chaos: f32, iter: u32, grid: &mut map::Grid<Tile> | {
-      let d = DrunkardsWalkD4::new(chaos);
-      d.apply(
-        grid,
-        None,
-        Some(wall.clone()),
-        floor.clone(),
-        iter
-      )
-    };
-
-    // Make three passes of this basic walk to carve caves.
-
-    // Total randomness - Really centralized areas that are mostly opened since it walks over itself a lot
-    drunk_d4(1.0, 800, &mut grid);
-
-    // Semi random - A mixture of the previous and next option
-    drunk_d4(0.5, 1000, &mut grid);
-
-    // Mostly orderly - Long corridors that occasionally deviate
-    drunk_d4(0.25, 1000, &mut grid);
-
-    // Add 1 - 3 structures, weighted towards 1
-    // So like a normal person would use a normal distribution or something
-    // but I could not get it to work for the life of me, probably due to a version mismatch in rand
-    // This is like, pretty good, and I like the results, so, why not.
-    // ---
-    // An interesting property of dungeon generation is that, more often than not, structures get "corrupted"
-    // or "eroded" by the way generation occurs. The earlier something appears in the generation stage,
-    // the more likely it is to not make it out 100% intact.
-    // So, if structures need to be "preserved" better, move it down, and the same principle holds for all
-    // features/filters/whatever
-    for _ in 0..*rand::thread_rng().choose(&[1, 1, 1, 1, 2, 3]).unwrap() {
-      Structure::new().apply(&mut grid);
+        Step::Stairs => self.add_stairs(&mut grid, rng),
+        Step::Traps => self.add_traps(&mut grid, rng),
+
+        Step::Stain => Stain::new().apply(&mut grid, rng)
+
+      }
     }
 
-    // Biome generation
+    // Spent 300 million years wondering why the map was all walls until I realized this CRUCIAL piece of code
+    // suddenly vanished.
+    // Don't delete.
+    self.grid = grid;
+
+    debugln!("dungeon", "done!");
 
-    // Mostly just a proof of concept. Biomes are generated by comparing noise maps to the grid then flipping biomes
+    // Return self
+    return self;
+
+  }
+
+  ///
+  /// Get a noise map of where a noise based step applies
+  ///
+  fn noise(&self, threshold: f32, rng: &mut StdRng) -> map::Grid<u8> {
+    Fussy::new(Dungeon::generate_grid(self.width, self.height, 0_u8), threshold, rng).build()
+  }
+
+  ///
+  /// Flip tiles to a biome and recolor them with the biome's palette
+  ///
+  /// Biomes are generated by comparing noise maps to the grid then flipping biomes
+  ///
+  fn add_biome(&self, grid: &mut map::Grid<Tile>, biome: tile::Biome, threshold: f32, rng: &mut StdRng) {
 
-    // Apply noise for Cave biome
-    let mut f1 = Fussy::new(Dungeon::generate_grid(self.width, self.height, 0_u8), 1.2);
-    let bin_grid1 = f1.build();
+    let bin_grid = self.noise(threshold, rng);
+    let palette = Palette::of(&biome);
 
-    debugln!("biome", "applying cave biomes...");
+    debugln!("biome", format!("applying {} biomes...", biome));
 
     // Iterate over the grid
     for x in 0..self.width {
       for y in 0..self.height {
         // Positions of 1 mean that the noise passes the threshold
-        if bin_grid1[x][y] == 1 {
+        if bin_grid[x][y] == 1 {
           // First flip the biome
-          grid[x][y].biome = tile::Biome::Cave;
+          grid[x][y].biome = biome.clone();
           // Then recolor based on tile type
           match grid[x][y].tiletype {
-            tile::Type::Wall(_) => {
-               grid[x][y].set_fg(RGB(67, 57, 57));
-               grid[x][y].set_bg(RGB(60, 50, 50));
+            // Secret doors have to keep passing for walls
+            tile::Type::Wall(_) | tile::Type::Door(tile::Door::Secret) => {
+              grid[x][y].set_fg(palette.wall_fg);
+              grid[x][y].set_bg(palette.wall_bg);
             },
-            tile::Type::Stair(_) | tile::Type::TallGrass => {
-              grid[x][y].set_bg(RGB(25, 20, 20));
+            tile::Type::Stair(_) | tile::Type::TallGrass | tile::Type::Door(_) => {
+              grid[x][y].set_bg(palette.floor_bg);
             },
             _ => {
-              grid[x][y].set_fg(RGB(32, 27, 27));
-              grid[x][y].set_bg(RGB(25, 20, 20));
+              grid[x][y].set_fg(palette.floor_fg);
+              grid[x][y].set_bg(palette.floor_bg);
             }
           }
         }
       }
     }
 
-    // Apply noise for Crypt biome
-    let mut f2 = Fussy::new(Dungeon::generate_grid(self.width, self.height, 0_u8), 1.2);
-    let bin_grid2 = f2.build();
-
-    debugln!("biome", "applying crypt biomes...");
+  }
 
-    for x in 0..self.width {
-      for y in 0..self.height {
-        if bin_grid2[x][y] == 1 {
-          grid[x][y].biome = tile::Biome::Crypt;
-          match grid[x][y].tiletype {
-            tile::Type::Wall(_) => {
-               grid[x][y].set_fg(RGB(57, 57, 57));
-               grid[x][y].set_bg(RGB(50, 50, 50));
-            },
-            tile::Type::Stair(_) | tile::Type::TallGrass => {
-              grid[x][y].set_bg(RGB(20, 20, 20));
-            }
-            _ => {
-              grid[x][y].set_fg(RGB(27, 27, 27));
-              grid[x][y].set_bg(RGB(20, 20, 20));
-            }
-          }
-        }
-      }
-    }
+  ///
+  /// Flood floors
+  ///
+  fn add_water(&self, grid: &mut map::Grid<Tile>, threshold: f32, rng: &mut StdRng) {
 
-    // Apply noise for Sunken biome
-    let mut f3 = Fussy::new(Dungeon::generate_grid(self.width, self.height, 0_u8), 1.4);
-    let bin_grid3 = f3.build();
+    let bin_grid = self.noise(threshold, rng);
 
-    debugln!("biome", "applying sunken biomes...");
+    debugln!("biome", "applying water...");
 
     for x in 0..self.width {
       for y in 0..self.height {
-        if bin_grid3[x][y] == 1 {
-          grid[x][y].biome = tile::Biome::Sunken;
+        if bin_grid[x][y] == 1 {
           match grid[x][y].tiletype {
-            tile::Type::Wall(_) => {
-              grid[x][y].set_fg(RGB(57, 57, 67));
-              grid[x][y].set_bg(RGB(50, 50, 60));
-            },
-            tile::Type::Stair(_) | tile::Type::TallGrass => {
-              grid[x][y].set_bg(RGB(20, 20, 25));
-            }
+            tile::Type::Wall(_) | tile::Type::Stair(_) | tile::Type::Door(_) => {},
             _ => {
-              grid[x][y].set_fg(RGB(27, 27, 32));
-              grid[x][y].set_bg(RGB(20, 20, 25));
+              let biome = grid[x][y].biome.clone();
+              grid[x][y] = TILES.tile("water", &biome);
             }
           }
         }
       }
     }
 
-    // Apply noise for water
-    let mut f4 = Fussy::new(Dungeon::generate_grid(self.width, self.height, 0_u8), 1.4);
-    let bin_grid4 = f4.build();
-
-    debugln!("biome", "applying water...");
+  }
 
-    for x in 0..self.width {
-      for y in 0..self.height {
-        if bin_grid4[x][y] == 1 {
-          match grid[x][y].tiletype {
-            tile::Type::Wall(_) | tile::Type::Stair(_) => {},
-            _ => {
-              grid[x][y].set_bg(RGB(25, 75, 80));
-              grid[x][y].tiletype = tile::Type::Water;
-            }
-          }
-        }
-      }
-    }
+  ///
+  /// Turn floors and walls into crystal
+  ///
+  fn add_crystal(&self, grid: &mut map::Grid<Tile>, threshold: f32, rng: &mut StdRng) {
 
-    // Apply noise for crystal biome
-    let mut f5 = Fussy::new(Dungeon::generate_grid(self.width, self.height, 0_u8), 1.67);
-    let bin_grid5 = f5.build();
+    let bin_grid = self.noise(threshold, rng);
 
     debugln!("biome", "applying crystal biomes...");
 
     for x in 0..self.width {
       for y in 0..self.height {
-        if bin_grid5[x][y] == 1 {
+        if bin_grid[x][y] == 1 {
           match grid[x][y].tiletype {
-            tile::Type::Floor(_) => {
-              grid[x][y] = Tile::new(
-                "Crystaline Floor", 
-                ' ', 
-                RGB(0, 0, 0), 
-                RGB(143, 101, 172), 
-                tile::Type::Floor(tile::Floor::Crystal)
-              );
-            },
-            tile::Type::Wall(_) => {
-              grid[x][y] = Tile::new(
-                "Crystaline Wall", 
-                ' ', 
-                RGB(0, 0, 0), 
-                RGB(176, 157, 204), 
-                tile::Type::Wall(tile::Wall::Crystal)
-              );
-            }
+            tile::Type::Floor(_) => grid[x][y] = TILES.tile("crystal_floor", &tile::Biome::Crystal),
+            tile::Type::Wall(_) => grid[x][y] = TILES.tile("crystal_wall", &tile::Biome::Crystal),
             _ => {}
           }
           grid[x][y].biome = tile::Biome::Crystal;
@@ -341,24 +303,27 @@ impl Dungeon {
       }
     }
 
-    // Apply noise for tall grass
-    let mut f6 = Fussy::new(Dungeon::generate_grid(self.width, self.height, 0_u8), 1.5);
-    let bin_grid6 = f6.build();
+  }
+
+  ///
+  /// Grow tall grass on floors
+  ///
+  fn add_tall_grass(&self, grid: &mut map::Grid<Tile>, threshold: f32, rng: &mut StdRng) {
+
+    let bin_grid = self.noise(threshold, rng);
 
     debugln!("biome", "applying tall grass...");
 
     for x in 0..self.width {
       for y in 0..self.height {
-        if bin_grid6[x][y] == 1 {
+        if bin_grid[x][y] == 1 {
           match grid[x][y].tiletype {
             tile::Type::Floor(_) => {
-              grid[x][y] = Tile::new(
-                "Tall Grass", 
-                '"', 
-                *rand::thread_rng().choose(&GRASS_COLORS).unwrap(), 
-                grid[x][y].get_bg(), 
-                tile::Type::TallGrass
-              );
+              // Grass keeps the ground it grows on, but comes in a few shades
+              let mut grass = TILES.tile("tall_grass", &grid[x][y].biome);
+              grass.set_fg(*rng.choose(&GRASS_COLORS).unwrap());
+              grass.set_bg(grid[x][y].get_bg());
+              grid[x][y] = grass;
             },
             _ => {}
           }
@@ -366,24 +331,28 @@ impl Dungeon {
       }
     }
 
-    // Apply noise for vines
-    let mut f7 = Fussy::new(Dungeon::generate_grid(self.width, self.height, 0_u8), 1.55);
-    let bin_grid7 = f7.build();
+  }
+
+  ///
+  /// Grow vines on floors
+  ///
+  fn add_vines(&self, grid: &mut map::Grid<Tile>, threshold: f32, rng: &mut StdRng) {
+
+    let bin_grid = self.noise(threshold, rng);
 
     debugln!("biome", "applying vines...");
 
     for x in 0..self.width {
       for y in 0..self.height {
-        if bin_grid7[x][y] == 1 {
+        if bin_grid[x][y] == 1 {
           match grid[x][y].tiletype {
             tile::Type::Floor(_) => {
-              grid[x][y] = Tile::new(
-                "Vine", 
-                *rand::thread_rng().choose(&VINE_GLYPHS).unwrap(), 
-                *rand::thread_rng().choose(&GRASS_COLORS).unwrap(), 
-                grid[x][y].get_bg(), 
-                tile::Type::Vine
-              );
+              // Same goes for vines, which also grow in every direction
+              let mut vine = TILES.tile("vine", &grid[x][y].biome);
+              vine.set_glyph(*rng.choose(&VINE_GLYPHS).unwrap());
+              vine.set_fg(*rng.choose(&GRASS_COLORS).unwrap());
+              vine.set_bg(grid[x][y].get_bg());
+              grid[x][y] = vine;
             },
             _ => {}
           }
@@ -391,30 +360,40 @@ impl Dungeon {
       }
     }
 
-    // Add pretty details last.
+  }
 
-    debugln!("details", "applying details...");
+  ///
+  /// Add some hard walls
+  ///
+  fn add_hard_walls(&self, grid: &mut map::Grid<Tile>, rng: &mut StdRng) {
+
+    debugln!("details", "applying hard walls...");
 
-    // Add some hard walls
     for x in 0..self.width {
       for y in 0..self.height {
         // Basically just select 30% of walls to be 'hard walls' which are no different from normal walls
         if grid[x][y].tiletype == tile::Type::Wall(tile::Wall::Normal) {
-          let mut rng = rand::thread_rng();
           let chance = rng.gen_range(1, 100);
           if chance > 70 {
-            grid[x][y].glyph = '#';
-            grid[x][y].tiletype = tile::Type::Wall(tile::Wall::Hard);
+            let biome = grid[x][y].biome.clone();
+            grid[x][y] = TILES.tile("hard_wall", &biome);
           }
         }
       }
     }
 
-    // Add floor features
+  }
+
+  ///
+  /// Add floor features
+  ///
+  fn add_floor_features(&self, grid: &mut map::Grid<Tile>, rng: &mut StdRng) {
+
+    debugln!("details", "applying floor features...");
+
     for x in 0..self.width {
       for y in 0..self.height {
         if grid[x][y].tiletype == tile::Type::Floor(tile::Floor::Normal) {
-          let mut rng = rand::thread_rng();
           let feature_chance = rng.gen_range(1, 100);
 
           // Create basic rock features
@@ -448,125 +427,57 @@ impl Dungeon {
       }
     }
 
-    // Add Stairs
+  }
+
+  ///
+  /// Add stairs, along with the entrances to branches that start from this floor
+  ///
+  fn add_stairs(&mut self, grid: &mut map::Grid<Tile>, rng: &mut StdRng) {
 
     // So I know that get_valid_location() should be deprecated since we started adding stairs
     // but we're just gonna have to live with it for now
 
     debugln!("stairs", "adding stairs...");
     
-    // Downstair location
-    let loc = Dungeon::get_valid_location(&grid);
-    self.add_tile(
-      &mut grid,
-      &mut Tile::new(
-        "Down Stair",
-        '>',
-        RGB(255, 255, 255),
-        RGB(0, 0, 0),
-        tile::Type::Stair(tile::Stair::DownStair(tile::DownStair::Normal))
-      ),
-      loc
-    );
+    // Downstair location, unless this is the bottom of the branch
+    if self.kind.bottom().map_or(true, |bottom| self.depth < bottom) {
+      let loc = Dungeon::get_valid_location(&grid, &Occupancy::default(), rng);
+      self.add_tile(grid, &mut TILES.tile("down_stair", &tile::Biome::Dungeon), loc);
+    }
 
     // Stair location
-    let loc = Dungeon::get_valid_location(&grid);
-    self.add_tile(
-      &mut grid,
-      &mut Tile::new(
-        "Up Stair",
-        '<',
-        RGB(255, 255, 255),
-        RGB(0, 0, 0),
-        tile::Type::Stair(tile::Stair::UpStair(tile::UpStair::Normal))
-      ),
-      loc
-    );
+    let loc = Dungeon::get_valid_location(&grid, &Occupancy::default(), rng);
+    self.add_tile(grid, &mut TILES.tile("up_stair", &tile::Biome::Dungeon), loc);
 
-    debugln!("trap", "setting traps...");
+    // Branch entrances only ever lead off the main dungeon
+    if self.kind != DungeonKind::Main {
+      return;
+    }
 
-    // Add a trap
-    let loc = Dungeon::get_valid_location(&grid);
-    self.add_tile(
-      &mut grid,
-      &mut Tile::new(
-        "Memory Loss Trap", 
-        '^', 
-        RGB(255, 255, 0), 
-        RGB(0, 0, 0), 
-        tile::Type::Trap(tile::Trap::MemoryLoss)
-      ),
-      loc
-    );
-
-    // Or two
-    let loc = Dungeon::get_valid_location(&grid);
-    self.add_tile(
-      &mut grid,
-      &mut Tile::new(
-        "Shaft", 
-        '^', 
-        RGB(200, 50, 20), 
-        RGB(0, 0, 0), 
-        tile::Type::Trap(tile::Trap::Shaft)
-      ),
-      loc
-    );
-
-    // Anotha one
-    let loc = Dungeon::get_valid_location(&grid);
-    self.add_tile(
-      &mut grid,
-      &mut Tile::new(
-        "Paint bomb", 
-        '^', 
-        RGB(50, 200, 20), 
-        RGB(0, 0, 0), 
-        tile::Type::Trap(tile::Trap::PaintBomb)
-      ),
-      loc
-    );
-
-    // And antoha one
-    let loc = Dungeon::get_valid_location(&grid);
-    self.add_tile(
-      &mut grid,
-      &mut Tile::new(
-        "Spike", 
-        '^', 
-        RGB(200, 200, 200), 
-        RGB(0, 0, 0), 
-        tile::Type::Trap(tile::Trap::Spike)
-      ),
-      loc
-    );
-
-    let loc = Dungeon::get_valid_location(&grid);
-    self.add_tile(
-      &mut grid,
-      &mut Tile::new(
-        "Teleport Trap", 
-        '^', 
-        RGB(50, 127, 200), 
-        RGB(0, 0, 0), 
-        tile::Type::Trap(tile::Trap::Teleport)
-      ),
-      loc
-    );
-
-    // Apply stain
-
-    Stain::new().apply(&mut grid);
+    for branch in BRANCHES.iter().filter(|branch| branch.entrance() == Some(self.depth)) {
+      debugln!("stairs", format!("adding entrance to {}...", branch.name()));
+      let loc = Dungeon::get_valid_location(&grid, &Occupancy::default(), rng);
+      // Entrances are down stairs in the color of the branch they lead to
+      let mut entrance = TILES.tile("down_stair", &tile::Biome::Dungeon);
+      entrance.set_name(branch.name());
+      entrance.set_fg(Palette::of(&branch.biome()).wall_bg);
+      entrance.tiletype = tile::Type::Stair(tile::Stair::DownStair(tile::DownStair::Branch(*branch)));
+      self.add_tile(grid, &mut entrance, loc);
+    }
 
-    // Spent 300 million years wondering why the map was all walls until I realized this CRUCIAL piece of code
-    // suddenly vanished.
-    // Don't delete.
-    self.grid = grid;
+  }
 
-    debugln!("dungeon", "done!");
+  ///
+  /// Set some traps
+  ///
+  fn add_traps(&mut self, grid: &mut map::Grid<Tile>, rng: &mut StdRng) {
 
-    // Return self
-    return self;
+    debugln!("trap", "setting traps...");
+
+    for id in ["memory_loss_trap", "shaft_trap", "paint_bomb_trap", "spike_trap", "teleport_trap"].iter() {
+      let loc = Dungeon::get_valid_location(&grid, &Occupancy::default(), rng);
+      self.add_tile(grid, &mut TILES.tile(id, &tile::Biome::Dungeon), loc);
+    }
 
   }
 
@@ -595,9 +506,9 @@ impl Dungeon {
   ///
   /// Add some blood
   /// 
-  pub fn add_blood(&mut self, pos: Pos) {
+  pub fn add_blood(&mut self, pos: Pos, rng: &mut StdRng) {
 
-    Stain::add_viscera(pos.x as usize, pos.y as usize, filter::stain::VISCERA_DIAMETER, &mut self.grid);
+    Stain::add_viscera(pos.x as usize, pos.y as usize, filter::stain::VISCERA_DIAMETER, &mut self.grid, rng);
 
   }
 
@@ -606,17 +517,18 @@ impl Dungeon {
   }
 
   ///
-  /// Get the player's starting location as a tuple
+  /// Get a random spawnable location that nothing is standing on
+  ///
+  /// While a floor is being built nothing stands anywhere yet, so an empty `Occupancy` is passed in
   ///
   /// NOTE: Should be deprecated and removed once stairs show up
   ///
-  pub fn get_valid_location(grid: &map::Grid<Tile>) -> Pos {
+  pub fn get_valid_location(grid: &map::Grid<Tile>, occupancy: &Occupancy, rng: &mut StdRng) -> Pos {
     loop {
-      let mut rng = rand::thread_rng();
       let x : usize = rng.gen_range(1, grid.width() - 2);
       let y : usize = rng.gen_range(1, grid.height() - 2);
 
-      if tile::spawnable(&grid[x][y]) {
+      if tile::spawnable(&grid[x][y]) && !occupancy.is_occupied(Pos::from_usize(x, y)) {
         return Pos::from_usize(x, y);
       }
 
@@ -626,11 +538,17 @@ impl Dungeon {
   ///
   /// Return a new `Dungeon` that consists of pure walls
   ///
-  pub fn new(map_dim: Pos) -> Self {
+  /// * `kind` - Branch the floor is in, which decides how it gets built
+  /// * `depth` - How deep the floor is, which decides what structures can show up
+  ///
+  pub fn new(map_dim: Pos, kind: DungeonKind, depth: usize) -> Self {
     Dungeon {
       width: map_dim.x as usize,
       height: map_dim.y as usize,
-      grid: map::Grid::new()
+      grid: map::Grid::new(),
+      kind,
+      depth,
+      spawns: vec![]
     }
   }
 
@@ -8,32 +8,103 @@
 // use std::fs::File;
 // use std::io::prelude::*;
 
+use std::cmp;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 extern crate rand;
-use self::rand::Rng;
+use self::rand::{Rng, SeedableRng, StdRng};
 
 use image;
 
 use core::tcod::map::{Map, FovAlgorithm};
 
-use core::creature::{ai, Actions, Creature, Stats};
-use core::item::{Item, ItemProperty, Money};
+use core::creature::{ai, faction, Actions, Character, Creature, Effect, FACTIONS, MONSTERS};
+use core::item::{self, Item, ItemProperty, Launcher, money_value};
 use core::log;
 use core::renderer::{Renderable, RGB};
-use core::time::Time;
+use core::save::StaticStr;
+use core::time::{self, Energy, Time};
 
 pub mod dungeon;
-use self::dungeon::{Dungeon, map::{self, Pos, tile, Tile}};
+use self::dungeon::{Dungeon, DungeonKind, Spawn, map::{self, fov, path, Occupancy, Occupant, Pos, tile, Tile, TILES}};
+
+pub mod combat;
+use self::combat::Hit;
+
+pub mod sanity;
+use self::sanity::{Madness, Phantom};
+
+mod world_tests;
 
 ///
 /// Configurations
 /// 
 
-/// FOV conf
+/// Player conf
+
+// Health the player starts with, before fortitude is added on
+const PLAYER_HP : isize = 30;
+
+// Health the player starts with for every point of fortitude
+const HP_PER_FORTITUDE : isize = 3;
+
+// How heavy the player is
+const PLAYER_WEIGHT : usize = 20;
+
+/// Spawning conf
+
+// How many monsters are spawned on a new floor
+const CREATURES_PER_FLOOR : usize = 6;
+
+// How many pieces of junk are scattered around a new floor
+const JUNK_PER_FLOOR : usize = 5;
+
+// One in this many monsters spawn carrying some junk
+const JUNK_CARRY_CHANCE : usize = 4;
+
+// One in this many floors has a sling lying around
+const SLING_CHANCE : usize = 3;
+
+// How many tiles the player can throw things
+const THROW_RANGE : usize = 8;
+
+// How many turns trap effects last
+const SPIKE_BLEED_TURNS : usize = 5;
+const TELEPORT_CONFUSE_TURNS : usize = 8;
+const PAINT_BLIND_TURNS : usize = 6;
+const SHAFT_SLOW_TURNS : usize = 10;
+
+// Health lost every turn spent bleeding
+const BLEED_DAMAGE : isize = 1;
+
+// Chance out of 100 for a confused creature to stumble off in a random direction
+const STUMBLE_CHANCE : isize = 50;
+
+// Incense or smoke at least this strong masks the scent of whatever is standing in it, for a few turns after
+const MASK_SCENT : u8 = 40;
+const MASK_TURNS : usize = 3;
+
+/// Sanity conf
+
+// How close corruption and fountains have to be to have an effect
+const SANITY_RADIUS : isize = 2;
 
-// FOV Radius
-const FOV_DIAMETER : i32 = 15;
+// Sanity is lost once every this many turns spent near corruption, in a crypt, or with something dreadful in sight
+const CORRUPTION_DRAIN_TURNS : usize = 2;
+const CRYPT_DRAIN_TURNS : usize = 4;
+const DREAD_DRAIN_TURNS : usize = 4;
+
+// Sanity regained every turn spent resting or next to a fountain
+const REST_SANITY : isize = 1;
+const FOUNTAIN_SANITY : isize = 2;
+
+// One in this many turns a mind that's hallucinating hears something that isn't there
+const WHISPER_CHANCE : usize = 30;
+
+// One in this many turns a mind that's lost conjures a phantom, up to a limit, somewhere this close
+const PHANTOM_CHANCE : usize = 10;
+const MAX_PHANTOMS : usize = 3;
+const PHANTOM_RADIUS : isize = 6;
 
 /// Scent conf
 
@@ -56,25 +127,85 @@ const SC_DIAM_UPPER : isize = ((SC_DIAM / 2) + 1);
 // Lower index for ranges
 const SC_DIAM_LOWER : isize = -(SC_DIAM / 2);
 
+/// Smell conf
+
+// Scents weaker than this are too faint to notice when sniffing
+const SNIFF_MIN : u8 = 10;
+
+// How many different scents a sniff picks out
+const SNIFF_REPORT : usize = 3;
+
+// Scents at least this strong are strong, and at least half as strong are lingering. Anything else is faint
+const STRONG_SCENT : u8 = 120;
+
+/// Door conf
+
+// Out of 100, the chance of forcing a locked door open
+const FORCE_LOCK_CHANCE : isize = 25;
+
+// Out of 100, the chance of noticing a secret door next to the player each turn, before perception is added on
+const SEARCH_CHANCE : isize = 10;
+
+// How loud opening or closing a door is
+const DOOR_SOUND : usize = 15;
+
+// Sounds get this many times quieter for every closed door they go through
+const DOOR_MUFFLE : usize = 4;
+
 ///
 /// Represent a floor in the dungeon
 ///
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Floor {
   pub dun: Dungeon,
   // Creatures need to be boxed because they hold a trait object, which has an undefined size.
   // Whenever you create a creature, just slap it into Box::new() and it works
   pub creatures: Vec<Creature>,
   // Items on the floor
-  pub items: Vec<Item>
+  pub items: Vec<Item>,
+  // Flow maps made for the floor so far. They are cheap to make again, so they aren't saved
+  #[serde(skip)]
+  pub paths: path::Paths,
+  // Who is standing where, rebuilt from the creatures whenever the floor is entered
+  #[serde(skip)]
+  pub occupancy: Occupancy
 }
 
 impl Floor {
   pub fn new(dun: Dungeon, creatures: Vec<Creature>) -> Self {
-    Floor {
+    let mut floor = Floor {
       dun,
       creatures,
-      items: vec![]
+      items: vec![],
+      paths: path::Paths::default(),
+      occupancy: Occupancy::default()
+    };
+    floor.reindex(None);
+    return floor;
+  }
+
+  ///
+  /// Rebuild the occupancy index from scratch, along with the player if they're on this floor
+  ///
+  /// Needed whenever a floor is loaded or entered, since the index isn't saved along with it
+  ///
+  pub fn reindex(&mut self, player: Option<Pos>) {
+    self.occupancy = Occupancy::new(self.dun.width, self.dun.height);
+    for (idx, creature) in self.creatures.iter().enumerate().filter(|&(_, creature)| creature.state != Actions::Die) {
+      self.occupancy.place(creature.actor.pos, Occupant::Creature(idx));
+    }
+    if let Some(pos) = player {
+      self.occupancy.place(pos, Occupant::Player);
+    }
+  }
+
+  ///
+  /// Put an item on the floor, adding it to a stack of like items on the same tile if there is one
+  ///
+  pub fn place_item(&mut self, item: Item) {
+    match self.items.iter_mut().find(|floor_item| floor_item.pos == item.pos && floor_item.stacks_with(&item)) {
+      Some(floor_item) => floor_item.quantity += item.quantity,
+      None => self.items.push(item)
     }
   }
 
@@ -82,115 +213,120 @@ impl Floor {
 
 pub struct World {
   pub player: Creature,
+  // Race and background the player was made with
+  pub character: Character,
   pub floor: Floor,
+  // Every floor that has been visited, from every branch
   pub floor_stack: Vec<Floor>,
   pub floor_num: usize,
+  // Branch of the dungeon the player is in
+  pub branch: DungeonKind,
+  // Ticks of time that have passed since the world was made
+  pub turn: usize,
+  // What killed the player, once something has
+  pub cause_of_death: Option<String>,
+  // Creatures that only the player can see, conjured up by a mind that's going
+  pub phantoms: Vec<Phantom>,
   // http://tomassedovic.github.io/tcod-rs/tcod/map/struct.Map.html
-  pub tcod_map: Map
+  pub tcod_map: Map,
+  // Seed the world was created from, kept around so it can be shown to the player and put in bug reports
+  pub seed: usize,
+  // The one and only RNG for the simulation. Everything that affects the state of the world must roll dice with
+  // this and never with `rand::thread_rng()`, otherwise the same seed will stop producing the same game
+  pub rng: StdRng
 }
 
 impl World {
 
   ///
-  /// Create a set of creatures for testing. 100% temporary
+  /// Populate a floor with monsters that live in its branch at its depth
   ///
-  fn create_test_creatures(g: &map::Grid<Tile>) -> Vec<Creature> {
-    
-    let mut creatures = vec![];
-
-    creatures.push(
-      Creature::new(
-        "ant",
-        'a',
-        Dungeon::get_valid_location(g),
-        RGB(150, 0, 0), RGB(0, 0, 0),
-        Stats::debug_new(
-          15,
-          tile::Scent::Insectoid
-        ),
-        ai::SimpleAI::new()
-      )
-    );
+  fn spawn_creatures(floor: &mut Floor, rng: &mut StdRng) {
 
-    creatures.push(
-      Creature::new(
-        "bee",
-        'b',
-        Dungeon::get_valid_location(g),
-        RGB(150, 150, 0), RGB(0, 0, 0),
-        Stats::debug_new(
-          15,
-          tile::Scent::Insectoid
-        ),
-        ai::SimpleAI::new()
-      )
-    );
+    let (kind, depth) = (floor.dun.kind, floor.dun.depth);
 
-    creatures.push(
-      Creature::new(
-        "cat",
-        'c',
-        Dungeon::get_valid_location(g),
-        RGB(150, 0, 150), RGB(0, 0, 0),
-        Stats::debug_new(
-          5,
-          tile::Scent::Feline
-        ),
-        ai::TrackerAI::new()
-      )
-    );
+    for _ in 0..CREATURES_PER_FLOOR {
+      if let Some(monster) = kind.choose_monster(depth, rng) {
+        let pos = Dungeon::get_valid_location(&floor.dun.grid, &floor.occupancy, rng);
+        let mut creature = monster.create(pos);
+        if rng.gen_range(0, JUNK_CARRY_CHANCE) == 0 {
+          creature.stash(World::new_junk(pos, rng));
+        }
+        floor.occupancy.place(pos, Occupant::Creature(floor.creatures.len()));
+        floor.creatures.push(creature);
+      }
+    }
 
-    creatures.push(
-      Creature::new(
-        "blink hound",
-        'd',
-        Dungeon::get_valid_location(g),
-        RGB(150, 150, 150), RGB(0, 0, 0),
-        Stats::debug_new(
-          20,
-          tile::Scent::Canine
-        ),
-        ai::BlinkAI::new()
-      )
-    );
+  }
 
-    creatures.push(
-      Creature::new(
-        "Kurt",
-        '@',
-        Dungeon::get_valid_location(g),
-        RGB(200, 200, 200), RGB(0, 0, 0),
-        Stats::debug_new(
-          50,
-          tile::Scent::Canine
-        ),
-        ai::TalkerAI::new()
-      )
-    );
+  ///
+  /// Return a random piece of junk
+  ///
+  fn new_junk(pos: Pos, rng: &mut StdRng) -> Item {
+    let id = *rng.choose(&["rock", "bone", "incense"]).unwrap();
+    item::from_id(id, pos, rng).unwrap()
+  }
 
-    creatures.push(
-      Creature::new(
-        "Echidna",
-        'e',
-        Dungeon::get_valid_location(g),
-        RGB(50, 50, 200), RGB(0, 0, 0),
-        Stats::debug_new(
-          15,
-          tile::Scent::Canine
-        ),
-        ai::SmellerAI::new()
-      )
-    );
+  ///
+  /// Scatter items around a floor
+  ///
+  fn spawn_items(g: &map::Grid<Tile>, rng: &mut StdRng) -> Vec<Item> {
 
-    return creatures;
+    let mut items = vec![];
+
+    // Create n gold coins at a valid location
+    let gold_loc = Dungeon::get_valid_location(g, &Occupancy::default(), rng);
+    items.push(item::from_id("gold", gold_loc, rng).unwrap());
+
+    for _ in 0..JUNK_PER_FLOOR {
+      let pos = Dungeon::get_valid_location(g, &Occupancy::default(), rng);
+      items.push(World::new_junk(pos, rng));
+    }
 
+    // Something to throw all of those rocks with
+    if rng.gen_range(0, SLING_CHANCE) == 0 {
+      let pos = Dungeon::get_valid_location(g, &Occupancy::default(), rng);
+      items.push(item::from_id("sling", pos, rng).unwrap());
+    }
+
+    return items;
+
+  }
+
+  ///
+  /// Spawn the monsters and items that the floor's structures asked for
+  ///
+  /// Unknown ids are skipped, since structure files are checked against the registries when they are read.
+  /// So are monsters that would end up on top of another one
+  ///
+  fn spawn_structure_requests(floor: &mut Floor, rng: &mut StdRng) {
+    let spawns : Vec<(Pos, Spawn)> = floor.dun.spawns.drain(..).collect();
+    for (pos, spawn) in spawns {
+      match spawn {
+        Spawn::Monster(id) => if let Some(monster) = MONSTERS.get(&id) {
+          if !floor.occupancy.is_occupied(pos) {
+            floor.occupancy.place(pos, Occupant::Creature(floor.creatures.len()));
+            floor.creatures.push(monster.create(pos));
+          }
+        },
+        Spawn::Item(id) => if let Some(item) = item::from_id(&id, pos, rng) {
+          floor.place_item(item);
+        }
+      }
+    }
   }
 
   ///
-  /// Create a basic dungeon for testing
+  /// Build a floor of a branch along with everything on it
   ///
-  fn create_test_dungeon(map_dim: Pos) -> Dungeon {
-    Dungeon::new(map_dim).build()
+  fn create_floor(map_dim: Pos, kind: DungeonKind, depth: usize, rng: &mut StdRng) -> Floor {
+    let dun = Dungeon::new(map_dim, kind, depth).build(rng);
+    let mut floor = Floor::new(dun, vec![]);
+    floor.items = World::spawn_items(&floor.dun.grid, rng);
+    // Structures get first pick of where their monsters stand
+    World::spawn_structure_requests(&mut floor, rng);
+    World::spawn_creatures(&mut floor, rng);
+    return floor;
   }
 
   ///
@@ -226,21 +362,25 @@ impl World {
   } 
 
   ///
-  /// Return a new player `Creature`
+  /// Return a new player `Creature` made from a `Character`
   ///
   #[inline]
-  fn new_player() -> Creature {
-    Creature::new(
+  fn new_player(character: &Character) -> Creature {
+    let mut player = Creature::new(
       "Player",
       '@',
       Pos::new(40, 25),
       RGB(255, 255, 255), RGB(0, 0, 0),
-      Stats::debug_new(
-        20,
-        tile::Scent::Player
-      ),
+      character.stats(PLAYER_WEIGHT),
       ai::PlayerAI::new()
-    )
+    );
+    player.stats.health_points = PLAYER_HP + player.stats.fortitude * HP_PER_FORTITUDE;
+    // The player starts out of their right mind
+    player.stats.sanity_points = player.stats.max_sanity();
+    // The player gets the first move
+    player.energy = Energy(time::TURN);
+    player.faction = StaticStr(faction::PLAYER_FACTION);
+    return player;
   }
 
 
@@ -263,6 +403,8 @@ impl World {
 
     self.player.actor.pos.x = (self.floor.dun.width / 2) as isize;
     self.player.actor.pos.y = (self.floor.dun.height / 2) as isize;
+
+    self.floor.reindex(Some(self.player.actor.pos));
     
   }
 
@@ -279,11 +421,479 @@ impl World {
 
   }
 
+  ///
+  /// Get the state of the door at `pos`, if there is a door there
+  ///
+  /// Anywhere off the floor has no door
+  ///
+  pub fn door_at(&self, pos: Pos) -> Option<tile::Door> {
+    if pos.x < 0 || pos.y < 0 || pos.x as usize >= self.floor.dun.width || pos.y as usize >= self.floor.dun.height {
+      return None;
+    }
+    match self.floor.dun[pos].tiletype {
+      tile::Type::Door(ref door) => Some(door.clone()),
+      _ => None
+    }
+  }
+
+  ///
+  /// Get every spot next to `pos` with a door in one of the given states
+  ///
+  pub fn doors_around(&self, pos: Pos, states: &[tile::Door]) -> Vec<Pos> {
+    let mut doors = vec![];
+    for dx in -1..2 {
+      for dy in -1..2 {
+        let next = Pos::new(pos.x + dx, pos.y + dy);
+        if next != pos && self.door_at(next).map_or(false, |door| states.contains(&door)) {
+          doors.push(next);
+        }
+      }
+    }
+    return doors;
+  }
+
+  ///
+  /// Turn the door at `pos` into another door by tile id
+  ///
+  /// The FOV map changes along with the tile, so a door that opens can be seen through straight away. Flow maps were
+  /// made with the door the way it was, so they're thrown out
+  ///
+  fn set_door(&mut self, pos: Pos, id: &str) {
+    TILES.change(id, &mut self.floor.dun[pos]);
+    let t = &self.floor.dun[pos];
+    self.tcod_map.set(pos.x as i32, pos.y as i32, !tile::opaque(t), tile::walkable(t));
+    self.floor.paths.clear();
+  }
+
+  ///
+  /// Have the player open the door at `pos`
+  ///
+  /// Locked doors only give way some of the time, but trying still takes a turn. Returns `false` if no time was spent
+  ///
+  pub fn player_open(&mut self, pos: Pos) -> bool {
+
+    match self.door_at(pos) {
+      Some(tile::Door::Closed) => {
+        log!("You open the door", RGB(200, 200, 200));
+        self.set_door(pos, "open_door");
+      },
+      Some(tile::Door::Locked) => {
+        if self.rng.gen_range(0, 100) < FORCE_LOCK_CHANCE {
+          log!("You force the door open!", RGB(255, 255, 200));
+          self.set_door(pos, "open_door");
+        } else {
+          log!("The door is locked, and it holds", RGB(150, 150, 150));
+        }
+      },
+      Some(tile::Door::Open) => {
+        log!("That door is already open", RGB(150, 150, 150));
+        return false;
+      },
+      // Secret doors are just walls until they're found
+      _ => {
+        log!("There's nothing there to open", RGB(150, 150, 150));
+        return false;
+      }
+    }
+
+    return true;
+
+  }
+
+  ///
+  /// Have the player close the door at `pos`
+  ///
+  /// Doors with something standing or lying in them won't close. Returns `false` if no time was spent
+  ///
+  pub fn player_close(&mut self, pos: Pos) -> bool {
+
+    match self.door_at(pos) {
+      Some(tile::Door::Open) => {
+        if self.floor.occupancy.is_occupied(pos) || self.floor.items.iter().any(|item| item.pos == pos) {
+          log!("Something is in the way", RGB(150, 150, 150));
+          return false;
+        }
+        log!("You close the door", RGB(200, 200, 200));
+        self.set_door(pos, "closed_door");
+      },
+      Some(tile::Door::Closed) | Some(tile::Door::Locked) => {
+        log!("That door is already closed", RGB(150, 150, 150));
+        return false;
+      },
+      _ => {
+        log!("There's nothing there to close", RGB(150, 150, 150));
+        return false;
+      }
+    }
+
+    return true;
+
+  }
+
+  ///
+  /// Give the player a chance to notice any secret doors next to them
+  ///
+  fn search_secret_doors(&mut self) {
+    let chance = SEARCH_CHANCE + self.player.stats.perception;
+    for pos in self.doors_around(self.player.actor.pos, &[tile::Door::Secret]) {
+      if self.rng.gen_range(0, 100) < chance {
+        log!("You find a secret door!", RGB(255, 255, 200));
+        self.set_door(pos, "closed_door");
+      }
+    }
+  }
+
+  ///
+  /// Get which way `to` is from `from`, as in "northeast"
+  ///
+  fn compass(from: Pos, to: Pos) -> &'static str {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    // Only count a direction if it's not too far off from the other one
+    let x = if dx.abs() * 2 < dy.abs() { 0 } else { dx.signum() };
+    let y = if dy.abs() * 2 < dx.abs() { 0 } else { dy.signum() };
+    match (x, y) {
+      (0, -1)  => "north",
+      (0, 1)   => "south",
+      (1, 0)   => "east",
+      (-1, 0)  => "west",
+      (1, -1)  => "northeast",
+      (-1, -1) => "northwest",
+      (1, 1)   => "southeast",
+      (-1, 1)  => "southwest",
+      _        => "here"
+    }
+  }
+
+  ///
+  /// Have the player sniff the air, logging the strongest scents within smelling distance that aren't their own
+  ///
+  /// Each scent is only reported once, from wherever it's strongest
+  ///
+  pub fn player_sniff(&self) {
+
+    let pos = self.player.actor.pos;
+    let radius = self.player.stats.smell_radius() as isize;
+    let own = &self.player.stats.scent_type;
+
+    let mut strongest : Vec<(u8, tile::Scent, Pos)> = vec![];
+
+    for x in (pos.x - radius)..(pos.x + radius + 1) {
+      for y in (pos.y - radius)..(pos.y + radius + 1) {
+
+        let at = Pos::new(x, y);
+        if x < 0 || y < 0 || x as usize >= self.floor.dun.width || y as usize >= self.floor.dun.height || (at ^ pos) > radius as f32 {
+          continue;
+        }
+
+        for scent in self.floor.dun[at].scents.iter().filter(|scent| scent.scent_type != *own && scent.val >= SNIFF_MIN) {
+          match strongest.iter_mut().find(|entry| entry.1 == scent.scent_type) {
+            Some(entry) => if scent.val > entry.0 {
+              *entry = (scent.val, scent.scent_type.clone(), at);
+            },
+            None => strongest.push((scent.val, scent.scent_type.clone(), at))
+          }
+        }
+
+      }
+    }
+
+    if strongest.is_empty() {
+      log!("You smell nothing out of the ordinary", RGB(150, 150, 150));
+      return;
+    }
+
+    strongest.sort_by(|a, b| b.0.cmp(&a.0));
+
+    for &(val, ref scent, at) in strongest.iter().take(SNIFF_REPORT) {
+      let strength = if val >= STRONG_SCENT {
+        "strong"
+      } else if val >= STRONG_SCENT / 2 {
+        "lingering"
+      } else {
+        "faint"
+      };
+      let place = if at == pos { "right here".to_string() } else { format!("to the {}", World::compass(pos, at)) };
+      let name = scent.to_string().to_lowercase();
+      log!(format!("A {} {} scent {}", strength, name, place), scent.color());
+    }
+
+  }
+
+  ///
+  /// Check if the player and a creature on the floor are at odds, which is when either one is hostile to the other
+  ///
+  fn at_odds_with_player(&self, idx: usize) -> bool {
+    let (player, other) = (self.player.faction.0, self.floor.creatures[idx].faction.0);
+    FACTIONS.is_hostile(player, other) || FACTIONS.is_hostile(other, player)
+  }
+
+  ///
+  /// Have the player attack whatever creature is standing at `pos`, as long as they're at odds with it
+  ///
+  /// Returns `false` if there was nothing there to attack
+  ///
+  pub fn player_attack(&mut self, pos: Pos) -> bool {
+
+    let defender = match self.floor.occupancy.get(pos) {
+      Some(Occupant::Creature(idx)) if self.at_odds_with_player(idx) => &mut self.floor.creatures[idx],
+      _ => return false
+    };
+
+    let name = defender.actor.get_id();
+
+    match combat::melee(&self.player, defender, &mut self.rng) {
+      Hit::Miss => log!(format!("You miss the {}", name), RGB(150, 150, 150)),
+      Hit::Hit(_) => log!(format!("You hit the {}", name), RGB(255, 255, 255)),
+      Hit::Kill(_) => {
+        log!(format!("You kill the {}!", name), RGB(255, 200, 0));
+        self.floor.dun.add_blood(pos, &mut self.rng);
+        self.floor.occupancy.remove(pos);
+      }
+    }
+
+    return true;
+
+  }
+
+  ///
+  /// Have the player, who just moved from `from` to `pos`, trade places with a peaceful creature standing at `pos`
+  ///
+  /// Returns `false` if there was nothing there to trade places with
+  ///
+  pub fn player_swap(&mut self, from: Pos, pos: Pos) -> bool {
+
+    let idx = match self.floor.occupancy.get(pos) {
+      Some(Occupant::Creature(idx)) if !self.at_odds_with_player(idx) => idx,
+      _ => return false
+    };
+
+    // Noclipping players can come from places creatures can't go
+    if !self.is_valid_pos(from.x, from.y) {
+      return false;
+    }
+
+    let other = &mut self.floor.creatures[idx];
+    other.actor.prev_pos = pos;
+    other.actor.pos = from;
+    log!(format!("You swap places with the {}", other.actor.get_id()), RGB(200, 200, 200));
+
+    self.floor.occupancy.place(from, Occupant::Creature(idx));
+    self.floor.occupancy.place(pos, Occupant::Player);
+
+    return true;
+
+  }
+
   ///
   /// Check for dead creatures
   /// 
   pub fn check_death(&mut self) {
-    self.floor.creatures.retain( |creature| creature.state != Actions::Die )
+
+    // Dead creatures drop whatever they were carrying
+    let mut dropped = vec![];
+    for creature in self.floor.creatures.iter_mut().filter(|creature| creature.state == Actions::Die) {
+      for mut item in creature.inventory.drain(..) {
+        item.pos = creature.actor.pos;
+        dropped.push(item);
+      }
+    }
+    for item in dropped {
+      self.floor.place_item(item);
+    }
+
+    // Dead creatures give up their spots, unless something else has already taken them
+    for (idx, creature) in self.floor.creatures.iter().enumerate().filter(|&(_, creature)| creature.state == Actions::Die) {
+      if self.floor.occupancy.get(creature.actor.pos) == Some(Occupant::Creature(idx)) {
+        self.floor.occupancy.remove(creature.actor.pos);
+      }
+    }
+
+    let before = self.floor.creatures.len();
+    self.floor.creatures.retain( |creature| creature.state != Actions::Die );
+
+    // Creatures after the dead ones have moved down in the list
+    if self.floor.creatures.len() != before {
+      for (idx, creature) in self.floor.creatures.iter().enumerate() {
+        self.floor.occupancy.place(creature.actor.pos, Occupant::Creature(idx));
+      }
+    }
+
+  }
+
+  ///
+  /// Pick up everything under the player
+  ///
+  /// Money goes straight into the wallet, everything else goes into the inventory if it isn't too heavy.
+  /// Returns `false` if nothing was picked up
+  ///
+  pub fn player_pickup(&mut self) -> bool {
+
+    let player_pos = self.player.actor.pos;
+    let mut picked_up = false;
+    let mut left = vec![];
+
+    for item in self.floor.items.drain(..) {
+
+      if item.pos != player_pos {
+        left.push(item);
+        continue;
+      }
+
+      // Money weighs nothing, so this only ever stops other items
+      if !self.player.can_carry(&item) {
+        log!(format!("You can't carry {}, it's too heavy", item.describe()), RGB(150, 150, 150));
+        left.push(item);
+        continue;
+      }
+
+      if !self.player.has_room(&item) {
+        log!(format!("You have no room for {}", item.describe()), RGB(150, 150, 150));
+        left.push(item);
+        continue;
+      }
+
+      log!(format!("You pick up {}", item.describe()), item.get_fg());
+      picked_up = true;
+
+      match item.property {
+        ItemProperty::Money(ref tender) => self.player.wallet += money_value(tender) * item.quantity as f32,
+        _ => self.player.stash(item)
+      }
+
+    }
+
+    self.floor.items = left;
+
+    return picked_up;
+
+  }
+
+  ///
+  /// Drop an item from the player's inventory by index
+  ///
+  /// Returns `false` if there is no such item
+  ///
+  pub fn player_drop(&mut self, idx: usize) -> bool {
+
+    if idx >= self.player.inventory.len() {
+      return false;
+    }
+
+    let mut item = self.player.inventory.remove(idx);
+    item.pos = self.player.actor.pos;
+    log!(format!("You drop {}", item.describe()), item.get_fg());
+    self.floor.place_item(item);
+
+    return true;
+
+  }
+
+  ///
+  /// Have the player throw one item off of a stack in their inventory at `target`
+  ///
+  /// Returns `false` if there is no such item
+  ///
+  pub fn player_throw(&mut self, idx: usize, target: Pos) -> bool {
+
+    if idx >= self.player.inventory.len() {
+      return false;
+    }
+
+    let projectile = self.player_take_one(idx);
+    log!(format!("You throw {}", projectile.describe()), projectile.get_fg());
+
+    let damage = projectile.thrown_damage();
+    self.player_shoot(projectile, target, THROW_RANGE, damage);
+
+    return true;
+
+  }
+
+  ///
+  /// Get the first launcher the player is carrying that they have ammo for, along with where that ammo is in their
+  /// inventory
+  ///
+  pub fn player_ammo(&self) -> Option<(Launcher, usize)> {
+    self.player.inventory.iter()
+      .filter_map(|item| match item.property {
+        ItemProperty::Launcher(ref launcher) => Some(launcher),
+        _ => None
+      })
+      .filter_map(|launcher| {
+        self.player.inventory.iter().position(|item| item.is_ammo_for(launcher)).map(|idx| (launcher.clone(), idx))
+      })
+      .next()
+  }
+
+  ///
+  /// Have the player fire a launcher at `target`
+  ///
+  /// Returns `false` if they have nothing to fire
+  ///
+  pub fn player_fire(&mut self, target: Pos) -> bool {
+
+    let (launcher, idx) = match self.player_ammo() {
+      Some(found) => found,
+      None => return false
+    };
+
+    let projectile = self.player_take_one(idx);
+    log!(format!("You fire {}", projectile.describe()), projectile.get_fg());
+
+    self.player_shoot(projectile, target, launcher.range, launcher.damage);
+
+    return true;
+
+  }
+
+  ///
+  /// Take a single item off of a stack in the player's inventory, getting rid of the stack if it was the last one
+  ///
+  fn player_take_one(&mut self, idx: usize) -> Item {
+    if self.player.inventory[idx].quantity > 1 {
+      self.player.inventory[idx].quantity -= 1;
+      let mut item = self.player.inventory[idx].clone();
+      item.quantity = 1;
+      return item;
+    }
+    return self.player.inventory.remove(idx);
+  }
+
+  ///
+  /// Send a projectile flying from the player towards `target`, no further than `range` tiles
+  ///
+  /// If it reaches a creature it may hit it for up to `damage`. Whether it hits or misses, it falls to the floor where
+  /// it stopped
+  ///
+  fn player_shoot(&mut self, mut projectile: Item, target: Pos, range: usize, damage: isize) {
+
+    let start = self.player.actor.pos;
+    let path = combat::trace(&self.floor.dun.grid, &self.floor.occupancy, start, target, range);
+
+    // Something that can't leave the player's tile falls at their feet
+    let land = path.last().cloned().unwrap_or(start);
+    let name = projectile.get_id();
+
+    if let Some(Occupant::Creature(idx)) = self.floor.occupancy.get(land) {
+
+      let defender = &mut self.floor.creatures[idx];
+      let defender_name = defender.actor.get_id();
+
+      match combat::ranged(&self.player, defender, damage, path.len() as isize, &mut self.rng) {
+        Hit::Miss => log!(format!("The {} misses the {}", name, defender_name), RGB(150, 150, 150)),
+        Hit::Hit(_) => log!(format!("The {} hits the {}", name, defender_name), RGB(255, 255, 255)),
+        Hit::Kill(_) => {
+          log!(format!("The {} kills the {}!", name, defender_name), RGB(255, 200, 0));
+          self.floor.dun.add_blood(land, &mut self.rng);
+          self.floor.occupancy.remove(land);
+        }
+      }
+
+    }
+
+    projectile.pos = land;
+    self.floor.place_item(projectile);
+
   }
 
   ///
@@ -293,7 +903,7 @@ impl World {
 
     // Don't repeat if we already know what's under foot
     match self.player.state {
-      Actions::Wait | Actions::Die | Actions::Unknown | Actions::UpStair | Actions::DownStair => return,
+      Actions::Wait | Actions::Die | Actions::Unknown | Actions::UpStair | Actions::DownStair | Actions::Throw => return,
       _ => ()
     }
 
@@ -319,9 +929,9 @@ impl World {
 
     for item in items_at_feet {
       if item.quantity > 1 {
-        log!(Box::leak(format!("You see {} {}s here", item.quantity, item.get_id()).into_boxed_str()), item.get_fg());
+        log!(format!("You see {} {}s here", item.quantity, item.get_id()), item.get_fg());
       } else {
-        log!(Box::leak(format!("You see a {} here", item.get_id()).into_boxed_str()), item.get_fg());
+        log!(format!("You see a {} here", item.get_id()), item.get_fg());
       }
     }
 
@@ -361,18 +971,22 @@ impl World {
 
               log!("You fall down a shaft!", RGB(200, 50, 20));
               
-              for _floors in 0..rand::thread_rng().gen_range(1, 4) {
-                self.go_down();
+              // Branches that end don't go any further than their bottom floor
+              for _floors in 0..self.rng.gen_range(1, 4) {
+                if !self.go_down() {
+                  break;
+                }
               }
 
+              log!("You hurt your leg in the fall", RGB(100, 150, 255));
+              self.player.statuses.apply(Effect::Slowed, SHAFT_SLOW_TURNS);
+
             },
 
             // Turn creature a new color
             tile::Trap::PaintBomb => {
 
-              let mut rng = rand::thread_rng();
-
-              let col = RGB(rng.gen_range(1, 255), rng.gen_range(1, 255), rng.gen_range(1, 255));
+              let col = RGB(self.rng.gen_range(1, 255), self.rng.gen_range(1, 255), self.rng.gen_range(1, 255));
 
               log!("It's a paint bomb!", RGB(100, 100, 100));
 
@@ -380,6 +994,9 @@ impl World {
 
               self.player.actor.set_fg(col);
 
+              log!("Paint gets in your eyes!", RGB(150, 150, 150));
+              self.player.statuses.apply(Effect::Blind, PAINT_BLIND_TURNS);
+
             },
 
             // Move randomly on map
@@ -387,7 +1004,12 @@ impl World {
 
               log!("It's a teleporter!", RGB(50, 127, 200));
 
-              self.player.actor.pos = Dungeon::get_valid_location(&self.floor.dun.grid);
+              let pos = Dungeon::get_valid_location(&self.floor.dun.grid, &self.floor.occupancy, &mut self.rng);
+              self.floor.occupancy.move_occupant(self.player.actor.pos, pos);
+              self.player.actor.pos = pos;
+
+              log!("You feel disoriented", RGB(200, 100, 255));
+              self.player.statuses.apply(Effect::Confused, TELEPORT_CONFUSE_TURNS);
 
             },
 
@@ -396,7 +1018,8 @@ impl World {
 
               log!("You impale yourself on a spike!", RGB(200, 200, 200));
 
-              self.floor.dun.add_blood(self.player.actor.pos);
+              self.floor.dun.add_blood(self.player.actor.pos, &mut self.rng);
+              self.player.statuses.apply(Effect::Bleeding, SPIKE_BLEED_TURNS);
 
             }
 
@@ -433,13 +1056,12 @@ impl World {
               // Turn creature a new color
               tile::Trap::PaintBomb => {
 
-                let mut rng = rand::thread_rng();
-
-                let col = RGB(rng.gen_range(1, 255), rng.gen_range(1, 255), rng.gen_range(1, 255));
+                let col = RGB(self.rng.gen_range(1, 255), self.rng.gen_range(1, 255), self.rng.gen_range(1, 255));
 
                 log!("You hear an explosion!", RGB(100, 100, 100));
 
                 creature.actor.set_fg(col);
+                creature.statuses.apply(Effect::Blind, PAINT_BLIND_TURNS);
 
               }
 
@@ -448,20 +1070,27 @@ impl World {
 
                 log!("You hear the hum of a teleporter!", RGB(50, 127, 200));
 
-                creature.actor.pos = Dungeon::get_valid_location(&self.floor.dun.grid);
+                let pos = Dungeon::get_valid_location(&self.floor.dun.grid, &self.floor.occupancy, &mut self.rng);
+                self.floor.occupancy.move_occupant(creature.actor.pos, pos);
+                creature.actor.pos = pos;
+                creature.statuses.apply(Effect::Confused, TELEPORT_CONFUSE_TURNS);
 
               }
 
               // Bleed
               tile::Trap::Spike => {
 
-                self.floor.dun.add_blood(creature.actor.pos);
+                self.floor.dun.add_blood(creature.actor.pos, &mut self.rng);
+                creature.statuses.apply(Effect::Bleeding, SPIKE_BLEED_TURNS);
 
               }
             }
           }
           _ => ()
         }
+
+        // Slow creatures can go a few updates without acting, and shouldn't spring the same trap again in each one
+        creature.actor.prev_pos = creature.actor.pos;
       }
       
     }
@@ -482,27 +1111,34 @@ impl World {
   ///
   /// Go downstairs if possible
   ///
-  pub fn go_down(&mut self) {
-
-    if self.floor_num <= self.floor_stack.len() {
-      self.floor_stack[self.floor_num] = self.floor.clone();
+  /// Returns `false` without going anywhere if the player is already on the bottom floor of their branch
+  ///
+  pub fn go_down(&mut self) -> bool {
+    if self.branch.bottom() == Some(self.floor_num) {
+      return false;
     }
-    self.floor_num += 1;
-    self.test_traverse();
-
+    let (branch, depth) = (self.branch, self.floor_num + 1);
+    self.travel(branch, depth);
+    return true;
   }
 
   ///
   /// Save the current floor and go up one floor
   ///
+  /// Going up from the first floor of a branch leads back to the floor of the main dungeon holding its entrance
+  ///
   pub fn go_up(&mut self) {
     
     // Be sure we aren't going to mess something up
     assert!(self.floor_num != 0);
 
-    self.floor_stack[self.floor_num] = self.floor.clone();
-    self.floor_num -= 1;
-    self.test_traverse();
+    let depth = self.floor_num - 1;
+    if self.branch.entrance() == Some(depth) {
+      self.travel(DungeonKind::Main, depth);
+    } else {
+      let branch = self.branch;
+      self.travel(branch, depth);
+    }
 
   }
   
@@ -529,7 +1165,12 @@ impl World {
   /// See if the player is able to go down on the current tile and draw some stuff to the log
   /// 
   pub fn player_go_down(&mut self) {
-    match self.get_tile_at(self.player.actor.pos.x, self.player.actor.pos.y).tiletype {
+    match self.get_tile_at(self.player.actor.pos.x, self.player.actor.pos.y).tiletype.clone() {
+      tile::Type::Stair(tile::Stair::DownStair(tile::DownStair::Branch(branch))) => {
+        let depth = self.floor_num + 1;
+        self.travel(branch, depth);
+        log!(format!("You enter the {}...", branch.name()), RGB(255, 255, 200));
+      },
       tile::Type::Stair(tile::Stair::DownStair(_)) => {
         self.go_down();
         log!("You bravely venture forth...", RGB(255, 255, 200));
@@ -539,38 +1180,46 @@ impl World {
   }
 
   ///
-  /// Temporary function for stair traversal
+  /// Save the current floor and move the player to a floor of some branch, building it if it's never been visited
   ///
-  pub fn test_traverse(&mut self) {
-    
-    // Create floor var
-    let mut floor;
-
-    // If the floor number that we are on is not a floor in the stack,
-    // we need to add a new floor to the stack
-    if self.floor_num > self.floor_stack.len() - 1 {
-      let dun = World::create_test_dungeon(self.floor.dun.get_bounds_pos());
-      let grid = dun.grid.clone();
-      let creatures = World::create_test_creatures(&grid);
-      floor = Floor::new(dun, creatures);
-      // Create n gold coins at a valid location
-      let gold_loc = Dungeon::get_valid_location(&floor.dun.grid);
-      floor.items.push(
-        Item::new("gold piece", '$', gold_loc, RGB(238, 232, 170), RGB(0, 0, 0), rand::thread_rng().gen_range(10, 40), ItemProperty::Money(Money::Gold))
-      );
-      self.floor_stack.push(floor.clone());
-    // Otherwise the floor already exists in the stack and can be brought out
-    } else {
-      floor = self.floor_stack[self.floor_num].clone();
+  /// Floors below the bottom of a branch don't exist, so asking for one leads to the bottom floor instead
+  ///
+  pub fn travel(&mut self, branch: DungeonKind, depth: usize) {
+
+    let depth = branch.bottom().map_or(depth, |bottom| cmp::min(depth, bottom));
+
+    // Put the current floor back in the stack, so that it's the same when we return
+    let (cur_branch, cur_depth) = (self.branch, self.floor_num);
+    match self.floor_stack.iter().position(|floor| floor.dun.kind == cur_branch && floor.dun.depth == cur_depth) {
+      Some(idx) => self.floor_stack[idx] = self.floor.clone(),
+      None => self.floor_stack.push(self.floor.clone())
     }
 
+    // Bring out the floor if it already exists, otherwise it needs to be made and added to the stack
+    let floor = match self.floor_stack.iter().position(|floor| floor.dun.kind == branch && floor.dun.depth == depth) {
+      Some(idx) => self.floor_stack[idx].clone(),
+      None => {
+        let floor = World::create_floor(self.floor.dun.get_bounds_pos(), branch, depth, &mut self.rng);
+        self.floor_stack.push(floor.clone());
+        floor
+      }
+    };
+
     self.floor = floor;
+    self.branch = branch;
+    self.floor_num = depth;
+
+    // Phantoms stay behind with the floor they haunted
+    self.phantoms.clear();
 
     self.tcod_map = World::new_tcod_map(self.floor.dun.get_bounds_pos(), &self.floor.dun);
 
-    let start_loc = Dungeon::get_valid_location(&self.floor.dun.grid);
+    // The index isn't saved along with floors in the stack
+    self.floor.reindex(None);
+    let start_loc = Dungeon::get_valid_location(&self.floor.dun.grid, &self.floor.occupancy, &mut self.rng);
     self.player.actor.pos.x = start_loc.x;
     self.player.actor.pos.y = start_loc.y;
+    self.floor.occupancy.place(start_loc, Occupant::Player);
 
     self.update_fov();
 
@@ -600,40 +1249,60 @@ impl World {
   ///
   /// Return a new `World`
   ///
-  pub fn new(map_dim: Pos) -> Self {
-
-    // Create a basic dungeon, tcod map from that dungeon, and a grid we can
-    // put test creatures on.
-    let dun = World::create_test_dungeon(map_dim);
-    let grid = dun.grid.clone();
-    let tcod_map =  World::new_tcod_map(map_dim, &dun);
+  /// * `seed` - Seed for the world's RNG. The same seed and the same player inputs always produce the same game
+  ///
+  pub fn new(map_dim: Pos, seed: usize) -> Self {
 
-    let mut floor = Floor::new(dun, World::create_test_creatures(&grid));
+    // Every random choice from here on out stems from this
+    let mut rng = StdRng::from_seed(&[seed][..]);
 
-    // Create n gold coins at a valid location
-    let gold_loc = Dungeon::get_valid_location(&floor.dun.grid);
-    floor.items.push(
-      Item::new("gold piece", '$', gold_loc, RGB(238, 232, 170), RGB(0, 0, 0), rand::thread_rng().gen_range(10, 40), ItemProperty::Money(Money::Gold))
-    );
+    // Create a basic dungeon, tcod map from that dungeon, and a grid we can
+    // put creatures on.
+    let floor = World::create_floor(map_dim, DungeonKind::Main, 0, &mut rng);
+    let tcod_map =  World::new_tcod_map(map_dim, &floor.dun);
 
     let mut floor_stack = Vec::new();
     floor_stack.push(floor.clone());
 
+    let character = Character::default();
+
     let mut world = World {
-      player: World::new_player(),
+      player: World::new_player(&character),
+      character,
       floor,
       floor_stack,
       floor_num: 0,
-      tcod_map
+      branch: DungeonKind::Main,
+      turn: 0,
+      cause_of_death: None,
+      phantoms: vec![],
+      tcod_map,
+      seed,
+      rng
     };
 
-    world.player.actor.pos = Dungeon::get_valid_location(&world.floor.dun.grid);
+    world.player.actor.pos = Dungeon::get_valid_location(&world.floor.dun.grid, &world.floor.occupancy, &mut world.rng);
+    world.floor.occupancy.place(world.player.actor.pos, Occupant::Player);
     world.update_fov();
 
     return world;
 
   }
 
+  ///
+  /// Remake the player from a `Character`
+  ///
+  /// Making the player doesn't roll any dice, so a world made from a seed stays the same whoever plays it. This is
+  /// meant to be done before the first turn, since the player starts over with nothing but where they're standing
+  ///
+  pub fn set_character(&mut self, character: Character) {
+    let pos = self.player.actor.pos;
+    self.player = World::new_player(&character);
+    self.player.actor.pos = pos;
+    self.character = character;
+    self.update_fov();
+  }
+
   // Get a mutable reference to a tile at a point on the current dungeon
   pub fn get_mut_tile_at(&mut self, x: isize, y: isize) -> &mut Tile {
     &mut self.floor.dun[x as usize][y as usize]
@@ -655,6 +1324,8 @@ impl World {
     match &self.player.state {
       Actions::Move => sounds.push((self.player.actor.pos, self.player.stats.weight)),
       Actions::Talk => sounds.push((self.player.actor.pos, 25)),
+      Actions::Attack | Actions::Throw => sounds.push((self.player.actor.pos, 30)),
+      Actions::Open | Actions::Close => sounds.push((self.player.actor.pos, DOOR_SOUND)),
       _ => {}
     }
 
@@ -663,6 +1334,8 @@ impl World {
       match &creature.state {
         Actions::Move => sounds.push((creature.actor.pos, creature.stats.weight)),
         Actions::Talk => sounds.push((creature.actor.pos, 25)),
+        Actions::Attack => sounds.push((creature.actor.pos, 30)),
+        Actions::Open | Actions::Close => sounds.push((creature.actor.pos, DOOR_SOUND)),
         _ => {}
       }
     }
@@ -680,15 +1353,18 @@ impl World {
     let player_x = self.player.actor.pos.x;
     let player_y = self.player.actor.pos.y;
 
+    // Unless something is covering it up
+    let masked = self.player.statuses.has(Effect::Masked);
+
+    // Which scent that is depends on the player's race
+    let player_scent = self.player.stats.scent_type.clone();
+
     for x in SC_DIAM_LOWER..SC_DIAM_UPPER {
       for y in SC_DIAM_LOWER..SC_DIAM_UPPER {
-        if self.is_valid_pos(player_x - x, player_y - y) {
+        if !masked && self.is_valid_pos(player_x - x, player_y - y) {
           for scent in &mut self.get_mut_tile_at(player_x - x, player_y - y).scents {
-            match scent.scent_type {
-              tile::Scent::Player => {
-                scent.val = SC_INC
-              }
-              _ => {}
+            if scent.scent_type == player_scent {
+              scent.val = SC_INC
             }
           }
         }
@@ -697,13 +1373,20 @@ impl World {
 
     // Save information about creatures
     let mut creature_information = vec![];
-    for creature in &self.floor.creatures {
+    for creature in self.floor.creatures.iter().filter(|creature| !creature.statuses.has(Effect::Masked)) {
       let creature_x = creature.actor.pos.x;
       let creature_y = creature.actor.pos.y;
       let scent_type = creature.stats.scent_type.clone();
       creature_information.push((creature_x, creature_y, scent_type));
     }
 
+    // Some items give off scents of their own, like burning incense
+    for item in &self.floor.items {
+      if let ItemProperty::Scented(ref scent_type) = item.property {
+        creature_information.push((item.pos.x, item.pos.y, scent_type.clone()));
+      }
+    }
+
     // For tuple in creature information
     for tuple in &creature_information {
       // Unpack
@@ -806,16 +1489,29 @@ impl World {
         }
     }
 
+    // Doors that aren't open get in the way of sound
+    let shut = |t: &Tile| match t.tiletype {
+      tile::Type::Door(ref door) => *door != tile::Door::Open,
+      _ => false
+    };
+
     // Expand each sound point-source
     // Sound decreases in intensity proportional
     // to the inverse of distance squared
     for sound in sounds {
       for x in 0..self.floor.dun.width {
         for y in 0..self.floor.dun.height {
-          let mut tile = self.get_mut_tile_at(x as isize, y as isize);
-          if true {
-            tile.sound += sound.1 / ((dist(sound.0, x as isize, y as isize) + 1).pow(2));
+          let mut volume = sound.1 / ((dist(sound.0, x as isize, y as isize) + 1).pow(2));
+          // Every closed door between the source and the tile muffles the sound. Sounds that have already died out
+          // don't need checking
+          if volume > 0 {
+            let line = fov::line(sound.0, Pos::from_usize(x, y));
+            if line.len() > 2 {
+              let doors = line[1..line.len() - 1].iter().filter(|pos| shut(&self.floor.dun[**pos])).count();
+              volume /= DOOR_MUFFLE.pow(doors as u32);
+            }
           }
+          self.get_mut_tile_at(x as isize, y as isize).sound += volume;
         }
       }
     }
@@ -824,18 +1520,536 @@ impl World {
   ///
   /// Update the fov map from the player's perspective
   /// 
+  /// How far the player can see comes from their perception. tcod takes a radius of 0 to mean no limit at all, so the
+  /// player always sees at least what's right next to them
+  /// 
   pub fn update_fov(&mut self) {
-    self.tcod_map.compute_fov(self.player.actor.pos.x as i32, self.player.actor.pos.y as i32, FOV_DIAMETER, true, FovAlgorithm::Shadow);
+    let radius = cmp::max(self.player.sight_radius(), 1) as i32;
+    self.tcod_map.compute_fov(self.player.actor.pos.x as i32, self.player.actor.pos.y as i32, radius, true, FovAlgorithm::Shadow);
+  }
+
+  ///
+  /// Get what a creature can see of the other creatures on the floor
+  ///
+  fn sightings(creatures: &[Creature], idx: usize, vision: &fov::Vision) -> Vec<ai::Sighting> {
+    creatures.iter().enumerate()
+      .filter(|&(id, other)| id != idx && other.state != Actions::Die && vision.can_see(other.actor.pos))
+      .map(|(id, other)| ai::Sighting {
+        id,
+        name: other.actor.get_id(),
+        pos: other.actor.pos,
+        scent: other.stats.scent_type.clone(),
+        faction: other.faction.0,
+        state: other.state.clone()
+      })
+      .collect()
+  }
+
+  ///
+  /// Have a creature attack the player
+  ///
+  fn creature_attack_player(&mut self, idx: usize) {
+
+    let creature = &mut self.floor.creatures[idx];
+    creature.state = Actions::Attack;
+
+    let name = creature.actor.get_id();
+
+    match combat::melee(creature, &mut self.player, &mut self.rng) {
+      Hit::Miss => log!(format!("The {} misses you", name), RGB(150, 150, 150)),
+      Hit::Hit(_) => log!(format!("The {} hits you", name), RGB(255, 100, 100)),
+      Hit::Kill(_) => {
+        log!(format!("The {} kills you!", name), RGB(255, 0, 0));
+        self.cause_of_death = Some(format!("Killed by a {}", name));
+        self.floor.dun.add_blood(self.player.actor.pos, &mut self.rng);
+      }
+    }
+
+  }
+
+  ///
+  /// Have a creature attack another creature
+  ///
+  /// The player only hears about fights they can see
+  ///
+  fn creature_attack_creature(&mut self, idx: usize, target: usize) {
+
+    self.floor.creatures[idx].state = Actions::Attack;
+
+    // Borrow both creatures at once
+    let (attacker, defender) = if idx < target {
+      let (left, right) = self.floor.creatures.split_at_mut(target);
+      (&left[idx], &mut right[0])
+    } else {
+      let (left, right) = self.floor.creatures.split_at_mut(idx);
+      (&right[0], &mut left[target])
+    };
+
+    let (name, defender_name, pos) = (attacker.actor.get_id(), defender.actor.get_id(), defender.actor.pos);
+    let seen = self.tcod_map.is_in_fov(pos.x as i32, pos.y as i32);
+
+    match combat::melee(attacker, defender, &mut self.rng) {
+      Hit::Miss => if seen {
+        log!(format!("The {} misses the {}", name, defender_name), RGB(150, 150, 150));
+      },
+      Hit::Hit(_) => if seen {
+        log!(format!("The {} hits the {}", name, defender_name), RGB(200, 200, 200));
+      },
+      Hit::Kill(_) => {
+        if seen {
+          log!(format!("The {} kills the {}!", name, defender_name), RGB(255, 150, 0));
+        }
+        self.floor.dun.add_blood(pos, &mut self.rng);
+        self.floor.occupancy.remove(pos);
+      }
+    }
+
+  }
+
+  ///
+  /// Carry out what a creature's AI decided to do
+  ///
+  /// `from` is where the creature stood before its AI ran. Anything that can't be done, like attacking something out of
+  /// reach or walking into a wall of other creatures, turns into waiting
+  ///
+  fn resolve_intent(&mut self, idx: usize, from: Pos, intent: ai::Intent) {
+
+    // Only plain actions get to move a creature by themselves
+    match intent {
+      ai::Intent::Act(_) => (),
+      _ => self.floor.creatures[idx].actor.pos = from
+    }
+
+    // Confused creatures don't always end up where they meant to
+    if let ai::Intent::Act(Actions::Move) = intent {
+      if self.floor.creatures[idx].statuses.has(Effect::Confused) {
+        let (to, costs) = (self.floor.creatures[idx].actor.pos, self.floor.creatures[idx].stats.movement);
+        self.floor.creatures[idx].actor.pos = self.stumble(from, to, &costs);
+      }
+    }
+
+    let pos = self.floor.creatures[idx].actor.pos;
+
+    // Where a target is, if it's still standing
+    let target_pos = |world: &World, target: ai::Target| match target {
+      ai::Target::Player => if world.player.state != Actions::Die { Some(world.player.actor.pos) } else { None },
+      ai::Target::Creature(id) => world.floor.creatures.get(id)
+        .filter(|creature| id != idx && creature.state != Actions::Die)
+        .map(|creature| creature.actor.pos)
+    };
+
+    match intent {
+
+      ai::Intent::Act(action) => {
+
+        self.floor.creatures[idx].state = action;
+
+        // Walking into a closed door opens it, for creatures that know how. Everything else is stuck
+        if self.floor.creatures[idx].state == Actions::Move && self.door_at(pos) == Some(tile::Door::Closed) {
+          self.floor.creatures[idx].actor.pos = from;
+          if self.floor.creatures[idx].stats.movement.door.is_some() {
+            if self.tcod_map.is_in_fov(pos.x as i32, pos.y as i32) {
+              let name = self.floor.creatures[idx].actor.get_id();
+              log!(format!("The {} opens a door", name), RGB(200, 200, 200));
+            }
+            self.set_door(pos, "open_door");
+            self.floor.creatures[idx].state = Actions::Open;
+          } else {
+            self.floor.creatures[idx].state = Actions::Wait;
+          }
+          return;
+        }
+
+        match self.floor.occupancy.get(pos) {
+
+          // Nothing gets to stand on the player. Walking into them is an attack if the creature is hostile to them,
+          // anything else (like blinking) just fails
+          Some(Occupant::Player) => {
+            self.floor.creatures[idx].actor.pos = from;
+            let hostile = FACTIONS.is_hostile(self.floor.creatures[idx].faction.0, self.player.faction.0);
+            if self.floor.creatures[idx].state == Actions::Move && hostile && self.player.state != Actions::Die {
+              self.creature_attack_player(idx);
+            } else {
+              self.floor.creatures[idx].state = Actions::Wait;
+            }
+          },
+
+          // Walking into an enemy is an attack, and creatures of a kind shuffle past each other. Anything else is in the way
+          Some(Occupant::Creature(other)) if other != idx => {
+            let hostile = FACTIONS.is_hostile(self.floor.creatures[idx].faction.0, self.floor.creatures[other].faction.0);
+            let same_kind = self.floor.creatures[other].actor.get_id() == self.floor.creatures[idx].actor.get_id();
+            if self.floor.creatures[idx].state == Actions::Move && hostile {
+              self.floor.creatures[idx].actor.pos = from;
+              self.creature_attack_creature(idx, other);
+            } else if self.floor.creatures[idx].state == Actions::Move && same_kind {
+              let mate = &mut self.floor.creatures[other];
+              mate.actor.prev_pos = pos;
+              mate.actor.pos = from;
+              self.floor.occupancy.place(from, Occupant::Creature(other));
+              self.floor.occupancy.place(pos, Occupant::Creature(idx));
+            } else {
+              self.floor.creatures[idx].actor.pos = from;
+              self.floor.creatures[idx].state = Actions::Wait;
+            }
+          },
+
+          _ => self.floor.occupancy.move_occupant(from, pos)
+
+        }
+
+      },
+
+      // ^ is overridden to be the distance formula, this isn't xor
+      ai::Intent::Attack(target) => match target_pos(self, target) {
+        Some(tpos) if (pos ^ tpos) < 2.0 => match target {
+          ai::Target::Player => self.creature_attack_player(idx),
+          ai::Target::Creature(id) => self.creature_attack_creature(idx, id)
+        },
+        _ => self.floor.creatures[idx].state = Actions::Wait
+      },
+
+      ai::Intent::Pickup => {
+
+        let mut picked_up = false;
+        let mut left = vec![];
+
+        for item in self.floor.items.drain(..) {
+          let creature = &mut self.floor.creatures[idx];
+          if item.pos != pos || !creature.can_carry(&item) || !creature.has_room(&item) {
+            left.push(item);
+            continue;
+          }
+          picked_up = true;
+          match item.property {
+            ItemProperty::Money(ref tender) => creature.wallet += money_value(tender) * item.quantity as f32,
+            _ => creature.stash(item)
+          }
+        }
+
+        self.floor.items = left;
+        self.floor.creatures[idx].state = if picked_up { Actions::Pickup } else { Actions::Wait };
+
+      },
+
+      // Take whatever step puts the most distance between the creature and the target
+      ai::Intent::Flee(target) => {
+
+        let step = target_pos(self, target).and_then(|tpos| {
+          let mut best = (pos, pos ^ tpos);
+          for dx in -1..2 {
+            for dy in -1..2 {
+              let next = Pos::new(pos.x + dx, pos.y + dy);
+              if self.is_valid_pos(next.x, next.y) && !self.floor.occupancy.is_occupied(next) && (next ^ tpos) > best.1 {
+                best = (next, next ^ tpos);
+              }
+            }
+          }
+          if best.0 != pos { Some(best.0) } else { None }
+        });
+
+        let creature = &mut self.floor.creatures[idx];
+        match step {
+          Some(next) => {
+            creature.actor.prev_pos = pos;
+            creature.actor.pos = next;
+            creature.state = Actions::Move;
+            self.floor.occupancy.move_occupant(pos, next);
+          },
+          None => creature.state = Actions::Wait
+        }
+
+      }
+
+    }
+
+  }
+
+  ///
+  /// Work out where a confused creature that meant to go from `from` to `to` ends up
+  ///
+  /// Some of the time it stumbles off in a random direction instead, and goes nowhere if something solid is in the way
+  /// or it would end up somewhere its `costs` don't let it walk
+  ///
+  pub fn stumble(&mut self, from: Pos, to: Pos, costs: &path::Costs) -> Pos {
+
+    if self.rng.gen_range(0, 100) >= STUMBLE_CHANCE {
+      return to;
+    }
+
+    let pos = Pos::new(from.x + self.rng.gen_range(-1, 2), from.y + self.rng.gen_range(-1, 2));
+    if self.is_valid_pos(pos.x, pos.y) && costs.cost(&self.floor.dun[pos]).is_some() {
+      return pos;
+    }
+
+    return from;
+
+  }
+
+  ///
+  /// Check if there's enough incense or smoke on a tile to mask the scent of whatever stands there
+  ///
+  fn masks_scent(&self, pos: Pos) -> bool {
+    let scents = &self.floor.dun[pos].scents;
+    scents[tile::Scent::Incense as usize].val >= MASK_SCENT || scents[tile::Scent::Smoke as usize].val >= MASK_SCENT
+  }
+
+  ///
+  /// Let a turn pass for everyone's status effects
+  ///
+  /// This is where effects that do something every turn happen. Only the player hears about their effects wearing off
+  ///
+  fn tick_statuses(&mut self) {
+
+    if self.player.state != Actions::Die {
+
+      let pos = self.player.actor.pos;
+
+      if self.masks_scent(pos) {
+        self.player.statuses.apply(Effect::Masked, MASK_TURNS);
+      }
+
+      if self.player.statuses.has(Effect::Bleeding) {
+        self.player.stats.health_points -= BLEED_DAMAGE;
+        self.floor.dun.add_blood(pos, &mut self.rng);
+        if self.player.stats.health_points <= 0 {
+          log!("You bleed to death!", RGB(255, 0, 0));
+          self.player.state = Actions::Die;
+          self.cause_of_death = Some("Bled to death".to_string());
+        }
+      }
+
+      for effect in self.player.statuses.tick() {
+        log!(format!("You are no longer {}", effect.name()), RGB(150, 150, 150));
+      }
+
+    }
+
+    for idx in 0..self.floor.creatures.len() {
+
+      if self.floor.creatures[idx].state == Actions::Die {
+        continue;
+      }
+
+      let pos = self.floor.creatures[idx].actor.pos;
+
+      if self.masks_scent(pos) {
+        self.floor.creatures[idx].statuses.apply(Effect::Masked, MASK_TURNS);
+      }
+
+      if self.floor.creatures[idx].statuses.has(Effect::Bleeding) {
+        self.floor.creatures[idx].stats.health_points -= BLEED_DAMAGE;
+        self.floor.dun.add_blood(pos, &mut self.rng);
+        if self.floor.creatures[idx].stats.health_points <= 0 {
+          if self.tcod_map.is_in_fov(pos.x as i32, pos.y as i32) {
+            let name = self.floor.creatures[idx].actor.get_id();
+            log!(format!("The {} bleeds to death", name), RGB(200, 200, 200));
+          }
+          self.floor.creatures[idx].state = Actions::Die;
+          self.floor.occupancy.remove(pos);
+        }
+      }
+
+      self.floor.creatures[idx].statuses.tick();
+
+    }
+
+  }
+
+  ///
+  /// Check if any tile within `radius` of `pos` passes a test
+  ///
+  fn is_near<F: Fn(&Tile) -> bool>(&self, pos: Pos, radius: isize, test: F) -> bool {
+    for x in (pos.x - radius)..(pos.x + radius + 1) {
+      for y in (pos.y - radius)..(pos.y + radius + 1) {
+        if x >= 0 && y >= 0 && (x as usize) < self.floor.dun.width && (y as usize) < self.floor.dun.height
+          && test(&self.floor.dun[x as usize][y as usize]) {
+          return true;
+        }
+      }
+    }
+    return false;
+  }
+
+  ///
+  /// Let a turn pass for the player's mind
+  ///
+  /// Corruption, crypts and dreadful creatures in sight wear sanity down, while resting or sitting by a fountain builds
+  /// it back up. The less there is left, the more the player hears and sees things that aren't there
+  ///
+  fn tick_sanity(&mut self) {
+
+    if self.player.state == Actions::Die {
+      return;
+    }
+
+    let pos = self.player.actor.pos;
+    let mut change = 0;
+
+    if self.turn % CORRUPTION_DRAIN_TURNS == 0 && self.is_near(pos, SANITY_RADIUS, |tile| tile.corrupted) {
+      change -= 1;
+    }
+
+    if self.turn % CRYPT_DRAIN_TURNS == 0 && self.floor.dun[pos].biome == tile::Biome::Crypt {
+      change -= 1;
+    }
+
+    if self.turn % DREAD_DRAIN_TURNS == 0 {
+      change -= self.floor.creatures.iter()
+        .filter(|creature| creature.state != Actions::Die)
+        .filter(|creature| self.tcod_map.is_in_fov(creature.actor.pos.x as i32, creature.actor.pos.y as i32))
+        .map(|creature| creature.stats.dread)
+        .sum::<isize>();
+    }
+
+    if self.player.state == Actions::Wait {
+      change += REST_SANITY;
+    }
+
+    if self.is_near(pos, SANITY_RADIUS, |tile| tile.tiletype == tile::Type::ArtStructure) {
+      change += FOUNTAIN_SANITY;
+    }
+
+    let before = Madness::of(&self.player.stats);
+    let points = self.player.stats.sanity_points + change;
+    self.player.stats.sanity_points = cmp::max(0, cmp::min(points, self.player.stats.max_sanity()));
+    let madness = Madness::of(&self.player.stats);
+
+    if madness > before {
+      log!("Your grip on reality slips", madness.color());
+    } else if madness < before {
+      log!("Your head clears a little", RGB(150, 150, 150));
+    }
+
+    if madness >= Madness::Hallucinating && self.rng.gen_range(0, WHISPER_CHANCE) == 0 {
+      log!(sanity::whisper(&mut self.rng), RGB(200, 200, 200));
+    }
+
+    self.tick_phantoms(madness);
+
+  }
+
+  ///
+  /// Let a turn pass for phantoms
+  ///
+  /// Phantoms drift towards the player, and fade once their time is up or the player gets right next to one. A mind
+  /// that's lost keeps conjuring more of them
+  ///
+  fn tick_phantoms(&mut self, madness: Madness) {
+
+    let player = self.player.actor.pos;
+
+    for idx in 0..self.phantoms.len() {
+      let from = self.phantoms[idx].actor.pos;
+      // Half of the time they stalk the player, the other half they wander
+      let to = if self.rng.gen_range(0, 2) == 0 {
+        Pos::new(from.x + (player.x - from.x).signum(), from.y + (player.y - from.y).signum())
+      } else {
+        Pos::new(from.x + self.rng.gen_range(-1, 2), from.y + self.rng.gen_range(-1, 2))
+      };
+      if self.is_valid_pos(to.x, to.y) {
+        self.phantoms[idx].actor.pos = to;
+      }
+      self.phantoms[idx].turns -= 1;
+    }
+
+    for phantom in &self.phantoms {
+      let pos = phantom.actor.pos;
+      if (pos ^ player) < 2.0 {
+        log!(format!("The {} was never there", phantom.actor.get_id()), RGB(150, 150, 150));
+      } else if phantom.turns == 0 && self.tcod_map.is_in_fov(pos.x as i32, pos.y as i32) {
+        log!(format!("The {} fades away", phantom.actor.get_id()), RGB(150, 150, 150));
+      }
+    }
+    self.phantoms.retain(|phantom| phantom.turns > 0 && (phantom.actor.pos ^ player) >= 2.0);
+
+    if madness == Madness::Lost && self.phantoms.len() < MAX_PHANTOMS && self.rng.gen_range(0, PHANTOM_CHANCE) == 0 {
+      let pos = Pos::new(
+        player.x + self.rng.gen_range(-PHANTOM_RADIUS, PHANTOM_RADIUS + 1),
+        player.y + self.rng.gen_range(-PHANTOM_RADIUS, PHANTOM_RADIUS + 1)
+      );
+      if self.is_valid_pos(pos.x, pos.y) && !self.floor.occupancy.is_occupied(pos) && (pos ^ player) >= 2.0 {
+        if let Some(phantom) = Phantom::conjure(pos, self.floor.dun.kind, self.floor.dun.depth, &mut self.rng) {
+          self.phantoms.push(phantom);
+        }
+      }
+    }
+
+  }
+
+  ///
+  /// Let one tick of time pass, where everything regains energy and creatures with enough of it act
+  ///
+  fn tick(&mut self) {
+
+    self.turn += 1;
+
+    self.tick_statuses();
+    self.tick_sanity();
+
+    self.player.energy.gain(self.player.speed());
+
+    for idx in 0..self.floor.creatures.len() {
+
+      // The dead don't get a turn
+      if self.floor.creatures[idx].state == Actions::Die {
+        continue;
+      }
+
+      let speed = self.floor.creatures[idx].speed();
+      self.floor.creatures[idx].energy.gain(speed);
+
+      // Fast creatures can act more than once per tick. Dying is free, so the dead have to be stopped here
+      while self.floor.creatures[idx].energy.ready() && self.floor.creatures[idx].state != Actions::Die {
+
+        let from = self.floor.creatures[idx].actor.pos;
+
+        let intent = {
+
+          // Creatures work out what they see for themselves, so that the player's FOV in `tcod_map` is left alone
+          let vision = {
+            let me = &self.floor.creatures[idx];
+            fov::Vision::new(&self.floor.dun.grid, me.actor.pos, me.sight_radius())
+          };
+
+          let ctx = ai::Context {
+            faction: self.floor.creatures[idx].faction.0,
+            map: &self.floor.dun.grid,
+            player: &self.player,
+            creatures: World::sightings(&self.floor.creatures, idx, &vision),
+            items: &self.floor.items,
+            occupancy: &self.floor.occupancy,
+            vision,
+            turn: self.turn,
+            depth: self.floor.dun.depth
+          };
+
+          self.floor.creatures[idx].take_turn(&ctx, &mut self.floor.paths, &mut self.rng)
+
+        };
+
+        self.resolve_intent(idx, from, intent);
+
+        let creature = &mut self.floor.creatures[idx];
+        creature.energy.spend(&creature.state);
+
+      }
+
+    }
+
   }
 
   ///
   /// Update the game world
   ///
+  /// The player has just acted, so they pay for it and then time passes until they can act again
+  ///
   pub fn update(&mut self) {
+    self.search_secret_doors();
     self.update_fov();
     self.update_scent();
-    for creature in &mut self.floor.creatures {
-      creature.take_turn(&self.floor.dun.grid, &self.player)
+    self.player.energy.spend(&self.player.state);
+    while !self.player.energy.ready() {
+      self.tick();
     }
     self.check_traps();
     self.check_items();
@@ -0,0 +1,154 @@
+//!
+//! Sanity, and what happens to the player's mind as it runs out
+//!
+//! The world decides when sanity is lost or regained. This only decides how far gone a mind is and what it starts
+//! making up because of it: things on screen that look wrong, messages about things that never happened, and phantoms
+//! that nobody else can see.
+//!
+
+extern crate rand;
+use self::rand::{Rng, StdRng};
+
+use core::creature::{Actor, Stats};
+use core::renderer::RGB;
+use core::world::dungeon::DungeonKind;
+use core::world::dungeon::map::Pos;
+
+///
+/// Configuration
+///
+
+// Percent of sanity left at or below which each level of madness sets in
+const SHAKEN_PERCENT : isize = 50;
+const HALLUCINATING_PERCENT : isize = 30;
+const LOST_PERCENT : isize = 15;
+
+// How many turns a phantom lasts before it fades
+const PHANTOM_TURNS : usize = 20;
+
+// Things a mind that's going hears, sees and feels
+const WHISPERS : [&str; 8] = [
+  "You hear footsteps behind you",
+  "Something whispers your name",
+  "The walls are breathing",
+  "Something brushes past you",
+  "A door slams somewhere nearby",
+  "The floor tilts beneath you",
+  "Someone is laughing",
+  "You feel watched"
+];
+
+///
+/// How far gone a mind is, from not at all to completely
+///
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Madness {
+  // Nothing out of the ordinary
+  Sane,
+  // Things on screen start to look wrong
+  Shaken,
+  // Along with hearing things that aren't there
+  Hallucinating,
+  // Along with seeing phantoms
+  Lost
+}
+
+impl Madness {
+
+  ///
+  /// Work out how far gone a mind is from how much sanity it has left
+  ///
+  pub fn of(stats: &Stats) -> Madness {
+
+    let max = stats.max_sanity();
+
+    // Nothing to lose
+    if max == 0 {
+      return Madness::Sane;
+    }
+
+    let percent = stats.sanity_points * 100 / max;
+
+    if percent <= LOST_PERCENT {
+      Madness::Lost
+    } else if percent <= HALLUCINATING_PERCENT {
+      Madness::Hallucinating
+    } else if percent <= SHAKEN_PERCENT {
+      Madness::Shaken
+    } else {
+      Madness::Sane
+    }
+
+  }
+
+  ///
+  /// Name of the level of madness, as shown next to sanity
+  ///
+  pub fn name(&self) -> &'static str {
+    match *self {
+      Madness::Sane          => "sane",
+      Madness::Shaken        => "shaken",
+      Madness::Hallucinating => "hallucinating",
+      Madness::Lost          => "lost"
+    }
+  }
+
+  ///
+  /// Color the level of madness is shown in
+  ///
+  pub fn color(&self) -> RGB {
+    match *self {
+      Madness::Sane          => RGB(255, 255, 255),
+      Madness::Shaken        => RGB(200, 150, 255),
+      Madness::Hallucinating => RGB(170, 80, 255),
+      Madness::Lost          => RGB(130, 0, 200)
+    }
+  }
+
+  ///
+  /// Chance out of 100 for anything on screen to look like something it isn't
+  ///
+  pub fn hallucination_chance(&self) -> u32 {
+    match *self {
+      Madness::Sane          => 0,
+      Madness::Shaken        => 2,
+      Madness::Hallucinating => 5,
+      Madness::Lost          => 10
+    }
+  }
+
+}
+
+///
+/// Get something to hear that isn't there
+///
+pub fn whisper(rng: &mut StdRng) -> &'static str {
+  *rng.choose(&WHISPERS).unwrap()
+}
+
+///
+/// A creature that only the player can see
+///
+/// Phantoms aren't on the floor as far as anything else is concerned, so they can't block, be smelled or be hit.
+/// They fade after a while, or as soon as the player gets close enough to see through them
+///
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Phantom {
+  pub actor: Actor,
+  // Turns left before it fades
+  pub turns: usize
+}
+
+impl Phantom {
+
+  ///
+  /// Conjure up a phantom at `pos` that looks like something that lives in a branch at a depth
+  ///
+  pub fn conjure(pos: Pos, kind: DungeonKind, depth: usize, rng: &mut StdRng) -> Option<Phantom> {
+    kind.choose_monster(depth, rng).map(|monster| Phantom {
+      actor: Actor::new(monster.name, monster.glyph, pos, monster.fg, monster.bg),
+      turns: PHANTOM_TURNS
+    })
+  }
+
+}
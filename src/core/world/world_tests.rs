@@ -0,0 +1,213 @@
+#[cfg(test)]
+mod tests {
+
+  extern crate rand;
+  use self::rand::{SeedableRng, StdRng};
+
+  use core::creature::{ai, Actions, Creature, Effect, Stats, MONSTERS};
+  use core::world::World;
+  use core::world::combat::{self, Hit};
+  use core::world::dungeon::map::{tile, Occupant, Pos};
+  use core::world::sanity::Madness;
+
+  ///
+  /// Make a world with nothing on an open floor but the player, who stands in the middle
+  ///
+  fn empty_world() -> World {
+    let mut world = World::new(Pos::new(80, 50), 1337);
+    world.test_empty();
+    return world;
+  }
+
+  ///
+  /// Put a monster on the floor, returning its index
+  ///
+  fn add(world: &mut World, id: &str, pos: Pos) -> usize {
+    let idx = world.floor.creatures.len();
+    world.floor.creatures.push(MONSTERS.get(id).unwrap().create(pos));
+    world.floor.occupancy.place(pos, Occupant::Creature(idx));
+    return idx;
+  }
+
+  ///
+  /// Make a monster that isn't on any floor
+  ///
+  fn monster(id: &str) -> Creature {
+    MONSTERS.get(id).unwrap().create(Pos::new(0, 0))
+  }
+
+  ///
+  /// Shoot at `defender` until something connects
+  ///
+  fn shoot(defender: &mut Creature, max_damage: isize, rng: &mut StdRng) -> Hit {
+    let attacker = monster("ant");
+    loop {
+      match combat::ranged(&attacker, defender, max_damage, 1, rng) {
+        Hit::Miss => continue,
+        hit => return hit
+      }
+    }
+  }
+
+  ///
+  /// Have a creature try to walk from where it is to `to`
+  ///
+  fn walk(world: &mut World, idx: usize, to: Pos) {
+    let from = world.floor.creatures[idx].actor.pos;
+    world.floor.creatures[idx].actor.pos = to;
+    world.resolve_intent(idx, from, ai::Intent::Act(Actions::Move));
+  }
+
+  //
+  // Occupancy
+  //
+
+  #[test]
+  fn test_same_kind_swap() {
+
+    let mut world = empty_world();
+    let (a, b) = (Pos::new(5, 5), Pos::new(6, 5));
+    let first = add(&mut world, "ant", a);
+    let second = add(&mut world, "ant", b);
+
+    walk(&mut world, first, b);
+
+    assert_eq!(world.floor.creatures[first].actor.pos, b);
+    assert_eq!(world.floor.creatures[second].actor.pos, a);
+    assert_eq!(world.floor.occupancy.get(a), Some(Occupant::Creature(second)));
+    assert_eq!(world.floor.occupancy.get(b), Some(Occupant::Creature(first)));
+
+  }
+
+  #[test]
+  fn test_other_kind_blocks() {
+
+    // Ants and bees don't mind each other, but they don't swap either
+    let mut world = empty_world();
+    let (a, b) = (Pos::new(5, 5), Pos::new(6, 5));
+    let ant = add(&mut world, "ant", a);
+    let bee = add(&mut world, "bee", b);
+
+    walk(&mut world, ant, b);
+
+    assert_eq!(world.floor.creatures[ant].actor.pos, a);
+    assert_eq!(world.floor.creatures[ant].state, Actions::Wait);
+    assert_eq!(world.floor.creatures[bee].actor.pos, b);
+    assert_eq!(world.floor.occupancy.get(a), Some(Occupant::Creature(ant)));
+    assert_eq!(world.floor.occupancy.get(b), Some(Occupant::Creature(bee)));
+
+  }
+
+  #[test]
+  fn test_death_shifts_occupancy() {
+
+    let mut world = empty_world();
+    let (a, b, c) = (Pos::new(5, 5), Pos::new(7, 5), Pos::new(9, 5));
+    add(&mut world, "ant", a);
+    let dead = add(&mut world, "ant", b);
+    add(&mut world, "ant", c);
+
+    world.floor.creatures[dead].state = Actions::Die;
+    world.check_death();
+
+    // The last ant moved down in the list, and the index has to follow it
+    assert_eq!(world.floor.creatures.len(), 2);
+    assert_eq!(world.floor.occupancy.get(a), Some(Occupant::Creature(0)));
+    assert_eq!(world.floor.occupancy.get(b), None);
+    assert_eq!(world.floor.occupancy.get(c), Some(Occupant::Creature(1)));
+    assert_eq!(world.floor.occupancy.get(world.player.actor.pos), Some(Occupant::Player));
+
+  }
+
+  //
+  // Combat
+  //
+
+  #[test]
+  fn test_hit_chance_clamp() {
+
+    let attacker = monster("ant");
+    let mut defender = monster("ant");
+
+    defender.stats.evasion_value = 100;
+    assert_eq!(combat::hit_chance(&attacker, &defender), combat::MIN_HIT_CHANCE);
+
+    defender.stats.evasion_value = -100;
+    assert_eq!(combat::hit_chance(&attacker, &defender), combat::MAX_HIT_CHANCE);
+
+    defender.stats.evasion_value = 0;
+    let chance = combat::hit_chance(&attacker, &defender);
+    assert!(chance > combat::MIN_HIT_CHANCE && chance < combat::MAX_HIT_CHANCE);
+
+  }
+
+  #[test]
+  fn test_armor_soaks_damage() {
+
+    let mut rng = StdRng::from_seed(&[1337][..]);
+    let mut defender = monster("ant");
+    defender.stats.armor_value = 10;
+    let health = defender.stats.health_points;
+
+    for _ in 0..20 {
+      assert_eq!(shoot(&mut defender, 10, &mut rng), Hit::Hit(0));
+    }
+    assert_eq!(defender.stats.health_points, health);
+    assert!(defender.state != Actions::Die);
+
+  }
+
+  #[test]
+  fn test_kill_at_zero() {
+
+    let mut rng = StdRng::from_seed(&[1337][..]);
+    let mut defender = monster("ant");
+    defender.stats.armor_value = 0;
+    defender.stats.health_points = 2;
+
+    // Left with 1 health, which is close enough to death to get hasted
+    assert_eq!(shoot(&mut defender, 1, &mut rng), Hit::Hit(1));
+    assert_eq!(defender.stats.health_points, 1);
+    assert!(defender.state != Actions::Die);
+    assert!(defender.statuses.has(Effect::Hasted));
+
+    // Exactly 0 is dead
+    assert_eq!(shoot(&mut defender, 1, &mut rng), Hit::Kill(1));
+    assert_eq!(defender.stats.health_points, 0);
+    assert_eq!(defender.state, Actions::Die);
+
+  }
+
+  //
+  // Sanity
+  //
+
+  #[test]
+  fn test_madness_thresholds() {
+
+    let mut stats = Stats::debug_new(20, tile::Scent::Player);
+    assert_eq!(stats.max_sanity(), 50);
+
+    // Each level sets in at or below its percent of 50, 30 and 15
+    for &(sanity, madness) in &[(50, Madness::Sane), (26, Madness::Sane), (25, Madness::Shaken), (16, Madness::Shaken),
+                                (15, Madness::Hallucinating), (8, Madness::Hallucinating), (7, Madness::Lost),
+                                (0, Madness::Lost), (-5, Madness::Lost)] {
+      stats.sanity_points = sanity;
+      assert_eq!(Madness::of(&stats), madness, "{} sanity", sanity);
+    }
+
+  }
+
+  #[test]
+  fn test_madness_without_sanity() {
+
+    // A mind with no sanity to lose can't lose it
+    let mut stats = Stats::debug_new(20, tile::Scent::Player);
+    stats.insight = -100;
+    stats.sanity_points = 0;
+    assert_eq!(stats.max_sanity(), 0);
+    assert_eq!(Madness::of(&stats), Madness::Sane);
+
+  }
+
+}
@@ -67,7 +67,10 @@ extern crate serde_derive;
 // For making images
 extern crate image;
 
-// Defer to game to start playing.
+// Defer to game to start playing, or to play back a replay if one was asked for.
 fn main() {
-  core::Engine::new().play();
+  match core::init::replay() {
+    Some(options) => core::Engine::watch(options),
+    None => core::Engine::new().play()
+  }
 }
\ No newline at end of file